@@ -0,0 +1,60 @@
+//! Run audio through the firmware's beat tracker the way the cube would hear it, for `cube_beats` and
+//! the beat tracking tests.
+
+use audio::{ AudioSource, Listener, BLOCK_US, FFT_SIZE };
+use wav::WavSource;
+
+/// Blocks between reports, about half a second
+pub const REPORT_BLOCKS: u32 = 32;
+
+/// What the tracker made of one stretch of audio
+pub struct Report {
+	/// Time since the start of the audio
+	pub time_us: u64,
+	pub bpm: u16,
+	pub count: u32,
+	pub phase: u8,
+	/// Kick drums heard since the last report
+	pub kicks: u32,
+}
+
+impl Report {
+	/// How far the beat clock is from beats at `bpm` starting at the beginning of the audio, out of 256
+	pub fn phase_error(&self, bpm: u16) -> u8 {
+		let beat_us = 60_000_000 / bpm.max(1) as u64;
+		let expected = (self.time_us % beat_us * 256 / beat_us) as u8;
+
+		self.phase.wrapping_sub(expected).min(expected.wrapping_sub(self.phase))
+	}
+}
+
+/// Run `samples` through a fresh tracker, reporting every `REPORT_BLOCKS` blocks
+pub fn track(samples: Vec<i16>) -> Vec<Report> {
+	let mut source = WavSource::new(samples);
+	let mut listener = Listener::new();
+	let mut block = [0i16; FFT_SIZE];
+	let mut reports = Vec::new();
+	let mut blocks = 0;
+	let mut kicks = 0;
+
+	while !source.is_finished() {
+		source.read(&mut block);
+		listener.listen(&block, BLOCK_US);
+		blocks += 1;
+
+		// The beat clock is read every block, like a pattern would each frame
+		let beat = listener.tracker.beat();
+
+		if beat.kick {
+			kicks += 1;
+		}
+
+		if blocks % REPORT_BLOCKS == 0 {
+			reports.push(Report { time_us: blocks as u64 * BLOCK_US as u64, bpm: beat.bpm, count: beat.count, phase: beat.phase, kicks });
+
+			kicks = 0;
+		}
+	}
+
+	reports
+}
@@ -0,0 +1,49 @@
+//! Run audio through the firmware's beat tracker.
+//!
+//! ```text
+//! cube_beats <in.wav>
+//! ```
+//!
+//! Prints the tempo and beat clock twice a second along with how many kick drums were heard. The host
+//! tests run generated drum loops with known tempos through the same tracker.
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use zinc_cube_host::beats;
+use zinc_cube_host::wav;
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_beats <in.wav>");
+
+	process::exit(1);
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	if args.len() != 1 {
+		usage();
+	}
+
+	let data = fs::read(&args[0]).unwrap_or_else(|e| {
+		eprintln!("Failed to read {}: {}", args[0], e);
+
+		process::exit(1);
+	});
+
+	let samples = wav::parse(&data).unwrap_or_else(|e| {
+		eprintln!("{} is not a usable WAV file: {}", args[0], e);
+
+		process::exit(1);
+	});
+
+	println!("    time  bpm  beat  phase  kicks");
+
+	for report in beats::track(samples) {
+		println!("{:>7}ms  {:>3}  {:>4}  {:>5}  {:>5}", report.time_us / 1000, report.bpm, report.count, report.phase, report.kicks);
+	}
+}
@@ -0,0 +1,74 @@
+//! Convert CSV or JSON frame lists into a recording or a Rust table the firmware can play.
+//!
+//! ```text
+//! cube_convert <input.csv|input.json> <output.zca|output.rs> [--name <CONST_NAME>]
+//! ```
+//!
+//! See `src/convert.rs` for the input formats.
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use zinc_cube_host::convert;
+use zinc_cube_host::recording;
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_convert <input.csv|input.json> <output.zca|output.rs> [--name <CONST_NAME>]");
+
+	process::exit(1);
+}
+
+fn fail(message: String) -> ! {
+	eprintln!("{}", message);
+
+	process::exit(1);
+}
+
+fn extension(path: &str) -> String {
+	Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase()
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	if args.len() != 2 && args.len() != 4 {
+		usage();
+	}
+
+	let input_path = &args[0];
+	let output_path = &args[1];
+
+	let name = if args.len() == 4 {
+		if args[2] != "--name" {
+			usage();
+		}
+
+		args[3].clone()
+	} else {
+		Path::new(output_path).file_stem().and_then(|s| s.to_str()).unwrap_or("ANIMATION").to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+	};
+
+	let text = fs::read_to_string(input_path).unwrap_or_else(|e| fail(format!("Failed to read {}: {}", input_path, e)));
+
+	let frames = match extension(input_path).as_str() {
+		"csv" => convert::parse_csv(&text),
+		"json" => convert::parse_json(&text),
+		_ => fail(format!("Don't know how to read {}; expected a .csv or .json file", input_path)),
+	}.unwrap_or_else(|e| fail(format!("{}: {}", input_path, e)));
+
+	let output = match extension(output_path).as_str() {
+		"zca" => recording::encode(&frames).unwrap_or_else(|e| fail(e)),
+		"rs" => convert::to_rust_table(&name, input_path, &frames).into_bytes(),
+		_ => fail(format!("Don't know how to write {}; expected a .zca or .rs file", output_path)),
+	};
+
+	if let Err(e) = fs::write(output_path, &output) {
+		fail(format!("Failed to write {}: {}", output_path, e));
+	}
+
+	println!("Converted {} frames from {} into {}", frames.len(), input_path, output_path);
+}
@@ -0,0 +1,81 @@
+//! Record a pattern from the simulator into the compact animation format read by `Playback`.
+//!
+//! ```text
+//! cube_record <pattern> <out.zca> [--cycles <n>] [--audio <in.wav>]
+//! ```
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::process;
+
+use zinc_cube_host::recording;
+use zinc_cube_host::sim::{ self, Simulator, PATTERNS };
+use zinc_cube_host::wav;
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_record <pattern> <out.zca> [--cycles <n>] [--audio <in.wav>]");
+	eprintln!("Patterns: {}", PATTERNS.join(", "));
+
+	process::exit(1);
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	if args.len() < 2 {
+		usage();
+	}
+
+	let pattern = &args[0];
+	let out_path = &args[1];
+
+	let mut cycles: u32 = 1;
+	let mut audio = None;
+
+	let mut options = args[2..].iter();
+
+	while let Some(option) = options.next() {
+		let value = options.next().unwrap_or_else(|| usage());
+
+		match option.as_str() {
+			"--cycles" => cycles = value.parse().unwrap_or_else(|_| usage()),
+			"--audio" => audio = Some(wav::load(value).unwrap_or_else(|e| {
+				eprintln!("{}", e);
+
+				process::exit(1);
+			})),
+			_ => usage(),
+		}
+	}
+
+	let mut cube = sim::cube();
+	let mut simulator = match audio {
+		Some(samples) => Simulator::with_audio(samples),
+		None => Simulator::new(),
+	};
+
+	for _ in 0..cycles {
+		if !sim::run_pattern(pattern, &mut cube, &mut simulator) {
+			eprintln!("Unknown pattern {}", pattern);
+
+			usage();
+		}
+	}
+
+	let encoded = recording::encode(&simulator.frames).unwrap_or_else(|e| {
+		eprintln!("{}", e);
+
+		process::exit(1);
+	});
+
+	if let Err(e) = File::create(out_path).and_then(|mut f| f.write_all(&encoded)) {
+		eprintln!("Failed to write {}: {}", out_path, e);
+
+		process::exit(1);
+	}
+
+	println!("Recorded {} frames of {} into {} ({} bytes)", simulator.frames.len(), pattern, out_path, encoded.len());
+}
@@ -0,0 +1,115 @@
+//! Render a pattern or recording to an animated GIF or a PNG sheet of layers.
+//!
+//! ```text
+//! cube_render <pattern|recording.zca> <out.gif|out.png> [--cycles <n>] [--every <n>] [--audio <in.wav>]
+//! ```
+//!
+//! `--cycles` runs a pattern more than once. `--every` only puts every nth frame in a PNG sheet,
+//! which otherwise gets very tall for long patterns. `--audio` plays a WAV file to the audio reactive
+//! patterns, which otherwise hear silence.
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use zinc_cube_host::recording;
+use zinc_cube_host::render;
+use zinc_cube_host::sim::{ self, Frame, Simulator, PATTERNS };
+use zinc_cube_host::wav;
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_render <pattern|recording.zca> <out.gif|out.png> [--cycles <n>] [--every <n>] [--audio <in.wav>]");
+	eprintln!("Patterns: {}", PATTERNS.join(", "));
+
+	process::exit(1);
+}
+
+fn fail(message: String) -> ! {
+	eprintln!("{}", message);
+
+	process::exit(1);
+}
+
+/// A count given to an option, which must be a positive number
+fn count(value: &str) -> usize {
+	value.parse().ok().filter(|v| *v > 0).unwrap_or_else(|| usage())
+}
+
+fn load_frames(source: &str, cycles: u32, audio: Option<Vec<i16>>) -> Vec<Frame> {
+	if source.ends_with(".zca") {
+		let data = fs::read(source).unwrap_or_else(|e| fail(format!("Failed to read {}: {}", source, e)));
+
+		return recording::decode(&data).unwrap_or_else(|e| fail(format!("{} is not a valid recording: {:?}", source, e)));
+	}
+
+	let mut cube = sim::cube();
+	let mut simulator = match audio {
+		Some(samples) => Simulator::with_audio(samples),
+		None => Simulator::new(),
+	};
+
+	for _ in 0..cycles {
+		if !sim::run_pattern(source, &mut cube, &mut simulator) {
+			eprintln!("Unknown pattern {}", source);
+
+			usage();
+		}
+	}
+
+	simulator.frames
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	if args.len() < 2 {
+		usage();
+	}
+
+	let source = &args[0];
+	let out_path = &args[1];
+
+	let mut cycles = 1;
+	let mut every = 1;
+	let mut audio = None;
+
+	let mut options = args[2..].iter();
+
+	while let Some(option) = options.next() {
+		let value = options.next().unwrap_or_else(|| usage());
+
+		match option.as_str() {
+			"--cycles" => cycles = count(value) as u32,
+			"--every" => every = count(value),
+			"--audio" => audio = Some(wav::load(value).unwrap_or_else(|e| fail(e))),
+			_ => usage(),
+		}
+	}
+
+	let frames = load_frames(source, cycles, audio);
+
+	if frames.is_empty() {
+		fail(format!("{} didn't produce any frames", source));
+	}
+
+	let extension = Path::new(out_path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+
+	let result = match extension.as_str() {
+		"gif" => render::write_gif(out_path, &frames),
+		"png" => {
+			let sampled: Vec<Frame> = frames.iter().step_by(every).cloned().collect();
+
+			render::write_layer_sheet(out_path, &sampled)
+		},
+		_ => fail(format!("Don't know how to write {}; expected a .gif or .png file", out_path)),
+	};
+
+	if let Err(e) = result {
+		fail(format!("Failed to write {}: {}", out_path, e));
+	}
+
+	println!("Rendered {} frames of {} into {}", frames.len(), source, out_path);
+}
@@ -0,0 +1,62 @@
+//! Show what the firmware's schedule does through a day.
+//!
+//! ```text
+//! cube_schedule <YYYY-MM-DD>
+//! ```
+//!
+//! Prints the brightness, playlist and colours the schedule picks for each half hour of the date. The
+//! host tests check the clock's calendar arithmetic and the default schedule.
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::process;
+
+use zinc_cube_host::clock::{ self, DateTime };
+use zinc_cube_host::schedule::{ self, Plan, SCHEDULE };
+use zinc_cube_host::settings;
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_schedule <YYYY-MM-DD>");
+
+	process::exit(1);
+}
+
+fn text(bytes: &[u8]) -> String {
+	String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn describe(plan: &Plan) -> String {
+	let brightness = match plan.brightness {
+		Some(level) => format!("at most {}", level),
+		None => "setting".to_string(),
+	};
+
+	let playlist = match plan.playlist {
+		Some(playlist) => text(settings::describe_playlist(playlist).as_bytes()),
+		None => "setting".to_string(),
+	};
+
+	format!("brightness {:<10} playlist {:<20} {}", brightness, playlist,
+		if plan.christmas { "christmas colours" } else { "rainbow colours" })
+}
+
+fn print_day(date: &str) {
+	let midnight = clock::parse(format!("{} 00:00", date).as_bytes()).unwrap_or_else(|| usage());
+
+	for half_hour in 0..48 {
+		let time = DateTime::from_seconds(midnight.to_seconds() + half_hour * 30 * 60);
+
+		println!("{:02}:{:02}  {}", time.hour, time.minute, describe(&schedule::plan(&SCHEDULE, Some(time))));
+	}
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	if args.len() != 1 {
+		usage();
+	}
+
+	print_day(&args[0]);
+}
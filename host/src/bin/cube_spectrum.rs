@@ -0,0 +1,91 @@
+//! Run a WAV file through the firmware's spectrum analyser.
+//!
+//! ```text
+//! cube_spectrum <in.wav> [out.gif|out.png]
+//! ```
+//!
+//! Prints the 16 band levels for every block of audio, lowest band on the left, so the DSP can be checked
+//! without the cube. Given an output file it also renders the spectrum pattern as the cube would
+//! show it.
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use zinc_cube_host::audio::{ self, Analyser, AudioSource, BLOCK_US, FFT_SIZE };
+use zinc_cube_host::patterns;
+use zinc_cube_host::render;
+use zinc_cube_host::sim::{ self, Simulator };
+use zinc_cube_host::wav::{ self, WavSource };
+
+/// Characters for levels from silent to full scale
+const SHADES: &[u8] = b" .:-=+*#%@";
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_spectrum <in.wav> [out.gif|out.png]");
+
+	process::exit(1);
+}
+
+fn fail(message: String) -> ! {
+	eprintln!("{}", message);
+
+	process::exit(1);
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	if args.is_empty() || args.len() > 2 {
+		usage();
+	}
+
+	let samples = wav::load(&args[0]).unwrap_or_else(|e| fail(e));
+
+	let mut source = WavSource::new(samples.clone());
+	let mut analyser = Analyser::new();
+	let mut block = [0i16; FFT_SIZE];
+	let mut time_ms = 0;
+
+	println!("    time  bands");
+
+	while !source.is_finished() {
+		source.read(&mut block);
+		analyser.update(&audio::band_energy(&block));
+
+		let bars: String = analyser.levels.iter().map(|&level| SHADES[level as usize * (SHADES.len() - 1) / 255] as char).collect();
+		let levels: Vec<String> = analyser.levels.iter().map(|level| format!("{:3}", level)).collect();
+
+		println!("{:>7}ms  |{}|  {}", time_ms, bars, levels.join(" "));
+
+		time_ms += BLOCK_US / 1000;
+	}
+
+	let out_path = match args.get(1) {
+		Some(path) => path,
+		None => return,
+	};
+
+	let mut cube = sim::cube();
+	let mut simulator = Simulator::with_audio(samples);
+
+	while !simulator.audio_finished() {
+		patterns::spectrum(&mut cube, &mut simulator);
+	}
+
+	let extension = Path::new(out_path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+
+	let result = match extension.as_str() {
+		"gif" => render::write_gif(out_path, &simulator.frames),
+		"png" => render::write_layer_sheet(out_path, &simulator.frames),
+		_ => fail(format!("Don't know how to write {}; expected a .gif or .png file", out_path)),
+	};
+
+	if let Err(e) = result {
+		fail(format!("Failed to write {}: {}", out_path, e));
+	}
+
+	println!("Rendered {} frames into {}", simulator.frames.len(), out_path);
+}
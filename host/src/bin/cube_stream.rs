@@ -0,0 +1,142 @@
+//! Stream frames to the cube over a serial port (or any other file, e.g. a pseudo-tty).
+//!
+//! ```text
+//! cube_stream <port> [--fps <n>] [--file <frames.bin>] [--loop]
+//! ```
+//!
+//! With `--file`, frames are read from a file of back to back 192 byte RGB frames in coordinate
+//! order. Without it, a rainbow test pattern is sent until interrupted.
+
+extern crate zinc_cube_host;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use zinc_cube_host::apa106led::{ Apa106Led, OFF };
+use zinc_cube_host::colour_functions::rgb_wheel;
+use zinc_cube_host::sender::Sender;
+use zinc_cube_host::stream::PAYLOAD_LEN;
+
+fn usage() -> ! {
+	eprintln!("Usage: cube_stream <port> [--fps <n>] [--file <frames.bin>] [--loop]");
+
+	process::exit(1);
+}
+
+fn read_frames(path: &str) -> Vec<[Apa106Led; 64]> {
+	let mut bytes = Vec::new();
+
+	if let Err(e) = File::open(path).and_then(|mut f| f.read_to_end(&mut bytes)) {
+		eprintln!("Failed to read {}: {}", path, e);
+
+		process::exit(1);
+	}
+
+	if bytes.len() % PAYLOAD_LEN != 0 {
+		eprintln!("{} is not a whole number of {} byte frames", path, PAYLOAD_LEN);
+
+		process::exit(1);
+	}
+
+	bytes.chunks(PAYLOAD_LEN).map(|chunk| {
+		let mut frame = [OFF; 64];
+
+		for (voxel, rgb) in frame.iter_mut().zip(chunk.chunks(3)) {
+			*voxel = Apa106Led { red: rgb[0], green: rgb[1], blue: rgb[2] };
+		}
+
+		frame
+	}).collect()
+}
+
+fn rainbow_frame(counter: u8) -> [Apa106Led; 64] {
+	let mut frame = [OFF; 64];
+
+	for (index, voxel) in frame.iter_mut().enumerate() {
+		*voxel = rgb_wheel((index as u8 * 4).wrapping_add(counter));
+	}
+
+	frame
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	let mut port_path = None;
+	let mut fps = 30;
+	let mut file = None;
+	let mut repeat = false;
+
+	let mut i = 0;
+
+	while i < args.len() {
+		match args[i].as_str() {
+			"--fps" => {
+				i += 1;
+				fps = args.get(i).and_then(|v| v.parse().ok()).unwrap_or_else(|| usage());
+			},
+			"--file" => {
+				i += 1;
+				file = Some(args.get(i).cloned().unwrap_or_else(|| usage()));
+			},
+			"--loop" => repeat = true,
+			arg if port_path.is_none() && !arg.starts_with("--") => port_path = Some(arg.to_string()),
+			_ => usage(),
+		}
+
+		i += 1;
+	}
+
+	let port_path = port_path.unwrap_or_else(|| usage());
+
+	if fps == 0 {
+		usage();
+	}
+
+	let mut sender = Sender::open(&port_path).unwrap_or_else(|e| {
+		eprintln!("Failed to open {}: {}", port_path, e);
+
+		process::exit(1);
+	});
+
+	let frame_time = Duration::from_millis(1000 / fps);
+
+	let mut send = |frame: &[Apa106Led; 64]| {
+		if let Err(e) = sender.send_frame(frame) {
+			eprintln!("Failed to write to {}: {}", port_path, e);
+
+			process::exit(1);
+		}
+
+		thread::sleep(frame_time);
+	};
+
+	match file {
+		Some(path) => {
+			let frames = read_frames(&path);
+
+			loop {
+				for frame in frames.iter() {
+					send(frame);
+				}
+
+				if !repeat {
+					break;
+				}
+			}
+		},
+		None => {
+			let mut counter: u8 = 0;
+
+			loop {
+				send(&rainbow_frame(counter));
+
+				counter = counter.wrapping_add(1);
+			}
+		},
+	}
+}
@@ -0,0 +1,189 @@
+//! Import animations from designers' CSV and JSON frame lists, and export them as Rust tables.
+//!
+//! CSV files have one lit voxel per row: `frame,x,y,z,r,g,b,delay`. An optional header row is
+//! skipped. Frames are numbered from zero and every frame needs at least one row; use a row with
+//! colour `0,0,0` for a blank frame. Voxels without a row are off.
+//!
+//! JSON files are an array of frames:
+//!
+//! ```text
+//! [ { "delay": 40, "voxels": [ { "x": 0, "y": 1, "z": 2, "r": 255, "g": 0, "b": 0 }, ... ] }, ... ]
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use apa106led::{ Apa106Led, OFF };
+use cube::CUBE_SIZE;
+use sim::Frame;
+
+fn check_coord(axis: &str, value: i64) -> Result<u8, String> {
+	if value < 0 || value >= CUBE_SIZE as i64 {
+		return Err(format!("{} = {} is outside the cube (0 - {})", axis, value, CUBE_SIZE - 1));
+	}
+
+	Ok(value as u8)
+}
+
+fn check_channel(channel: &str, value: i64) -> Result<u8, String> {
+	if !(0..=255).contains(&value) {
+		return Err(format!("{} = {} is not between 0 and 255", channel, value));
+	}
+
+	Ok(value as u8)
+}
+
+fn voxel_index(x: u8, y: u8, z: u8) -> usize {
+	(x + y * CUBE_SIZE + z * CUBE_SIZE * CUBE_SIZE) as usize
+}
+
+pub fn parse_csv(text: &str) -> Result<Vec<Frame>, String> {
+	let mut frames: BTreeMap<usize, Frame> = BTreeMap::new();
+
+	for (line_index, line) in text.lines().enumerate() {
+		let line_number = line_index + 1;
+		let line = line.trim();
+
+		if line.is_empty() {
+			continue;
+		}
+
+		let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+
+		if line_number == 1 && fields[0].parse::<i64>().is_err() {
+			continue;
+		}
+
+		if fields.len() != 8 {
+			return Err(format!("line {}: expected 8 fields (frame,x,y,z,r,g,b,delay), found {}", line_number, fields.len()));
+		}
+
+		let mut values = [0i64; 8];
+
+		for (value, field) in values.iter_mut().zip(fields.iter()) {
+			*value = field.parse().map_err(|_| format!("line {}: {:?} is not a number", line_number, field))?;
+		}
+
+		let at_line = |e: String| format!("line {}: {}", line_number, e);
+
+		if values[0] < 0 {
+			return Err(at_line(format!("frame {} is negative", values[0])));
+		}
+
+		if values[7] < 0 || values[7] > u16::MAX as i64 {
+			return Err(at_line(format!("delay {} is not between 0 and {}", values[7], u16::MAX)));
+		}
+
+		let x = check_coord("x", values[1]).map_err(at_line)?;
+		let y = check_coord("y", values[2]).map_err(at_line)?;
+		let z = check_coord("z", values[3]).map_err(at_line)?;
+		let red = check_channel("r", values[4]).map_err(at_line)?;
+		let green = check_channel("g", values[5]).map_err(at_line)?;
+		let blue = check_channel("b", values[6]).map_err(at_line)?;
+		let delay_ms = values[7] as u32;
+
+		let frame = frames.entry(values[0] as usize).or_insert(Frame { voxels: [OFF; 64], delay_ms });
+
+		if frame.delay_ms != delay_ms {
+			return Err(at_line(format!("delay {} doesn't match delay {} given earlier for frame {}", delay_ms, frame.delay_ms, values[0])));
+		}
+
+		frame.voxels[voxel_index(x, y, z)] = Apa106Led { red, green, blue };
+	}
+
+	let mut result = Vec::new();
+
+	for (index, frame) in frames.into_iter() {
+		if index != result.len() {
+			return Err(format!("frame {} has no rows; add a row with colour 0,0,0 for a blank frame", result.len()));
+		}
+
+		result.push(frame);
+	}
+
+	if result.is_empty() {
+		return Err("no frames found".to_string());
+	}
+
+	Ok(result)
+}
+
+fn json_int(object: &Value, key: &str) -> Result<i64, String> {
+	object.get(key).and_then(|v| v.as_i64()).ok_or_else(|| format!("missing or non-integer {:?}", key))
+}
+
+pub fn parse_json(text: &str) -> Result<Vec<Frame>, String> {
+	let root: Value = serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?;
+
+	let json_frames = root.as_array().ok_or("expected an array of frames")?;
+
+	let mut frames = Vec::new();
+
+	for (frame_index, json_frame) in json_frames.iter().enumerate() {
+		let at_frame = |e: String| format!("frame {}: {}", frame_index, e);
+
+		let delay = json_int(json_frame, "delay").map_err(at_frame)?;
+
+		if delay < 0 || delay > u16::MAX as i64 {
+			return Err(at_frame(format!("delay {} is not between 0 and {}", delay, u16::MAX)));
+		}
+
+		let json_voxels = json_frame.get("voxels").and_then(|v| v.as_array()).ok_or_else(|| at_frame("missing \"voxels\" array".to_string()))?;
+
+		let mut frame = Frame { voxels: [OFF; 64], delay_ms: delay as u32 };
+
+		for (voxel_number, voxel) in json_voxels.iter().enumerate() {
+			let at_voxel = |e: String| format!("frame {} voxel {}: {}", frame_index, voxel_number, e);
+
+			let x = json_int(voxel, "x").and_then(|v| check_coord("x", v)).map_err(at_voxel)?;
+			let y = json_int(voxel, "y").and_then(|v| check_coord("y", v)).map_err(at_voxel)?;
+			let z = json_int(voxel, "z").and_then(|v| check_coord("z", v)).map_err(at_voxel)?;
+			let red = json_int(voxel, "r").and_then(|v| check_channel("r", v)).map_err(at_voxel)?;
+			let green = json_int(voxel, "g").and_then(|v| check_channel("g", v)).map_err(at_voxel)?;
+			let blue = json_int(voxel, "b").and_then(|v| check_channel("b", v)).map_err(at_voxel)?;
+
+			frame.voxels[voxel_index(x, y, z)] = Apa106Led { red, green, blue };
+		}
+
+		frames.push(frame);
+	}
+
+	if frames.is_empty() {
+		return Err("no frames found".to_string());
+	}
+
+	Ok(frames)
+}
+
+/// Generate a Rust module containing a `TableFrame` table, for use with `playback::play_table`
+pub fn to_rust_table(name: &str, source: &str, frames: &[Frame]) -> String {
+	let mut out = String::new();
+
+	writeln!(out, "// Generated by cube_convert from {}", source).unwrap();
+	writeln!(out).unwrap();
+	writeln!(out, "use apa106led::Apa106Led;").unwrap();
+	writeln!(out, "use playback::TableFrame;").unwrap();
+	writeln!(out).unwrap();
+	// A static stays in flash. The firmware's compiler doesn't promote `&CONST` to a static, so each use of a
+	// const table would copy all of it onto the stack.
+	writeln!(out, "pub static {}: [TableFrame; {}] = [", name, frames.len()).unwrap();
+
+	for frame in frames.iter() {
+		writeln!(out, "\tTableFrame {{").unwrap();
+		writeln!(out, "\t\tdelay_ms: {},", frame.delay_ms).unwrap();
+		writeln!(out, "\t\tvoxels: [").unwrap();
+
+		for voxel in frame.voxels.iter() {
+			writeln!(out, "\t\t\tApa106Led {{ red: {}, green: {}, blue: {} }},", voxel.red, voxel.green, voxel.blue).unwrap();
+		}
+
+		writeln!(out, "\t\t],").unwrap();
+		writeln!(out, "\t}},").unwrap();
+	}
+
+	writeln!(out, "];").unwrap();
+
+	out
+}
@@ -0,0 +1,95 @@
+//! Host side tools for the cube. Firmware modules that don't touch any hardware are compiled
+//! straight from `../src` so the host and the cube always agree on formats and protocols.
+
+// The firmware is written for an old nightly and keeps to that compiler's idioms
+#![allow(clippy::redundant_field_names, clippy::new_without_default, clippy::unnecessary_cast, clippy::manual_clamp,
+	clippy::redundant_static_lifetimes, clippy::legacy_numeric_constants, clippy::cast_abs_to_unsigned,
+	clippy::needless_range_loop, clippy::manual_is_multiple_of)]
+
+// The firmware is `no_std`, so shared modules refer to `core` rather than `std`
+extern crate core;
+extern crate gif;
+extern crate png;
+extern crate serde_json;
+
+#[path = "../../src/apa106led.rs"]
+pub mod apa106led;
+#[path = "../../src/audio.rs"]
+pub mod audio;
+#[path = "../../src/automata.rs"]
+pub mod automata;
+#[path = "../../src/beat.rs"]
+pub mod beat;
+#[allow(ellipsis_inclusive_range_patterns)]
+#[path = "../../src/clock.rs"]
+pub mod clock;
+#[path = "../../src/colour_functions.rs"]
+pub mod colour_functions;
+#[allow(ellipsis_inclusive_range_patterns)]
+#[path = "../../src/console.rs"]
+pub mod console;
+#[allow(array_into_iter, mismatched_lifetime_syntaxes, clippy::into_iter_on_ref)]
+#[path = "../../src/cube.rs"]
+pub mod cube;
+#[path = "../../src/draw.rs"]
+pub mod draw;
+#[allow(non_upper_case_globals, unused_unsafe)]
+#[path = "../../src/embedded_rand.rs"]
+pub mod embedded_rand;
+#[path = "../../src/fixed.rs"]
+pub mod fixed;
+#[allow(ellipsis_inclusive_range_patterns)]
+#[path = "../../src/font.rs"]
+pub mod font;
+#[path = "../../src/frame_timing.rs"]
+pub mod frame_timing;
+#[path = "../../src/input.rs"]
+pub mod input;
+#[path = "../../src/particles.rs"]
+pub mod particles;
+#[path = "../../src/pattern_list.rs"]
+pub mod pattern_list;
+#[allow(unused_imports, unused_parens, clippy::identity_op)]
+#[path = "../../src/patterns.rs"]
+pub mod patterns;
+#[allow(deprecated)]
+#[path = "../../src/playback.rs"]
+pub mod playback;
+#[path = "../../src/power.rs"]
+pub mod power;
+#[path = "../../src/procedural.rs"]
+pub mod procedural;
+#[path = "../../src/protocols.rs"]
+pub mod protocols;
+#[path = "../../src/schedule.rs"]
+pub mod schedule;
+#[path = "../../src/scheduler.rs"]
+pub mod scheduler;
+#[allow(ellipsis_inclusive_range_patterns)]
+#[path = "../../src/settings.rs"]
+pub mod settings;
+#[path = "../../src/smooth.rs"]
+pub mod smooth;
+#[path = "../../src/snake.rs"]
+pub mod snake;
+#[path = "../../src/sprites.rs"]
+pub mod sprites;
+#[path = "../../src/status.rs"]
+pub mod status;
+#[path = "../../src/stream.rs"]
+pub mod stream;
+#[path = "../../src/tables.rs"]
+pub mod tables;
+#[path = "../../src/transform.rs"]
+pub mod transform;
+
+mod zinc;
+
+pub mod beats;
+pub mod convert;
+pub mod mock_eeprom;
+pub mod recording;
+pub mod render;
+pub mod sender;
+pub mod sim;
+pub mod wav;
@@ -0,0 +1,53 @@
+//! In-memory stand-in for the cube's EEPROM, for checking how settings are stored.
+
+use settings::{ Eeprom, SLOTS, SLOT_WORDS };
+
+/// What erased EEPROM reads as
+pub const ERASED: u32 = 0xffff_ffff;
+
+/// Words of EEPROM that counts how many times each one has been written
+#[derive(Clone)]
+pub struct MockEeprom {
+	pub words: Vec<u32>,
+	pub writes: Vec<u32>,
+	/// If set, only this many more writes succeed and the rest are lost, as if the power went
+	pub power_fails_after: Option<usize>,
+}
+
+impl MockEeprom {
+	/// Erased EEPROM the size of the TM4C123's
+	pub fn new() -> MockEeprom {
+		let size = (SLOTS * SLOT_WORDS) as usize;
+
+		MockEeprom {
+			words: vec![ERASED; size],
+			writes: vec![0; size],
+			power_fails_after: None,
+		}
+	}
+
+	pub fn total_writes(&self) -> u32 {
+		self.writes.iter().sum()
+	}
+
+	pub fn most_writes(&self) -> u32 {
+		self.writes.iter().cloned().max().unwrap_or(0)
+	}
+}
+
+impl Eeprom for MockEeprom {
+	fn read(&self, address: u32) -> u32 {
+		self.words[address as usize]
+	}
+
+	fn write(&mut self, address: u32, value: u32) {
+		match self.power_fails_after {
+			Some(0) => return,
+			Some(allowed) => self.power_fails_after = Some(allowed - 1),
+			None => (),
+		}
+
+		self.words[address as usize] = value;
+		self.writes[address as usize] += 1;
+	}
+}
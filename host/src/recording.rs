@@ -0,0 +1,120 @@
+//! Encoder for the recorded animation format described in `src/playback.rs`.
+
+use std::collections::HashMap;
+
+use apa106led::{ Apa106Led, OFF };
+use playback::{ FrameDecoder, PlaybackError, FLAG_DELAY, FLAG_DELTA, MAGIC, VERSION };
+use sim::Frame;
+
+fn push_colour(out: &mut Vec<u8>, colour: Apa106Led) {
+	out.extend_from_slice(&[ colour.red, colour.green, colour.blue ]);
+}
+
+fn key_frame(voxels: &[Apa106Led; 64]) -> Vec<u8> {
+	let mut out = Vec::new();
+	let mut voxel = 0;
+
+	while voxel < 64 {
+		let colour = voxels[voxel];
+		let count = voxels[voxel..].iter().take_while(|c| **c == colour).count();
+
+		out.push(count as u8);
+		push_colour(&mut out, colour);
+
+		voxel += count;
+	}
+
+	out
+}
+
+fn delta_frame(previous: &[Apa106Led; 64], voxels: &[Apa106Led; 64]) -> Vec<u8> {
+	let mut out = Vec::new();
+	let mut voxel = 0;
+
+	while voxel < 64 {
+		let skip = (voxel..64).take_while(|i| voxels[*i] == previous[*i]).count();
+		let start = voxel + skip;
+		let count = (start..64).take_while(|i| voxels[*i] != previous[*i]).count();
+
+		out.push(skip as u8);
+		out.push(count as u8);
+
+		for colour in voxels[start..start + count].iter() {
+			push_colour(&mut out, *colour);
+		}
+
+		voxel = start + count;
+	}
+
+	out
+}
+
+/// Encode frames into a recording. Each frame is stored as a key or delta frame, whichever is smaller.
+pub fn encode(frames: &[Frame]) -> Result<Vec<u8>, String> {
+	if frames.len() > u16::MAX as usize {
+		return Err(format!("Too many frames ({}), the limit is {}", frames.len(), u16::MAX));
+	}
+
+	let delay = |frame: &Frame| frame.delay_ms.min(u16::MAX as u32) as u16;
+
+	// The most common delay goes in the header so most frames don't need their own
+	let mut delay_counts = HashMap::new();
+
+	for frame in frames.iter() {
+		*delay_counts.entry(delay(frame)).or_insert(0) += 1;
+	}
+
+	let default_delay = delay_counts.iter().max_by_key(|&(delay, count)| (*count, *delay)).map(|(delay, _)| *delay).unwrap_or(0);
+
+	let mut out = Vec::new();
+
+	out.extend_from_slice(&MAGIC);
+	out.extend_from_slice(&[ VERSION, 4, 4, 4 ]);
+	out.extend_from_slice(&(frames.len() as u16).to_le_bytes());
+	out.extend_from_slice(&default_delay.to_le_bytes());
+
+	let mut previous: Option<&[Apa106Led; 64]> = None;
+
+	for frame in frames.iter() {
+		let mut flags = 0;
+		let mut body = key_frame(&frame.voxels);
+
+		if let Some(previous) = previous {
+			let delta = delta_frame(previous, &frame.voxels);
+
+			if delta.len() < body.len() {
+				flags |= FLAG_DELTA;
+				body = delta;
+			}
+		}
+
+		if delay(frame) != default_delay {
+			flags |= FLAG_DELAY;
+		}
+
+		out.push(flags);
+
+		if flags & FLAG_DELAY != 0 {
+			out.extend_from_slice(&delay(frame).to_le_bytes());
+		}
+
+		out.extend_from_slice(&body);
+
+		previous = Some(&frame.voxels);
+	}
+
+	Ok(out)
+}
+
+/// Decode a whole recording back into frames
+pub fn decode(data: &[u8]) -> Result<Vec<Frame>, PlaybackError> {
+	let mut decoder = FrameDecoder::new(data)?;
+	let mut voxels = [OFF; 64];
+	let mut frames = Vec::new();
+
+	while let Some(delay_ms) = decoder.next_frame(&mut voxels)? {
+		frames.push(Frame { voxels, delay_ms: delay_ms as u32 });
+	}
+
+	Ok(frames)
+}
@@ -0,0 +1,210 @@
+//! Render simulator output to images for sharing patterns with people who don't have a cube handy.
+//!
+//! `render_isometric` draws the whole cube in an isometric style 3D view with a soft glow around
+//! each lit LED. `render_layers` draws the four layers side by side, seen from above, for checking
+//! exact voxel colours.
+
+use std::fs::File;
+use std::io::{ self, BufWriter };
+use std::path::Path;
+
+use apa106led::Apa106Led;
+use sim::Frame;
+
+pub const ISOMETRIC_SIZE: usize = 320;
+
+/// Distance between neighbouring LEDs in the isometric view, in pixels
+const SPACING: f32 = 60.0;
+
+/// Patterns run at quite low brightness (see `MAX_BRIGHTNESS`) but LEDs still look bright, so LED
+/// intensity is exposed up before being tone mapped into the image.
+const EXPOSURE: f32 = 8.0;
+
+const GLOW_SIGMA: f32 = 9.0;
+const CORE_RADIUS: f32 = 3.5;
+
+const BACKGROUND: f32 = 0.02;
+
+/// Brightness of the dot drawn for unlit LEDs so the grid stays visible
+const UNLIT_DOT: f32 = 0.05;
+
+const LAYER_CELL: usize = 10;
+const LAYER_GAP: usize = 1;
+const LAYER_SPACING: usize = 8;
+
+/// Width of one layer in the layer sheet, including gaps between cells
+const LAYER_SIZE: usize = 4 * LAYER_CELL + 3 * LAYER_GAP;
+
+pub const LAYER_SHEET_WIDTH: usize = 4 * LAYER_SIZE + 5 * LAYER_SPACING;
+const LAYER_ROW_HEIGHT: usize = LAYER_SIZE + LAYER_SPACING;
+
+fn to_linear(colour: Apa106Led) -> [f32; 3] {
+	[ colour.red as f32 / 255.0, colour.green as f32 / 255.0, colour.blue as f32 / 255.0 ]
+}
+
+fn tone_map(value: f32) -> u8 {
+	((1.0 - (-value * EXPOSURE).exp()) * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Camera angles for the isometric view. A true isometric projection lines up the cube's diagonals so
+/// LEDs end up hidden behind each other; these angles keep every LED visible.
+const AZIMUTH_DEGREES: f32 = 30.0;
+const ELEVATION_DEGREES: f32 = 20.0;
+
+/// Position of voxel `(x, y, z)` in the isometric view. Z is up, X recedes to the right and Y to the left.
+fn project(x: usize, y: usize, z: usize) -> (f32, f32) {
+	let (wx, wy, wz) = (x as f32 - 1.5, y as f32 - 1.5, z as f32 - 1.5);
+
+	let azimuth = AZIMUTH_DEGREES.to_radians();
+	let elevation = ELEVATION_DEGREES.to_radians();
+
+	let sx = wx * azimuth.cos() - wy * azimuth.sin();
+	let depth = wx * azimuth.sin() + wy * azimuth.cos();
+	let sy = wz * elevation.cos() + depth * elevation.sin();
+
+	let centre = ISOMETRIC_SIZE as f32 / 2.0;
+
+	(centre + sx * SPACING, centre - sy * SPACING)
+}
+
+/// Render one frame in isometric 3D, returning `ISOMETRIC_SIZE` square RGB pixels
+pub fn render_isometric(voxels: &[Apa106Led; 64]) -> Vec<u8> {
+	let size = ISOMETRIC_SIZE;
+	let mut light = vec![ [0.0f32; 3]; size * size ];
+
+	let reach = (GLOW_SIGMA * 3.0) as isize;
+
+	for z in 0..4 {
+		for y in 0..4 {
+			for x in 0..4 {
+				let colour = to_linear(voxels[x + y * 4 + z * 16]);
+				let (cx, cy) = project(x, y, z);
+				let lit = colour.iter().any(|c| *c > 0.0);
+
+				for py in (cy as isize - reach)..(cy as isize + reach + 1) {
+					for px in (cx as isize - reach)..(cx as isize + reach + 1) {
+						if px < 0 || py < 0 || px >= size as isize || py >= size as isize {
+							continue;
+						}
+
+						let (dx, dy) = (px as f32 - cx, py as f32 - cy);
+						let distance_sq = dx * dx + dy * dy;
+						let pixel = &mut light[py as usize * size + px as usize];
+
+						if lit {
+							// Saturated core plus a gaussian halo. Light adds up so draw order doesn't matter.
+							let core = if distance_sq <= CORE_RADIUS * CORE_RADIUS { 1.0 } else { 0.0 };
+							let glow = (-distance_sq / (2.0 * GLOW_SIGMA * GLOW_SIGMA)).exp();
+
+							for channel in 0..3 {
+								pixel[channel] += colour[channel] * (core + glow);
+							}
+						} else if distance_sq <= 2.0 * 2.0 {
+							for channel in pixel.iter_mut() {
+								*channel += UNLIT_DOT / EXPOSURE;
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+
+	light.iter().flat_map(|pixel| pixel.map(|c| tone_map(c + BACKGROUND / EXPOSURE))).collect()
+}
+
+fn draw_layer_row(pixels: &mut [u8], row: usize, voxels: &[Apa106Led; 64]) {
+	let top = row * LAYER_ROW_HEIGHT + LAYER_SPACING;
+
+	for z in 0..4 {
+		let left = LAYER_SPACING + z * (LAYER_SIZE + LAYER_SPACING);
+
+		for y in 0..4 {
+			for x in 0..4 {
+				let colour = to_linear(voxels[x + y * 4 + z * 16]);
+				let rgb = [ tone_map(colour[0]), tone_map(colour[1]), tone_map(colour[2]) ];
+
+				// Seen from above with Y = 3 at the top, like a map
+				let cell_left = left + x * (LAYER_CELL + LAYER_GAP);
+				let cell_top = top + (3 - y) * (LAYER_CELL + LAYER_GAP);
+
+				for py in cell_top..cell_top + LAYER_CELL {
+					for px in cell_left..cell_left + LAYER_CELL {
+						let offset = (py * LAYER_SHEET_WIDTH + px) * 3;
+
+						pixels[offset..offset + 3].copy_from_slice(&rgb);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Render frames as a sheet with one row per frame, each row showing layers `z = 0` to `z = 3` from
+/// left to right. Returns the sheet's height and RGB pixels.
+pub fn render_layers(frames: &[Frame]) -> (usize, Vec<u8>) {
+	let height = frames.len() * LAYER_ROW_HEIGHT + LAYER_SPACING;
+	let mut pixels = vec![ 0; LAYER_SHEET_WIDTH * height * 3 ];
+
+	for (row, frame) in frames.iter().enumerate() {
+		draw_layer_row(&mut pixels, row, &frame.voxels);
+	}
+
+	(height, pixels)
+}
+
+/// Shortest frame delay, in centiseconds, that browsers honour. Anything shorter is slowed right down.
+const MIN_GIF_DELAY: u16 = 2;
+
+/// Write an animated GIF of the isometric view. GIF delays are in centiseconds, so frame times are
+/// tracked against the pattern's real timeline to stop rounding errors building up. Frames that would
+/// be on screen for less than `MIN_GIF_DELAY` are dropped.
+pub fn write_gif<P: AsRef<Path>>(path: P, frames: &[Frame]) -> io::Result<()> {
+	let file = BufWriter::new(File::create(path)?);
+	let size = ISOMETRIC_SIZE as u16;
+
+	let mut encoder = gif::Encoder::new(file, size, size, &[]).map_err(io::Error::other)?;
+
+	encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+
+	let mut elapsed_ms: u64 = 0;
+	let mut written_cs: u64 = 0;
+
+	for (index, frame) in frames.iter().enumerate() {
+		elapsed_ms += frame.delay_ms as u64;
+
+		let end_cs = (elapsed_ms + 5) / 10;
+		let delay_cs = end_cs.saturating_sub(written_cs);
+		let last = index == frames.len() - 1;
+
+		if delay_cs < MIN_GIF_DELAY as u64 && !last {
+			continue;
+		}
+
+		let rgb = render_isometric(&frame.voxels);
+		let mut gif_frame = gif::Frame::from_rgb_speed(size, size, &rgb, 10);
+
+		gif_frame.delay = delay_cs.clamp(MIN_GIF_DELAY as u64, u16::MAX as u64) as u16;
+
+		encoder.write_frame(&gif_frame).map_err(io::Error::other)?;
+
+		written_cs = end_cs;
+	}
+
+	Ok(())
+}
+
+/// Write a PNG sheet of the four layers of each frame
+pub fn write_layer_sheet<P: AsRef<Path>>(path: P, frames: &[Frame]) -> io::Result<()> {
+	let (height, pixels) = render_layers(frames);
+
+	let file = BufWriter::new(File::create(path)?);
+	let mut encoder = png::Encoder::new(file, LAYER_SHEET_WIDTH as u32, height as u32);
+
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+
+	let mut writer = encoder.write_header().map_err(io::Error::other)?;
+
+	writer.write_image_data(&pixels).map_err(io::Error::other)
+}
@@ -0,0 +1,47 @@
+use std::fs::{ File, OpenOptions };
+use std::io::{ self, Write };
+use std::path::Path;
+
+use apa106led::Apa106Led;
+use stream::{ encode_packet, PACKET_LEN };
+
+/// Sends frames to the cube using the streaming protocol in `stream.rs`.
+///
+/// Any `Write` will do; `Sender::open` is a shortcut for serial ports and pseudo-ttys. The port's
+/// baud rate isn't touched, so set it up first with something like `stty -F /dev/ttyACM0 115200 raw`.
+pub struct Sender<W: Write> {
+	port: W,
+
+	packet: [u8; PACKET_LEN],
+}
+
+impl Sender<File> {
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Sender<File>> {
+		let port = OpenOptions::new().write(true).open(path)?;
+
+		Ok(Sender::new(port))
+	}
+}
+
+impl<W: Write> Sender<W> {
+	pub fn new(port: W) -> Sender<W> {
+		Sender {
+			port,
+
+			packet: [0; PACKET_LEN],
+		}
+	}
+
+	/// Send a single frame. `frame` is in coordinate order (`x + 4 * y + 16 * z`).
+	pub fn send_frame(&mut self, frame: &[Apa106Led; 64]) -> io::Result<()> {
+		encode_packet(frame, &mut self.packet);
+
+		self.port.write_all(&self.packet)?;
+
+		self.port.flush()
+	}
+
+	pub fn into_inner(self) -> W {
+		self.port
+	}
+}
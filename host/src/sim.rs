@@ -0,0 +1,164 @@
+//! Runs the firmware's patterns on a PC, capturing every frame they show instead of sending it to
+//! the LEDs.
+
+use apa106led::{ Apa106Led, OFF, WARM_WHITE };
+use audio::{ AudioSource, Listener, BANDS, BLOCK_US, FFT_SIZE };
+use automata::{ BRIANS_BRAIN, EMBERS, SMALL_LIFE };
+use beat::Beat;
+use colour_functions::fade;
+use cube::Cube4;
+use fixed::Fixed8;
+use font::FONT_4X4;
+use patterns::{ self, MARQUEE_PALETTE, MARQUEE_TEXT, MAX_BRIGHTNESS };
+use procedural::Noise;
+use scheduler::Scheduler;
+use wav::WavSource;
+use zinc::hal::tiva_c::spi::Spi;
+
+static SPI: Spi = Spi;
+
+/// Names accepted by `run_pattern`
+pub const PATTERNS: [&str; 22] = [
+	"christmas_rainbow", "animated_slices", "rain", "fountain", "firework", "snow", "marquee", "plasma",
+	"value_noise", "perlin", "simplex", "fire", "spectrum", "life", "embers", "brians_brain", "snake",
+	"sprite_show", "blender", "smooth_blender", "daylight", "spiral",
+];
+
+#[derive(Copy, Clone)]
+pub struct Frame {
+	/// Voxel colours in coordinate order (`x + 4 * y + 16 * z`)
+	pub voxels: [Apa106Led; 64],
+	pub delay_ms: u32,
+}
+
+/// Scheduler that records frames instead of displaying them. It can listen to a WAV file in place of
+/// the cube's microphone, moving through it by each frame's delay.
+pub struct Simulator {
+	pub frames: Vec<Frame>,
+
+	audio: Option<WavSource>,
+	listener: Listener,
+	/// Time shown since the last block of audio was listened to
+	pending_us: u32,
+
+	/// Time shown in all, and how much of it the recorded frames' whole millisecond delays cover, so
+	/// holds that aren't whole milliseconds round without drifting
+	shown_us: u64,
+	recorded_ms: u64,
+	/// What the last frame asked to be held for, which is exactly how long it was
+	delta_us: u32,
+}
+
+impl Simulator {
+	pub fn new() -> Simulator {
+		Simulator {
+			frames: Vec::new(),
+
+			audio: None,
+			listener: Listener::new(),
+			pending_us: 0,
+
+			shown_us: 0,
+			recorded_ms: 0,
+			delta_us: 0,
+		}
+	}
+
+	/// A simulator that hears `samples`, from `wav::parse()`
+	pub fn with_audio(samples: Vec<i16>) -> Simulator {
+		Simulator { audio: Some(WavSource::new(samples)), ..Simulator::new() }
+	}
+
+	/// True once all the audio has been heard, or straight away if there isn't any
+	pub fn audio_finished(&self) -> bool {
+		self.audio.as_ref().is_none_or(|audio| audio.is_finished())
+	}
+}
+
+impl Scheduler for Simulator {
+	fn show_us(&mut self, cube: &mut Cube4, hold_us: u32) {
+		cube.flush();
+
+		self.shown_us += hold_us as u64;
+
+		let delay_ms = (self.shown_us + 500) / 1000 - self.recorded_ms;
+
+		self.recorded_ms += delay_ms;
+		self.delta_us = hold_us;
+
+		self.frames.push(Frame { voxels: cube.get_frame(), delay_ms: delay_ms as u32 });
+
+		if let Some(ref mut audio) = self.audio {
+			let mut block = [0i16; FFT_SIZE];
+
+			self.pending_us += hold_us;
+
+			while self.pending_us >= BLOCK_US {
+				audio.read(&mut block);
+				self.listener.listen(&block, BLOCK_US);
+				self.pending_us -= BLOCK_US;
+			}
+		}
+	}
+
+	fn delta_us(&mut self) -> u32 {
+		self.delta_us
+	}
+
+	fn bands(&mut self) -> Option<[u64; BANDS]> {
+		match self.audio {
+			Some(_) => Some(self.listener.energy),
+			None => None,
+		}
+	}
+
+	fn beat(&mut self) -> Option<Beat> {
+		match self.audio {
+			Some(_) => Some(self.listener.tracker.beat()),
+			None => None,
+		}
+	}
+}
+
+/// A blank cube backed by a dummy SPI peripheral
+pub fn cube() -> Cube4<'static> {
+	let mut cube = Cube4::new(&SPI);
+
+	cube.fill(OFF);
+
+	cube
+}
+
+/// Run one cycle of the named pattern with the same arguments `main.rs` uses. Returns false if there's
+/// no pattern by that name.
+pub fn run_pattern<S: Scheduler>(name: &str, cube: &mut Cube4, sched: &mut S) -> bool {
+	let raindrop_colour = fade(WARM_WHITE, Fixed8::from_ratio(MAX_BRIGHTNESS as i32, 255));
+
+	match name {
+		"christmas_rainbow" => patterns::christmas_rainbow(cube, sched),
+		"animated_slices" => patterns::animated_slices(cube, sched),
+		"rain" => patterns::rain(cube, sched, raindrop_colour),
+		"fountain" => patterns::fountain(cube, sched),
+		"firework" => patterns::firework(cube, sched),
+		"snow" => patterns::snow(cube, sched),
+		"marquee" => patterns::marquee(cube, sched, MARQUEE_TEXT, &FONT_4X4, &MARQUEE_PALETTE),
+		"plasma" => patterns::plasma(cube, sched, 2, 40),
+		"value_noise" => patterns::noise_field(cube, sched, Noise::Value, 4, 80),
+		"perlin" => patterns::noise_field(cube, sched, Noise::Perlin, 4, 80),
+		"simplex" => patterns::noise_field(cube, sched, Noise::Simplex, 4, 80),
+		"fire" => patterns::fire(cube, sched, 12, 90),
+		"spectrum" => patterns::spectrum(cube, sched),
+		"life" => patterns::automaton(cube, sched, SMALL_LIFE, 40, 60),
+		"embers" => patterns::automaton(cube, sched, EMBERS, 70, 60),
+		"brians_brain" => patterns::automaton(cube, sched, BRIANS_BRAIN, 40, 60),
+		"snake" => patterns::snake(cube, sched),
+		"sprite_show" => patterns::sprite_show(cube, sched),
+		"blender" => patterns::blender(cube, sched, raindrop_colour),
+		"smooth_blender" => patterns::smooth_blender(cube, sched, raindrop_colour),
+		"daylight" => patterns::daylight(cube, sched),
+		"spiral" => patterns::spiral(cube, sched),
+		_ => return false,
+	}
+
+	true
+}
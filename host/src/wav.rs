@@ -0,0 +1,208 @@
+//! Feed WAV files to the firmware's audio patterns and spectrum analyser in place of the ADC.
+//!
+//! Only uncompressed PCM is supported, 8 or 16 bits, any number of channels and any sample rate.
+//! Channels are mixed down to mono and the result resampled to the cube's `SAMPLE_RATE`.
+//!
+//! There are also generated fixtures with known tempos for checking beat detection.
+
+use std::f32::consts::PI;
+use std::fs;
+
+use audio::{ AudioSource, FFT_SIZE, SAMPLE_RATE };
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes([ data[offset], data[offset + 1] ])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes([ data[offset], data[offset + 1], data[offset + 2], data[offset + 3] ])
+}
+
+/// Decode a WAV file into mono samples at `SAMPLE_RATE`
+pub fn parse(data: &[u8]) -> Result<Vec<i16>, String> {
+	if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+		return Err("not a RIFF WAVE file".to_string());
+	}
+
+	let mut format = None;
+	let mut offset = 12;
+
+	while offset + 8 <= data.len() {
+		let id = &data[offset..offset + 4];
+		let size = read_u32(data, offset + 4) as usize;
+		let body = offset + 8;
+		let end = body.saturating_add(size).min(data.len());
+
+		match id {
+			b"fmt " if size >= 16 && end - body >= 16 => {
+				format = Some((read_u16(data, body), read_u16(data, body + 2), read_u32(data, body + 4), read_u16(data, body + 14)));
+			},
+			b"data" => {
+				let (encoding, channels, rate, bits) = format.ok_or("data chunk before fmt chunk")?;
+
+				if encoding != 1 {
+					return Err(format!("unsupported encoding {}; only PCM is supported", encoding));
+				}
+
+				if channels == 0 || rate == 0 {
+					return Err("fmt chunk has no channels or a zero sample rate".to_string());
+				}
+
+				let samples = decode_pcm(&data[body..end], channels as usize, bits)?;
+
+				return Ok(resample(&samples, rate));
+			},
+			_ => {},
+		}
+
+		// Chunks are padded to an even length
+		offset = body.saturating_add(size).saturating_add(size & 1);
+	}
+
+	Err("no data chunk".to_string())
+}
+
+/// Read and decode a WAV file, with errors ready to show to the user
+pub fn load(path: &str) -> Result<Vec<i16>, String> {
+	let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+	parse(&data).map_err(|e| format!("{} is not a usable WAV file: {}", path, e))
+}
+
+/// Mix interleaved PCM down to mono 16 bit samples
+fn decode_pcm(data: &[u8], channels: usize, bits: u16) -> Result<Vec<i16>, String> {
+	let width = match bits {
+		8 => 1,
+		16 => 2,
+		_ => return Err(format!("unsupported sample size of {} bits; only 8 and 16 are supported", bits)),
+	};
+
+	let samples = data.chunks_exact(width * channels).map(|frame| {
+		let sum: i32 = frame.chunks_exact(width).map(|sample| match width {
+			// 8 bit WAV is unsigned
+			1 => (sample[0] as i32 - 128) << 8,
+			_ => i16::from_le_bytes([ sample[0], sample[1] ]) as i32,
+		}).sum();
+
+		(sum / channels as i32) as i16
+	});
+
+	Ok(samples.collect())
+}
+
+/// Linearly interpolate `samples` from `rate` to `SAMPLE_RATE`. Going down in rate this aliases
+/// anything above 4kHz, which only matters for the top band or two.
+fn resample(samples: &[i16], rate: u32) -> Vec<i16> {
+	if samples.is_empty() {
+		return Vec::new();
+	}
+
+	let length = (samples.len() as u64 * SAMPLE_RATE as u64 / rate as u64) as usize;
+
+	(0..length).map(|i| {
+		let position = i as f64 * rate as f64 / SAMPLE_RATE as f64;
+		let index = position as usize;
+		let fraction = position - index as f64;
+		let next = samples[(index + 1).min(samples.len() - 1)];
+
+		(samples[index] as f64 * (1.0 - fraction) + next as f64 * fraction) as i16
+	}).collect()
+}
+
+/// Plays back samples a block at a time, the same way the cube's ADC captures them. Reads past the end
+/// return silence.
+pub struct WavSource {
+	samples: Vec<i16>,
+	position: usize,
+}
+
+impl WavSource {
+	/// `samples` must already be at `SAMPLE_RATE`, as returned by `parse()`
+	pub fn new(samples: Vec<i16>) -> WavSource {
+		WavSource { samples, position: 0 }
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.position >= self.samples.len()
+	}
+}
+
+impl AudioSource for WavSource {
+	fn read(&mut self, block: &mut [i16; FFT_SIZE]) {
+		for (i, sample) in block.iter_mut().enumerate() {
+			*sample = self.samples.get(self.position + i).cloned().unwrap_or(0);
+		}
+
+		self.position += FFT_SIZE;
+	}
+}
+
+/// Tiny linear congruential generator, so generated audio is the same every time
+struct Noise(u32);
+
+impl Noise {
+	/// Uniform between -1 and 1
+	fn next(&mut self) -> f32 {
+		self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+		(self.0 >> 8) as f32 / (1 << 23) as f32 - 1.0
+	}
+}
+
+/// A drum loop with a known tempo for testing beat detection: a kick on every beat, a snare on two and
+/// four, closed hi-hats on the eighth notes, a bass line and a quiet pad, all over a little hiss
+pub fn drum_loop(bpm: f32, seconds: f32) -> Vec<i16> {
+	let length = (seconds * SAMPLE_RATE as f32) as usize;
+	let beat_length = 60.0 / bpm;
+	let mut noise = Noise(1);
+
+	(0..length).map(|i| {
+		let time = i as f32 / SAMPLE_RATE as f32;
+		let beat = (time / beat_length) as u32;
+		let in_beat = time - beat as f32 * beat_length;
+		let in_eighth = in_beat % (beat_length / 2.0);
+
+		// Pitch drops from 150Hz to 50Hz as it fades
+		let kick_phase = 2.0 * PI * (50.0 * in_beat + 100.0 * 0.03 * (1.0 - (-in_beat / 0.03).exp()));
+		let kick = kick_phase.sin() * (-in_beat / 0.12).exp();
+
+		let snare = if beat % 2 == 1 {
+			(noise.next() * 0.6 + (2.0 * PI * 190.0 * in_beat).sin() * 0.4) * (-in_beat / 0.08).exp()
+		} else {
+			0.0
+		};
+
+		// Crudely high passed by differencing two noise samples
+		let hat = (noise.next() - noise.next()) * 0.5 * (-in_eighth / 0.02).exp();
+
+		// Root notes on the off beats
+		let bass_notes = [ 55.0, 55.0, 73.4, 65.4 ];
+		let bass_note = bass_notes[(beat / 4 % 4) as usize];
+		let bass = if in_beat > beat_length / 2.0 {
+			(2.0 * PI * bass_note * time).sin() * (-(in_beat - beat_length / 2.0) / 0.15).exp()
+		} else {
+			0.0
+		};
+
+		let pad = [ 220.0, 277.2, 329.6 ].iter().map(|f| (2.0 * PI * f * time).sin()).sum::<f32>() / 3.0;
+
+		let mix = kick * 0.5 + snare * 0.25 + hat * 0.12 + bass * 0.2 + pad * 0.06 + noise.next() * 0.01;
+
+		(mix * 32767.0) as i16
+	}).collect()
+}
+
+/// Pink-ish noise with no beat at all, which beat detection should find no tempo in
+pub fn rumble(seconds: f32) -> Vec<i16> {
+	let length = (seconds * SAMPLE_RATE as f32) as usize;
+	let mut noise = Noise(7);
+	let mut low = 0.0;
+
+	(0..length).map(|_| {
+		let white = noise.next();
+
+		low = low * 0.95 + white * 0.05;
+
+		((low * 2.0 + white * 0.1) * 0.3 * 32767.0) as i16
+	}).collect()
+}
@@ -0,0 +1,20 @@
+//! Just enough of zinc's API for the firmware's `Cube4` to build and run on a PC. Flushed frames go
+//! nowhere; the simulator reads voxels back out of the cube instead.
+
+pub mod hal {
+	pub mod spi {
+		pub trait Spi {
+			fn write(&self, value: u8);
+		}
+	}
+
+	pub mod tiva_c {
+		pub mod spi {
+			pub struct Spi;
+
+			impl ::zinc::hal::spi::Spi for Spi {
+				fn write(&self, _value: u8) {}
+			}
+		}
+	}
+}
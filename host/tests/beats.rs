@@ -0,0 +1,98 @@
+//! Beat tracking against generated drum loops with known tempos
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::beats::{ self, Report };
+use zinc_cube_host::wav;
+
+/// Time the tracker gets to settle before it's judged
+const SETTLE_US: u64 = 8_000_000;
+
+const LOOP_SECONDS: f32 = 20.0;
+
+/// How far out a tempo can be and still pass
+const BPM_TOLERANCE: i32 = 2;
+
+/// How far the beat clock can drift from the beats, out of 256
+const MAX_PHASE_ERROR: u8 = 32;
+
+/// Reports from once the tracker has settled
+fn settled(samples: Vec<i16>) -> Vec<Report> {
+	let reports: Vec<Report> = beats::track(samples).into_iter().filter(|report| report.time_us >= SETTLE_US).collect();
+
+	assert!(reports.len() > 2, "not enough audio");
+
+	reports
+}
+
+fn check_tempo(bpm: u16) {
+	let reports = settled(wav::drum_loop(bpm as f32, LOOP_SECONDS));
+	let (first, last) = (reports.first().unwrap(), reports.last().unwrap());
+
+	for report in reports.iter() {
+		assert!((report.bpm as i32 - bpm as i32).abs() <= BPM_TOLERANCE,
+			"{}BPM heard as {}BPM at {}ms", bpm, report.bpm, report.time_us / 1000);
+
+		assert!(report.phase_error(bpm) <= MAX_PHASE_ERROR,
+			"{}BPM beat clock {}/256 out at {}ms", bpm, report.phase_error(bpm), report.time_us / 1000);
+	}
+
+	// Counting beats at the right rate, not just reporting the right tempo
+	let seconds = (last.time_us - first.time_us) as f32 / 1_000_000.0;
+	let beats = last.count.wrapping_sub(first.count) as f32;
+	let expected = seconds * bpm as f32 / 60.0;
+
+	assert!((beats - expected).abs() <= 1.5, "{}BPM counted {} beats in {:.1}s, expected {:.1}", bpm, beats, seconds, expected);
+
+	// Every beat of the loop has a kick drum. The snares on two and four count as kicks too.
+	let kicks: u32 = reports.iter().skip(1).map(|report| report.kicks).sum();
+
+	assert!(kicks as f32 >= expected - 2.0, "{}BPM heard {} kicks in {:.1}s, expected at least {:.1}", bpm, kicks, seconds, expected);
+}
+
+#[test]
+fn drums_85() {
+	check_tempo(85);
+}
+
+#[test]
+fn drums_90() {
+	check_tempo(90);
+}
+
+#[test]
+fn drums_100() {
+	check_tempo(100);
+}
+
+#[test]
+fn drums_110() {
+	check_tempo(110);
+}
+
+#[test]
+fn drums_120() {
+	check_tempo(120);
+}
+
+#[test]
+fn drums_128() {
+	check_tempo(128);
+}
+
+#[test]
+fn drums_140() {
+	check_tempo(140);
+}
+
+#[test]
+fn drums_150() {
+	check_tempo(150);
+}
+
+#[test]
+fn rumble_has_no_tempo() {
+	for report in settled(wav::rumble(LOOP_SECONDS)).iter() {
+		assert_eq!(report.bpm, 0, "heard {}BPM at {}ms", report.bpm, report.time_us / 1000);
+	}
+}
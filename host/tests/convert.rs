@@ -0,0 +1,116 @@
+//! Generating Rust tables with `cube_convert`
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::apa106led::{ Apa106Led, OFF };
+use zinc_cube_host::convert;
+use zinc_cube_host::playback::FrameDecoder;
+use zinc_cube_host::recording;
+
+const HEADER: &str = "frame,x,y,z,r,g,b,delay\n";
+
+/// `rows` under the usual header
+fn csv(rows: &str) -> Result<(), String> {
+	convert::parse_csv(&format!("{}{}", HEADER, rows)).map(|_| ())
+}
+
+/// A one-frame JSON animation with a single voxel described by `voxel`
+fn json_voxel(voxel: &str) -> Result<(), String> {
+	convert::parse_json(&format!("[ {{ \"delay\": 40, \"voxels\": [ {} ] }} ]", voxel)).map(|_| ())
+}
+
+#[test]
+fn table_is_static() {
+	let frames = convert::parse_csv("frame,x,y,z,r,g,b,delay\n0,0,0,0,1,2,3,40\n1,3,3,3,4,5,6,50\n").unwrap();
+	let table = convert::to_rust_table("SPARKLE", "sparkle.csv", &frames);
+
+	// In flash, rather than copied onto the stack wherever it's used
+	assert!(table.contains("pub static SPARKLE: [TableFrame; 2] = ["));
+	assert!(!table.contains("const"));
+
+	assert!(table.contains("delay_ms: 50,"));
+	assert!(table.contains("Apa106Led { red: 4, green: 5, blue: 6 },"));
+}
+
+#[test]
+fn csv_outside_the_cube_is_rejected() {
+	assert!(csv("0,3,3,3,255,255,255,40\n").is_ok());
+
+	for row in [ "0,4,0,0,1,1,1,40", "0,0,4,0,1,1,1,40", "0,0,0,4,1,1,1,40", "0,-1,0,0,1,1,1,40" ].iter() {
+		assert!(csv(row).is_err(), "{}", row);
+	}
+
+	for row in [ "0,0,0,0,256,0,0,40", "0,0,0,0,0,256,0,40", "0,0,0,0,0,0,256,40", "0,0,0,0,-1,0,0,40" ].iter() {
+		assert!(csv(row).is_err(), "{}", row);
+	}
+}
+
+#[test]
+fn csv_malformed_rows_are_rejected() {
+	for row in [ "0,0,0,0,1,1,40", "0,0,0,0,1,1,1,40,9", "0,0,0,0,1,x,1,40", "0,0,0,0,1,1,1,", "0,0,0,0,1,1.5,1,40" ].iter() {
+		let error = csv(row).unwrap_err();
+
+		// Pointing at the row after the header
+		assert!(error.starts_with("line 2:"), "{}: {}", row, error);
+	}
+}
+
+#[test]
+fn csv_frames_must_agree() {
+	// Different delays within one frame
+	assert!(csv("0,0,0,0,1,1,1,40\n0,1,0,0,1,1,1,50\n").is_err());
+
+	// Frame 1 missing
+	let error = csv("0,0,0,0,1,1,1,40\n2,0,0,0,1,1,1,40\n").unwrap_err();
+
+	assert!(error.contains("frame 1 has no rows"), "{}", error);
+
+	// Order doesn't matter, though
+	assert!(csv("1,0,0,0,1,1,1,50\n0,0,0,0,1,1,1,40\n").is_ok());
+}
+
+#[test]
+fn json_is_checked() {
+	assert!(json_voxel("{ \"x\": 3, \"y\": 3, \"z\": 3, \"r\": 255, \"g\": 255, \"b\": 255 }").is_ok());
+
+	for voxel in [
+		"{ \"x\": 4, \"y\": 0, \"z\": 0, \"r\": 1, \"g\": 1, \"b\": 1 }",
+		"{ \"x\": 0, \"y\": 0, \"z\": -1, \"r\": 1, \"g\": 1, \"b\": 1 }",
+		"{ \"x\": 0, \"y\": 0, \"z\": 0, \"r\": 1, \"g\": 300, \"b\": 1 }",
+		"{ \"x\": 0, \"y\": 0, \"z\": 0, \"r\": 1, \"g\": 1 }",
+		"{ \"x\": \"0\", \"y\": 0, \"z\": 0, \"r\": 1, \"g\": 1, \"b\": 1 }",
+	].iter() {
+		assert!(json_voxel(voxel).is_err(), "{}", voxel);
+	}
+
+	for text in [ "", "[", "[ { \"delay\": 40, \"voxels\": [] }, ]", "{ \"delay\": 40, \"voxels\": [] }", "[]" ].iter() {
+		assert!(convert::parse_json(text).is_err(), "{:?}", text);
+	}
+}
+
+#[test]
+fn json_plays_back() {
+	let text = "[
+		{ \"delay\": 40, \"voxels\": [ { \"x\": 0, \"y\": 0, \"z\": 0, \"r\": 255, \"g\": 0, \"b\": 0 } ] },
+		{ \"delay\": 40, \"voxels\": [ { \"x\": 1, \"y\": 2, \"z\": 3, \"r\": 0, \"g\": 9, \"b\": 0 }, { \"x\": 3, \"y\": 0, \"z\": 1, \"r\": 1, \"g\": 2, \"b\": 3 } ] },
+		{ \"delay\": 500, \"voxels\": [] }
+	]";
+
+	let frames = convert::parse_json(text).unwrap();
+	let data = recording::encode(&frames).unwrap();
+	let mut decoder = FrameDecoder::new(&data).unwrap();
+	let mut voxels = [OFF; 64];
+
+	let mut expected = [[OFF; 64]; 3];
+
+	expected[0][0] = Apa106Led { red: 255, green: 0, blue: 0 };
+	expected[1][1 + 2 * 4 + 3 * 16] = Apa106Led { red: 0, green: 9, blue: 0 };
+	expected[1][3 + 16] = Apa106Led { red: 1, green: 2, blue: 3 };
+
+	for (index, (expected, &delay_ms)) in expected.iter().zip([ 40, 40, 500 ].iter()).enumerate() {
+		assert_eq!(decoder.next_frame(&mut voxels).unwrap(), Some(delay_ms), "frame {}", index);
+		assert!(voxels[..] == expected[..], "frame {}", index);
+	}
+
+	assert_eq!(decoder.next_frame(&mut voxels).unwrap(), None);
+}
@@ -0,0 +1,252 @@
+//! Fixed point maths against f64, with errors in units of the last place (the smallest step the format
+//! can hold)
+
+extern crate zinc_cube_host;
+
+use std::f64::consts::PI;
+
+use zinc_cube_host::colour_functions::{ blackbody, temp_to_rgb };
+use zinc_cube_host::fixed::{ self, Fixed8, Fixed16, FIXED16_ONE, FIXED8_ONE };
+use zinc_cube_host::procedural;
+
+const SAMPLES: usize = 100_000;
+
+const Q16_LSB: f64 = FIXED16_ONE as f64;
+const Q8_LSB: f64 = FIXED8_ONE as f64;
+
+/// How much more than half a step exponents and powers can be out by, as a fraction of the result.
+/// Their Q2.30 working loses a little each time it's multiplied.
+const EXP_RELATIVE: f64 = 1e-8;
+
+/// Small deterministic generator, so every run checks the same numbers
+struct Random(u64);
+
+impl Random {
+	fn next(&mut self) -> u32 {
+		self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+
+		(self.0 >> 32) as u32
+	}
+
+	/// Evenly spread between `low` and `high`
+	fn range(&mut self, low: f64, high: f64) -> f64 {
+		low + (high - low) * self.next() as f64 / u32::MAX as f64
+	}
+}
+
+fn q16(value: f64) -> Fixed16 {
+	Fixed16((value * Q16_LSB).round() as i32)
+}
+
+fn f16(value: Fixed16) -> f64 {
+	value.0 as f64 / Q16_LSB
+}
+
+fn q8(value: f64) -> Fixed8 {
+	Fixed8((value * Q8_LSB).round() as i16)
+}
+
+fn f8(value: Fixed8) -> f64 {
+	value.0 as f64 / Q8_LSB
+}
+
+/// Check `samples` cases, each giving the fixed point result and the exact one in units of the last
+/// place, are within `limit` plus `relative` times the exact value
+fn check<F: FnMut(&mut Random) -> (f64, f64)>(name: &str, limit: f64, relative: f64, samples: usize, mut case: F) {
+	let mut random = Random(0x5EED);
+
+	for _ in 0..samples {
+		let (result, exact) = case(&mut random);
+		let error = (result - exact).abs();
+
+		assert!(error <= limit + relative * exact.abs(), "{} gave {} for {}, {} out", name, result, exact, error);
+	}
+}
+
+/// A Q16.16 whose log is spread evenly over every power of two, not just the big ones
+fn any_power_of_two(random: &mut Random) -> Fixed16 {
+	Fixed16((random.range(-16.0, 15.0).exp2() * Q16_LSB).max(1.0) as i32)
+}
+
+#[test]
+fn q16_arithmetic() {
+	check("mul", 0.5, 0.0, SAMPLES, |random| {
+		let (a, b) = (q16(random.range(-150.0, 150.0)), q16(random.range(-150.0, 150.0)));
+
+		(f16(a * b) * Q16_LSB, f16(a) * f16(b) * Q16_LSB)
+	});
+
+	check("div", 0.5, 0.0, SAMPLES, |random| {
+		let (a, b) = (q16(random.range(-1000.0, 1000.0)), q16(random.range(0.1, 100.0)));
+
+		(f16(a / b) * Q16_LSB, f16(a) / f16(b) * Q16_LSB)
+	});
+
+	check("lerp", 1.0, 0.0, SAMPLES, |random| {
+		let (a, b, t) = (q16(random.range(-1000.0, 1000.0)), q16(random.range(-1000.0, 1000.0)), q16(random.range(0.0, 1.0)));
+
+		(f16(Fixed16::lerp(a, b, t)) * Q16_LSB, (f16(a) + (f16(b) - f16(a)) * f16(t)) * Q16_LSB)
+	});
+}
+
+#[test]
+fn q16_sqrt() {
+	check("sqrt", 0.5, 0.0, SAMPLES, |random| {
+		let a = q16(random.range(0.0, 32767.0));
+
+		(f16(a.sqrt()) * Q16_LSB, f16(a).sqrt() * Q16_LSB)
+	});
+}
+
+#[test]
+fn sin_and_cos() {
+	// Every angle there is
+	for angle in 0..65536u32 {
+		let turns = angle as f64 * 2.0 * PI / 65536.0;
+		let sin = f16(fixed::sin(angle as u16)) * Q16_LSB;
+		let cos = f16(fixed::cos(angle as u16)) * Q16_LSB;
+
+		assert!((sin - turns.sin() * Q16_LSB).abs() <= 2.5, "sin({}) out by {}", angle, sin - turns.sin() * Q16_LSB);
+		assert!((cos - turns.cos() * Q16_LSB).abs() <= 2.5, "cos({}) out by {}", angle, cos - turns.cos() * Q16_LSB);
+	}
+}
+
+#[test]
+fn byte_sine() {
+	// Patterns' sine is the same one, in 256ths of a turn
+	for angle in 0..256u32 {
+		let exact = (angle as f64 * 2.0 * PI / 256.0).sin() * 127.0;
+
+		assert!((procedural::sine(angle as u8) as f64 - exact).abs() <= 0.5, "sine({}) gave {} for {}", angle, procedural::sine(angle as u8), exact);
+	}
+}
+
+#[test]
+fn logs() {
+	check("log2", 1.5, 0.0, SAMPLES, |random| {
+		let a = any_power_of_two(random);
+
+		(f16(a.log2()) * Q16_LSB, f16(a).log2() * Q16_LSB)
+	});
+
+	check("ln", 1.5, 0.0, SAMPLES, |random| {
+		let a = any_power_of_two(random);
+
+		(f16(a.ln()) * Q16_LSB, f16(a).ln() * Q16_LSB)
+	});
+}
+
+#[test]
+fn exp2() {
+	check("exp2", 0.5, EXP_RELATIVE, SAMPLES, |random| {
+		let a = q16(random.range(-16.0, 14.99));
+
+		(f16(a.exp2()) * Q16_LSB, f16(a).exp2() * Q16_LSB)
+	});
+}
+
+#[test]
+fn exp() {
+	check("exp", 0.5, EXP_RELATIVE, SAMPLES, |random| {
+		let a = q16(random.range(-11.0, 10.39));
+
+		(f16(a.exp()) * Q16_LSB, f16(a).exp() * Q16_LSB)
+	});
+}
+
+#[test]
+fn pow() {
+	check("pow", 0.5, EXP_RELATIVE, SAMPLES, |random| {
+		let (a, b) = (q16(random.range(0.5, 200.0)), q16(random.range(-1.0, 1.0)));
+
+		(f16(a.pow(b)) * Q16_LSB, f16(a).powf(f16(b)) * Q16_LSB)
+	});
+
+	// Big exponents, of small numbers, which multiply any error in the log too
+	check("pow", 0.5, 20.0 * EXP_RELATIVE, SAMPLES, |random| {
+		let (a, b) = (q16(random.range(1.01, 1.5)), q16(random.range(-20.0, 20.0)));
+
+		(f16(a.pow(b)) * Q16_LSB, f16(a).powf(f16(b)) * Q16_LSB)
+	});
+}
+
+#[test]
+fn q8_arithmetic() {
+	check("mul", 0.5, 0.0, SAMPLES, |random| {
+		let (a, b) = (q8(random.range(-11.0, 11.0)), q8(random.range(-11.0, 11.0)));
+
+		(f8(a * b) * Q8_LSB, f8(a) * f8(b) * Q8_LSB)
+	});
+
+	check("div", 0.5, 0.0, SAMPLES, |random| {
+		let (a, b) = (q8(random.range(-100.0, 100.0)), q8(random.range(1.0, 100.0)));
+
+		(f8(a / b) * Q8_LSB, f8(a) / f8(b) * Q8_LSB)
+	});
+
+	check("lerp", 1.0, 0.0, SAMPLES, |random| {
+		let (a, b, t) = (q8(random.range(-60.0, 60.0)), q8(random.range(-60.0, 60.0)), q8(random.range(0.0, 1.0)));
+
+		(f8(Fixed8::lerp(a, b, t)) * Q8_LSB, (f8(a) + (f8(b) - f8(a)) * f8(t)) * Q8_LSB)
+	});
+
+	check("scale", 0.5, 0.0, SAMPLES, |random| {
+		let (a, value) = (q8(random.range(0.0, 1.0)), (random.next() & 0xff) as u8);
+
+		(a.scale(value) as f64, f8(a) * value as f64)
+	});
+}
+
+/// `temp_to_rgb`'s formula in f64
+fn exact_temp_to_rgb(kelvin: u32) -> [f64; 3] {
+	let channel = |value: f64| value.clamp(0.0, 255.0).floor();
+	let temp = kelvin as f64 / 100.0;
+
+	if temp <= 66.0 {
+		[
+			255.0,
+			channel(99.4708025861 * temp.ln() - 161.1195681661),
+			if temp <= 19.0 { 0.0 } else { channel(138.5177312231 * (temp - 10.0).ln() - 305.0447927307) },
+		]
+	} else {
+		[
+			channel(329.698727446 * (temp - 60.0).powf(-0.1332047592)),
+			channel(288.1221695283 * (temp - 60.0).powf(-0.0755148492)),
+			255.0,
+		]
+	}
+}
+
+#[test]
+fn colour_temperatures() {
+	// Rounding down can still go either way when the exact value is right on a level
+	for kelvin in 500..16_001 {
+		let colour = temp_to_rgb(kelvin);
+		let exact = exact_temp_to_rgb(kelvin);
+
+		for (&channel, &exact) in [ colour.red, colour.green, colour.blue ].iter().zip(exact.iter()) {
+			assert!((channel as f64 - exact).abs() <= 1.0, "{}K gave {} for {}", kelvin, channel, exact);
+		}
+	}
+}
+
+#[test]
+fn blackbody_table() {
+	// The table is the formula exactly, and `temp_to_rgb` is within a level of it
+	for kelvin in (500..16_001).step_by(20) {
+		let colour = blackbody(kelvin);
+		let exact = exact_temp_to_rgb(kelvin);
+		let computed = temp_to_rgb(kelvin);
+
+		assert!([ colour.red as f64, colour.green as f64, colour.blue as f64 ] == exact, "{}K", kelvin);
+
+		for &(table, computed) in [ (colour.red, computed.red), (colour.green, computed.green), (colour.blue, computed.blue) ].iter() {
+			assert!((table as i32 - computed as i32).abs() <= 1, "{}K gave {} from the table and {} from the formula", kelvin, table, computed);
+		}
+	}
+
+	// In between it's the step below
+	assert!(blackbody(1519) == blackbody(1500));
+	assert!(blackbody(100) == blackbody(500));
+	assert!(blackbody(20_000) == blackbody(16_000));
+}
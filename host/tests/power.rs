@@ -0,0 +1,124 @@
+//! Powering the LEDs down for blank frames, and sleeping and waking
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::power::{ Power, State, BLANK_US, WAKE_IGNORE_US };
+
+/// Time between frames, about 30FPS
+const FRAME_US: u32 = 33_000;
+
+/// Timer counts to start from. The timer wraps every 71 minutes, so just before it does as well.
+const STARTS: [u32; 2] = [ 0, u32::MAX - BLANK_US ];
+
+/// Show frames from `start` until `end`, returning when the LEDs were powered down if they were
+fn frames(power: &mut Power, blank: bool, start: u32, end: u32) -> Option<u32> {
+	let mut now = start;
+
+	while now.wrapping_sub(start) < end.wrapping_sub(start) {
+		power.frame(blank, now);
+
+		if !power.led_power() {
+			return Some(now.wrapping_sub(start));
+		}
+
+		now = now.wrapping_add(FRAME_US);
+	}
+
+	None
+}
+
+#[test]
+fn starts_on() {
+	let power = Power::new();
+
+	assert!(power.state() == State::On);
+	assert!(power.led_power());
+}
+
+#[test]
+fn lit_frame_restarts_the_wait() {
+	for &start in STARTS.iter() {
+		let mut power = Power::new();
+
+		// Blank frames, broken up by a lit one just before they'd power the LEDs down
+		assert_eq!(frames(&mut power, true, start, start.wrapping_add(BLANK_US - FRAME_US)), None);
+
+		power.frame(false, start.wrapping_add(BLANK_US - FRAME_US));
+
+		assert!(power.led_power());
+		assert_eq!(frames(&mut power, true, start.wrapping_add(BLANK_US), start.wrapping_add(BLANK_US + BLANK_US / 2)), None);
+	}
+}
+
+#[test]
+fn blank_frames_power_down() {
+	for &start in STARTS.iter() {
+		let mut power = Power::new();
+
+		match frames(&mut power, true, start, start.wrapping_add(2 * BLANK_US)) {
+			Some(us) => assert!((BLANK_US..BLANK_US + FRAME_US).contains(&us), "from {}, dark after {}ms", start, us / 1000),
+			None => panic!("from {}, never dark", start),
+		}
+
+		assert!(power.state() == State::Dark);
+
+		power.frame(true, start.wrapping_add(3 * BLANK_US));
+
+		assert!(power.state() == State::Dark);
+
+		// Lit frames power the LEDs straight back up
+		power.frame(false, start.wrapping_add(3 * BLANK_US + FRAME_US));
+
+		assert!(power.state() == State::On);
+		assert!(power.led_power());
+	}
+}
+
+#[test]
+fn turn_off_and_wake() {
+	for &start in STARTS.iter() {
+		let mut power = Power::new();
+
+		// Waking while on does nothing
+		power.wake(start);
+
+		assert!(power.state() == State::On);
+		assert!(power.accepts_actions(start));
+
+		power.turn_off();
+
+		assert!(power.is_asleep());
+		assert!(!power.led_power());
+
+		power.frame(false, start.wrapping_add(FRAME_US));
+
+		assert!(power.is_asleep());
+
+		let woken = start.wrapping_add(60_000_000);
+
+		power.wake(woken);
+
+		assert!(power.state() == State::On);
+		assert!(power.led_power());
+
+		// The press that woke it does nothing else
+		assert!(!power.accepts_actions(woken.wrapping_add(WAKE_IGNORE_US - 1000)));
+		assert!(power.accepts_actions(woken.wrapping_add(WAKE_IGNORE_US)));
+	}
+}
+
+#[test]
+fn woken_from_dark() {
+	for &start in STARTS.iter() {
+		let mut power = Power::new();
+
+		// Turned off where the LEDs are already powered down
+		frames(&mut power, true, start, start.wrapping_add(2 * BLANK_US));
+		power.turn_off();
+		power.wake(start.wrapping_add(3 * BLANK_US));
+		power.frame(true, start.wrapping_add(3 * BLANK_US));
+
+		assert!(power.state() == State::On);
+		assert!(power.led_power());
+	}
+}
@@ -0,0 +1,302 @@
+//! The LED matrix protocols in `protocols`, one parser at a time and all together in `FrameReceiver`
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::apa106led::{ Apa106Led, OFF };
+use zinc_cube_host::protocols::{ AdaParser, FrameReceiver, GlediatorParser, Protocol, Tpm2Parser };
+use zinc_cube_host::stream::{ encode_packet, PACKET_LEN };
+
+/// A frame with every channel different. Values stay between 0x02 and 0x31, clear of every protocol's
+/// start, sync and end bytes, as real senders keep them clear of Glediator's.
+fn test_frame(seed: u8) -> [Apa106Led; 64] {
+	let mut frame = [OFF; 64];
+
+	for (index, voxel) in frame.iter_mut().enumerate() {
+		let channel = |offset: usize| ((index * 3 + offset + seed as usize) % 0x30 + 0x02) as u8;
+
+		*voxel = Apa106Led { red: channel(0), green: channel(1), blue: channel(2) };
+	}
+
+	frame
+}
+
+fn rgb(frame: &[Apa106Led; 64], pixels: usize) -> Vec<u8> {
+	frame[..pixels].iter().flat_map(|voxel| vec![ voxel.red, voxel.green, voxel.blue ]).collect()
+}
+
+fn tpm2(frame: &[Apa106Led; 64]) -> Vec<u8> {
+	let mut bytes = vec![ 0xC9, 0xDA, 0x00, 0xC0 ];
+
+	bytes.extend(rgb(frame, 64));
+	bytes.push(0x36);
+
+	bytes
+}
+
+fn ada(frame: &[Apa106Led; 64], pixels: usize) -> Vec<u8> {
+	let high = ((pixels - 1) >> 8) as u8;
+	let low = ((pixels - 1) & 0xff) as u8;
+
+	let mut bytes = vec![ b'A', b'd', b'a', high, low, high ^ low ^ 0x55 ];
+
+	bytes.extend(rgb(frame, pixels));
+
+	bytes
+}
+
+fn glediator(frame: &[Apa106Led; 64]) -> Vec<u8> {
+	let mut bytes = vec![ 0x01 ];
+
+	bytes.extend(rgb(frame, 64));
+
+	bytes
+}
+
+/// Push `bytes` into anything with `push()` and `frame()`, returning the frames that came out
+macro_rules! parse {
+	($parser:expr, $bytes:expr) => {{
+		let mut frames: Vec<[Apa106Led; 64]> = Vec::new();
+
+		for &byte in $bytes.iter() {
+			if $parser.push(byte) {
+				frames.push(*$parser.frame());
+			}
+		}
+
+		frames
+	}};
+}
+
+#[test]
+fn tpm2_frame() {
+	let mut parser = Tpm2Parser::new();
+	let frame = test_frame(0);
+
+	let frames = parse!(parser, tpm2(&frame));
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == frame);
+}
+
+#[test]
+fn tpm2_bad_header_and_end() {
+	let mut parser = Tpm2Parser::new();
+
+	// A command packet rather than a data frame
+	let mut command = tpm2(&test_frame(1));
+	command[1] = 0xC0;
+
+	assert!(parse!(parser, command).is_empty());
+
+	let mut bad_end = tpm2(&test_frame(1));
+	*bad_end.last_mut().unwrap() = 0x37;
+
+	assert!(parse!(parser, bad_end).is_empty());
+
+	// Still in step for the next good one
+	assert_eq!(parse!(parser, tpm2(&test_frame(2))).len(), 1);
+}
+
+#[test]
+fn tpm2_truncated() {
+	let mut parser = Tpm2Parser::new();
+	let frame = test_frame(3);
+
+	// The truncated frame swallows the start of the next one, so neither gets through, but the one
+	// after that does
+	let mut bytes = tpm2(&test_frame(4));
+	bytes.truncate(100);
+	bytes.extend(tpm2(&frame));
+	bytes.extend(tpm2(&frame));
+
+	let frames = parse!(parser, bytes);
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == frame);
+}
+
+#[test]
+fn ada_frame() {
+	let mut parser = AdaParser::new();
+	let frame = test_frame(5);
+
+	let frames = parse!(parser, ada(&frame, 64));
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == frame);
+
+	// Fewer pixels leave the rest off
+	let frames = parse!(parser, ada(&frame, 10));
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0][..10] == frame[..10]);
+	assert!(frames[0][10..].iter().all(|&voxel| voxel == OFF));
+}
+
+#[test]
+fn ada_bad_checksum() {
+	let mut parser = AdaParser::new();
+
+	let mut bytes = ada(&test_frame(6), 64);
+	bytes[5] ^= 0x01;
+
+	assert!(parse!(parser, bytes).is_empty());
+
+	assert_eq!(parse!(parser, ada(&test_frame(6), 64)).len(), 1);
+}
+
+#[test]
+fn ada_truncated() {
+	let mut parser = AdaParser::new();
+	let frame = test_frame(7);
+
+	let mut bytes = ada(&test_frame(8), 64);
+	bytes.truncate(50);
+	bytes.extend(ada(&frame, 64));
+	bytes.extend(ada(&frame, 64));
+
+	let frames = parse!(parser, bytes);
+
+	// Whatever the truncated frame ends up holding, the last one out is the right one
+	assert!(!frames.is_empty());
+	assert!(*frames.last().unwrap() == frame);
+}
+
+#[test]
+fn glediator_needs_two_starts() {
+	let mut parser = GlediatorParser::new();
+	let first = test_frame(9);
+	let second = test_frame(10);
+
+	// A single start byte and 192 bytes could be anything
+	assert!(parse!(parser, glediator(&first)).is_empty());
+
+	// Another start byte straight after shows it really was a frame, and so is this one
+	let frames = parse!(parser, glediator(&second));
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == second);
+}
+
+#[test]
+fn glediator_loses_sync() {
+	let mut parser = GlediatorParser::new();
+	let frame = test_frame(11);
+
+	let mut bytes = glediator(&frame);
+	bytes.extend(glediator(&frame));
+
+	assert_eq!(parse!(parser, bytes).len(), 1);
+
+	// A truncated frame loses sync, and the next frame only sets it up again
+	let mut bytes = glediator(&frame);
+	bytes.truncate(100);
+	bytes.extend(glediator(&frame));
+
+	assert!(parse!(parser, bytes).is_empty());
+
+	assert_eq!(parse!(parser, glediator(&frame)).len(), 1);
+
+	// So does anything other than a start byte after a frame
+	let mut bytes = vec![ 0x20 ];
+	bytes.extend(glediator(&frame));
+
+	assert!(parse!(parser, bytes).is_empty());
+}
+
+#[test]
+fn receiver_every_protocol() {
+	let mut receiver = FrameReceiver::new();
+
+	let mut packet = [0; PACKET_LEN];
+	encode_packet(&test_frame(12), &mut packet);
+
+	let cases = [
+		(packet.to_vec(), Protocol::Stream, test_frame(12)),
+		(tpm2(&test_frame(13)), Protocol::Tpm2, test_frame(13)),
+		(ada(&test_frame(14), 64), Protocol::Ada, test_frame(14)),
+	];
+
+	for (bytes, protocol, frame) in cases.iter() {
+		let frames = parse!(receiver, bytes);
+
+		assert_eq!(frames.len(), 1);
+		assert!(frames[0] == *frame);
+		assert!(receiver.protocol() == *protocol);
+	}
+
+	let mut bytes = glediator(&test_frame(15));
+	bytes.extend(glediator(&test_frame(16)));
+
+	let frames = parse!(receiver, bytes);
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == test_frame(16));
+	assert!(receiver.protocol() == Protocol::Glediator);
+}
+
+#[test]
+fn console_text_is_not_a_frame() {
+	let mut receiver = FrameReceiver::new();
+
+	let lines = [
+		"text Ada Lovelace\r\n", "text Ada", "\r\n", "brightness 4\r\n", "playlist 1 3 5-9\r\n",
+		"time 2024-12-24 18:30\r\n", "text ZZZ Ada\x01\x01 zap\r\n", "settings\r\n", "off\r\n",
+	];
+
+	let mut text = Vec::new();
+
+	for _ in 0..20 {
+		for line in lines.iter() {
+			text.extend_from_slice(line.as_bytes());
+		}
+	}
+
+	assert!(parse!(receiver, text).is_empty());
+
+	// Text in between frames doesn't stop them getting through
+	let frame = test_frame(17);
+	let mut bytes = tpm2(&frame);
+	bytes.extend_from_slice(b"settings\r\n");
+	bytes.extend(tpm2(&frame));
+
+	let frames = parse!(receiver, bytes);
+
+	assert_eq!(frames.len(), 2);
+	assert!(frames.iter().all(|received| *received == frame));
+}
+
+#[test]
+fn receiver_is_busy_only_inside_frames() {
+	let mut receiver = FrameReceiver::new();
+
+	// Typing, including text that starts out looking like a header
+	for &byte in b"text Ada Lovelace\r\ntext ZZZ\r\nbrightness 4\r\n".iter() {
+		receiver.push(byte);
+
+		assert!(receiver.is_idle(), "busy after {:?}", byte as char);
+	}
+
+	let mut packet = [0; PACKET_LEN];
+	encode_packet(&test_frame(18), &mut packet);
+
+	for bytes in [ packet.to_vec(), tpm2(&test_frame(19)), ada(&test_frame(20), 64), glediator(&test_frame(21)) ].iter() {
+		let (last, body) = bytes.split_last().unwrap();
+		let mut busy = 0;
+
+		for &byte in body.iter() {
+			assert!(!receiver.push(byte));
+
+			if !receiver.is_idle() {
+				busy += 1;
+			}
+		}
+
+		// Busy from the end of the header to the end of the frame, and idle again after it
+		assert!(busy >= 64 * 3, "only busy for {} of {} bytes", busy, bytes.len());
+
+		receiver.push(*last);
+
+		assert!(receiver.is_idle());
+	}
+}
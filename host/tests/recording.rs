@@ -0,0 +1,194 @@
+//! The recorded animation format: `recording::encode` on the host, `playback::FrameDecoder` and
+//! `Playback` in the firmware
+
+extern crate zinc_cube_host;
+
+use std::fs;
+
+use zinc_cube_host::apa106led::{ Apa106Led, OFF };
+use zinc_cube_host::convert;
+use zinc_cube_host::playback::{ self, Playback, PlaybackError, FLAG_DELAY, FLAG_DELTA, HEADER_LEN };
+use zinc_cube_host::recording;
+use zinc_cube_host::sim::{ self, Frame, Simulator };
+
+/// Every voxel a different colour, so key frames have no runs to speak of
+fn busy_frame(seed: u8) -> [Apa106Led; 64] {
+	let mut voxels = [OFF; 64];
+
+	for (index, voxel) in voxels.iter_mut().enumerate() {
+		let base = (index as u8).wrapping_mul(37).wrapping_add(seed);
+
+		*voxel = Apa106Led { red: base, green: base ^ 0x55, blue: base.wrapping_mul(3) };
+	}
+
+	voxels
+}
+
+fn frames_equal(a: &[Frame], b: &[Frame]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.voxels == b.voxels && a.delay_ms == b.delay_ms)
+}
+
+/// Flags byte of each frame in an encoding, stepping over the bodies by the layout in `playback`
+fn frame_flags(data: &[u8], frame_count: usize) -> Vec<u8> {
+	let mut position = HEADER_LEN;
+	let mut flags = Vec::new();
+
+	for _ in 0..frame_count {
+		let frame_flags = data[position];
+		position += 1;
+
+		if frame_flags & FLAG_DELAY != 0 {
+			position += 2;
+		}
+
+		let mut voxel = 0;
+
+		while voxel < 64 {
+			if frame_flags & FLAG_DELTA != 0 {
+				let (skip, count) = (data[position] as usize, data[position + 1] as usize);
+
+				position += 2 + count * 3;
+				voxel += skip + count;
+			} else {
+				voxel += data[position] as usize;
+				position += 4;
+			}
+		}
+
+		flags.push(frame_flags);
+	}
+
+	assert_eq!(position, data.len());
+
+	flags
+}
+
+#[test]
+fn key_frames() {
+	let mut single = [OFF; 64];
+	single[21] = Apa106Led { red: 1, green: 2, blue: 3 };
+
+	// Each cheaper as runs than as changes from the one before
+	let frames = vec![
+		Frame { voxels: busy_frame(0), delay_ms: 40 },
+		Frame { voxels: [OFF; 64], delay_ms: 40 },
+		Frame { voxels: [Apa106Led { red: 9, green: 8, blue: 7 }; 64], delay_ms: 40 },
+		Frame { voxels: single, delay_ms: 40 },
+	];
+
+	let data = recording::encode(&frames).unwrap();
+
+	assert!(frames_equal(&recording::decode(&data).unwrap(), &frames));
+	assert!(frame_flags(&data, frames.len()).iter().all(|&flags| flags & FLAG_DELTA == 0));
+
+	// A blank frame is a single run
+	let blank = recording::encode(&frames[1..2]).unwrap();
+
+	assert_eq!(&blank[HEADER_LEN..], &[ 0, 64, 0, 0, 0 ]);
+}
+
+#[test]
+fn delta_frames() {
+	let mut frames = vec![ Frame { voxels: busy_frame(1), delay_ms: 30 } ];
+
+	// Change a few voxels at a time, including the first and last
+	for &changed in [ 0usize, 63, 17, 18, 40 ].iter() {
+		let mut voxels = frames.last().unwrap().voxels;
+
+		voxels[changed] = Apa106Led { red: 200, green: 100, blue: changed as u8 };
+		frames.push(Frame { voxels, delay_ms: 30 });
+	}
+
+	// And one that doesn't change anything
+	let unchanged = *frames.last().unwrap();
+	frames.push(unchanged);
+
+	let data = recording::encode(&frames).unwrap();
+
+	assert!(frames_equal(&recording::decode(&data).unwrap(), &frames));
+
+	let flags = frame_flags(&data, frames.len());
+
+	assert_eq!(flags[0] & FLAG_DELTA, 0);
+	assert!(flags[1..].iter().all(|&flags| flags & FLAG_DELTA != 0));
+
+	// Much smaller than storing them all as key frames
+	assert!(data.len() < HEADER_LEN + 2 * (1 + 64 * 4));
+}
+
+#[test]
+fn delays() {
+	let delays = [ 40, 40, 100, 40, 0, 40, 65535 ];
+
+	let frames: Vec<Frame> = delays.iter().enumerate()
+		.map(|(index, &delay_ms)| Frame { voxels: busy_frame(index as u8), delay_ms })
+		.collect();
+
+	let data = recording::encode(&frames).unwrap();
+	let header = playback::parse_header(&data).unwrap();
+
+	// The most common delay goes in the header and only the others are stored per frame
+	assert_eq!(header.delay_ms, 40);
+	assert_eq!(header.frame_count, delays.len() as u16);
+
+	let flags = frame_flags(&data, frames.len());
+
+	for (&delay, &flags) in delays.iter().zip(flags.iter()) {
+		assert_eq!(flags & FLAG_DELAY != 0, delay != 40);
+	}
+
+	assert!(frames_equal(&recording::decode(&data).unwrap(), &frames));
+}
+
+#[test]
+fn bad_recordings() {
+	let frames = vec![ Frame { voxels: busy_frame(2), delay_ms: 40 }, Frame { voxels: busy_frame(3), delay_ms: 40 } ];
+	let data = recording::encode(&frames).unwrap();
+
+	let error = |data: &[u8]| recording::decode(data).err();
+
+	let mut bad_magic = data.clone();
+	bad_magic[0] = b'X';
+	assert_eq!(error(&bad_magic), Some(PlaybackError::BadMagic));
+
+	let mut bad_version = data.clone();
+	bad_version[3] = 2;
+	assert_eq!(error(&bad_version), Some(PlaybackError::UnsupportedVersion));
+
+	let mut bad_size = data.clone();
+	bad_size[5] = 8;
+	assert_eq!(error(&bad_size), Some(PlaybackError::WrongSize));
+
+	assert_eq!(error(&data[..HEADER_LEN - 1]), Some(PlaybackError::Truncated));
+	assert_eq!(error(&data[..data.len() - 1]), Some(PlaybackError::Truncated));
+
+	// A key frame run past the end of the frame
+	let mut bad_run = data[..HEADER_LEN].to_vec();
+	bad_run.extend_from_slice(&[ 0, 65, 1, 2, 3 ]);
+	assert_eq!(error(&bad_run), Some(PlaybackError::Corrupt));
+}
+
+#[test]
+fn playback_shows_every_frame() {
+	let frames: Vec<Frame> = (0..10).map(|seed| Frame { voxels: busy_frame(seed), delay_ms: 20 + seed as u32 }).collect();
+
+	// `Playback` wants its data for good, as it would be in flash
+	let data: &'static [u8] = Box::leak(recording::encode(&frames).unwrap().into_boxed_slice());
+
+	let mut cube = sim::cube();
+	let mut simulator = Simulator::new();
+
+	Playback::new(data).unwrap().play(&mut cube, &mut simulator);
+
+	assert!(frames_equal(&simulator.frames, &frames));
+}
+
+#[test]
+fn embedded_spiral_matches_its_design() {
+	let design = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../recordings/spiral.csv")).unwrap();
+	let embedded = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../recordings/spiral.zca")).unwrap();
+
+	let frames = convert::parse_csv(&design).unwrap();
+
+	assert!(frames_equal(&recording::decode(&embedded).unwrap(), &frames), "spiral.zca is out of date; regenerate it with cube_convert");
+}
@@ -0,0 +1,84 @@
+//! The clock's calendar arithmetic and time parsing, and the default schedule
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::clock::{ self, DateTime, EARLIEST_SET, SECONDS_PER_DAY };
+use zinc_cube_host::schedule::{ self, Plan, SCHEDULE };
+
+/// Last second that can be set, 2099-12-31 23:59:59
+const LATEST_SET: u32 = 4_102_444_799;
+
+#[test]
+fn known_times() {
+	let known: [(&str, u32); 5] = [
+		("2000-01-01 00:00:00", EARLIEST_SET),
+		("2024-02-29 12:00:00", 1_709_208_000),
+		("2024-03-01 00:00:00", 1_709_251_200),
+		("2038-01-19 03:14:08", 1 << 31),
+		("2099-12-31 23:59:59", LATEST_SET),
+	];
+
+	for &(formatted, seconds) in known.iter() {
+		assert_eq!(DateTime::from_seconds(seconds).format().as_bytes(), formatted.as_bytes(), "formatting {}", seconds);
+		assert_eq!(clock::parse(formatted.as_bytes()).map(|time| time.to_seconds()), Some(seconds), "parsing {}", formatted);
+	}
+}
+
+#[test]
+fn every_day_round_trips() {
+	// At a different time of day each time round
+	for seconds in (EARLIEST_SET..LATEST_SET).step_by(SECONDS_PER_DAY as usize + 61) {
+		let time = DateTime::from_seconds(seconds);
+
+		assert_eq!(time.to_seconds(), seconds);
+		assert!(clock::parse(time.format().as_bytes()) == Some(time), "{} didn't parse back", seconds);
+	}
+}
+
+#[test]
+fn accepted_times() {
+	for accepted in [ "2024-12-24 18:30", "2024-02-29 00:00:59", "2099-12-31 23:59" ].iter() {
+		assert!(clock::parse(accepted.as_bytes()).is_some(), "\"{}\" rejected", accepted);
+	}
+}
+
+#[test]
+fn rejected_times() {
+	let rejected = [
+		"", "18:30", "2024-12-24", "2024-12-24T18:30", "2024-12-24 18:30:", "2023-02-29 12:00", "2024-04-31 12:00",
+		"2024-13-01 12:00", "2024-00-10 12:00", "2024-12-00 12:00", "2024-12-24 24:00", "2024-12-24 18:60",
+		"2024-12-24 18:30:60", "1999-12-31 23:59", "2100-01-01 00:00", "2024-1-24 18:30",
+	];
+
+	for rejected in rejected.iter() {
+		assert!(clock::parse(rejected.as_bytes()).is_none(), "\"{}\" accepted", rejected);
+	}
+}
+
+#[test]
+fn clock_not_set() {
+	assert!(schedule::plan(&SCHEDULE, None) == Plan { brightness: None, playlist: None, christmas: true });
+}
+
+#[test]
+fn default_schedule() {
+	// At the edges of its rules
+	let expected: [(&str, Option<u8>, bool); 8] = [
+		("2024-07-01 12:00", None, false),
+		("2024-07-01 22:29", None, false),
+		("2024-07-01 22:30", Some(1), false),
+		("2024-07-02 00:00", Some(1), false),
+		("2024-07-02 06:59", Some(1), false),
+		("2024-07-02 07:00", None, false),
+		("2024-12-01 00:00", Some(1), true),
+		("2024-12-24 18:00", None, true),
+	];
+
+	for &(time, brightness, christmas) in expected.iter() {
+		let plan = schedule::plan(&SCHEDULE, clock::parse(time.as_bytes()));
+
+		assert_eq!(plan.brightness, brightness, "brightness at {}", time);
+		assert_eq!(plan.playlist.is_some(), brightness.is_some(), "playlist at {}", time);
+		assert_eq!(plan.christmas, christmas, "colours at {}", time);
+	}
+}
@@ -0,0 +1,250 @@
+//! The settings store, against an in-memory EEPROM that can be corrupted and worn out
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::cube::Correction;
+use zinc_cube_host::mock_eeprom::MockEeprom;
+use zinc_cube_host::pattern_list::EVERY_PATTERN;
+use zinc_cube_host::settings::{ self, Settings, Store, ALL_PATTERNS, SLOTS, SLOT_WORDS };
+
+/// Saves made by the wear test, about a week of changing pattern every 30 seconds
+const WEAR_SAVES: u32 = 20_000;
+
+fn changed() -> Settings {
+	Settings {
+		brightness: 7,
+		correction: Correction::Gamma,
+		playlist: 0b1011_0110,
+		last_pattern: 5,
+	}
+}
+
+/// `changed()` showing a different pattern
+fn on_pattern(last_pattern: u8) -> Settings {
+	Settings { last_pattern, ..changed() }
+}
+
+/// Open a store on a copy of `eeprom`, as the cube does after a reset
+fn reopen(eeprom: &MockEeprom) -> Settings {
+	Store::open(eeprom.clone()).settings()
+}
+
+/// A store with `changed()` saved in it
+fn saved() -> Store<MockEeprom> {
+	let mut store = Store::open(MockEeprom::new());
+
+	store.save(&changed());
+
+	store
+}
+
+/// Put a record straight into `slot`, without going through a store
+fn write_record(eeprom: &mut MockEeprom, slot: u32, settings: &Settings, sequence: u32) {
+	let address = (slot * SLOT_WORDS) as usize;
+
+	for (i, &word) in settings.encode(sequence).iter().enumerate() {
+		eeprom.words[address + i] = word;
+	}
+}
+
+/// Slot of the newest record, found by looking for the highest sequence number
+fn newest_slot(eeprom: &MockEeprom) -> u32 {
+	(0..SLOTS).max_by_key(|&slot| {
+		let address = (slot * SLOT_WORDS) as usize;
+
+		if eeprom.words[address] >> 16 == 0x5E77 { eeprom.words[address + 1] } else { 0 }
+	}).unwrap_or(0)
+}
+
+#[test]
+fn erased() {
+	let store = Store::open(MockEeprom::new());
+
+	assert!(store.settings() == Settings::defaults());
+	assert_eq!(store.eeprom().total_writes(), 0);
+}
+
+#[test]
+fn encode_decode_round_trip() {
+	for &settings in [ Settings::defaults(), changed(), Settings { correction: Correction::None, playlist: 0, ..changed() } ].iter() {
+		for &sequence in [ 0, 1, 0x8000_0000, 0xffff_ffff ].iter() {
+			match Settings::decode(&settings.encode(sequence)) {
+				Some((decoded, decoded_sequence)) => {
+					assert!(decoded == settings);
+					assert_eq!(decoded_sequence, sequence);
+				},
+				None => panic!("record with sequence {} didn't decode", sequence),
+			}
+		}
+	}
+}
+
+#[test]
+fn save_and_reload() {
+	assert!(reopen(saved().eeprom()) == changed());
+}
+
+#[test]
+fn save_unchanged_writes_nothing() {
+	let mut store = saved();
+	let writes = store.eeprom().total_writes();
+
+	store.save(&changed());
+
+	assert_eq!(store.eeprom().total_writes(), writes);
+}
+
+#[test]
+fn power_lost_mid_save() {
+	let mut interrupted = Store::open(saved().eeprom().clone());
+
+	interrupted.eeprom_mut().power_fails_after = Some(2);
+	interrupted.save(&on_pattern(9));
+
+	assert!(reopen(interrupted.eeprom()) == changed());
+}
+
+#[test]
+fn corrupt_checksum_falls_back_to_previous() {
+	let mut store = saved();
+
+	store.save(&on_pattern(9));
+
+	// A flipped bit in the newest record
+	let mut eeprom = store.eeprom().clone();
+	let address = (newest_slot(&eeprom) * SLOT_WORDS + 3) as usize;
+
+	eeprom.words[address] ^= 1 << 9;
+
+	assert!(reopen(&eeprom) == changed());
+}
+
+#[test]
+fn corrupt_only_record_falls_back_to_defaults() {
+	let mut eeprom = saved().eeprom().clone();
+
+	eeprom.words[4] ^= 1;
+
+	assert!(reopen(&eeprom) == Settings::defaults());
+}
+
+#[test]
+fn newest_slot_wins() {
+	let mut store = saved();
+
+	for pattern in 0..10 {
+		store.save(&on_pattern(pattern));
+	}
+
+	assert!(reopen(store.eeprom()) == on_pattern(9));
+
+	// By sequence number, not by position
+	let mut eeprom = MockEeprom::new();
+
+	write_record(&mut eeprom, 3, &on_pattern(1), 40);
+	write_record(&mut eeprom, 20, &on_pattern(2), 39);
+	write_record(&mut eeprom, 31, &on_pattern(3), 38);
+
+	assert!(reopen(&eeprom) == on_pattern(1));
+
+	// And the next save goes in the slot after it
+	let mut store = Store::open(eeprom);
+
+	store.save(&on_pattern(4));
+
+	assert_eq!(newest_slot(store.eeprom()), 4);
+	assert!(reopen(store.eeprom()) == on_pattern(4));
+}
+
+#[test]
+fn sequence_wraparound() {
+	// Saves carried on round from 0xffff_ffff to 2, starting in the last slots
+	let mut eeprom = MockEeprom::new();
+
+	write_record(&mut eeprom, 29, &on_pattern(1), 0xffff_fffe);
+	write_record(&mut eeprom, 30, &on_pattern(2), 0xffff_ffff);
+	write_record(&mut eeprom, 31, &on_pattern(3), 0);
+	write_record(&mut eeprom, 0, &on_pattern(4), 1);
+	write_record(&mut eeprom, 1, &on_pattern(5), 2);
+
+	assert!(reopen(&eeprom) == on_pattern(5));
+
+	// A store about to wrap
+	let mut eeprom = MockEeprom::new();
+
+	write_record(&mut eeprom, 0, &on_pattern(1), 0xffff_ffff);
+
+	let mut store = Store::open(eeprom);
+
+	store.save(&on_pattern(2));
+	store.save(&on_pattern(3));
+
+	// Sequence numbers 0 and 1, in slots 1 and 2
+	assert!(reopen(store.eeprom()) == on_pattern(3));
+	assert_eq!(store.eeprom().words[(2 * SLOT_WORDS + 1) as usize], 1);
+}
+
+#[test]
+fn other_version_is_ignored() {
+	let mut eeprom = saved().eeprom().clone();
+
+	for slot in 0..SLOTS {
+		let address = (slot * SLOT_WORDS) as usize;
+
+		if eeprom.words[address] >> 16 == 0x5E77 {
+			eeprom.words[address] += 1;
+		}
+	}
+
+	assert!(reopen(&eeprom) == Settings::defaults());
+}
+
+#[test]
+fn bad_brightness_is_rejected() {
+	// Even with a good checksum
+	let bad = Settings { brightness: 200, ..changed() };
+
+	assert!(Settings::decode(&bad.encode(1)).is_none());
+}
+
+#[test]
+fn wear_is_spread() {
+	let mut store = Store::open(MockEeprom::new());
+
+	for save in 0..WEAR_SAVES {
+		store.save(&on_pattern((save % 18) as u8));
+	}
+
+	assert!(reopen(store.eeprom()) == on_pattern(((WEAR_SAVES - 1) % 18) as u8));
+
+	// Every save writes the sequence number, so the best possible is an even share of the slots
+	let most = store.eeprom().most_writes();
+
+	assert!(most <= WEAR_SAVES / SLOTS + 1, "{} writes to one word", most);
+}
+
+#[test]
+fn playlists() {
+	let good: [(&str, u32, &str); 7] = [
+		("all", ALL_PATTERNS, "all"),
+		("1", 0b1, "1"),
+		("1 3 5-9", 0b1_1111_0101, "1 3 5-9"),
+		("2,3,4", 0b1110, "2-4"),
+		("18 1", 1 << 17 | 1, "1 18"),
+		("20", 1 << 19, "20"),
+		("1-20", EVERY_PATTERN, "all"),
+	];
+
+	for &(text, playlist, described) in good.iter() {
+		assert_eq!(settings::parse_playlist(text.as_bytes()), Some(playlist), "parsing \"{}\"", text);
+		assert_eq!(settings::describe_playlist(playlist).as_bytes(), described.as_bytes());
+	}
+
+	// Past the last pattern, as well as nonsense
+	for text in [ "", "0", "21", "25", "33", "19-21", "5-3", "1-", "x", "1 2 y" ].iter() {
+		assert_eq!(settings::parse_playlist(text.as_bytes()), None, "parsing \"{}\"", text);
+	}
+
+	// Bits for patterns that don't exist, say from a playlist saved by older firmware, aren't described
+	assert_eq!(settings::describe_playlist(1 << 24 | 1 << 2).as_bytes(), b"3");
+}
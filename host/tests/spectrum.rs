@@ -0,0 +1,122 @@
+//! The spectrum analyser, fed generated audio a block at a time the way the cube hears it
+
+extern crate zinc_cube_host;
+
+use std::f64::consts::PI;
+
+use zinc_cube_host::audio::{ self, Analyser, AudioSource, BANDS, BAND_EDGES, FFT_SIZE, MIN_CEILING, PEAK_DECAY, PEAK_HOLD, SAMPLE_RATE };
+use zinc_cube_host::wav::WavSource;
+
+/// Whole blocks of a sine at `hz`, about half of full scale
+fn tone(hz: f64, blocks: usize) -> Vec<i16> {
+	(0..blocks * FFT_SIZE).map(|i| ((i as f64 * hz * 2.0 * PI / SAMPLE_RATE as f64).sin() * 16_000.0) as i16).collect()
+}
+
+/// Whole blocks of white noise between -`amplitude` and `amplitude`, the same every time
+fn noise(amplitude: i32, blocks: usize) -> Vec<i16> {
+	let mut state: u32 = 0x5EED;
+
+	(0..blocks * FFT_SIZE).map(|_| {
+		state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+		((state >> 16) as i32 % (2 * amplitude + 1) - amplitude) as i16
+	}).collect()
+}
+
+/// Run `samples` through a fresh analyser, returning its levels and peaks after each block
+fn analyse(samples: Vec<i16>) -> Vec<([u8; BANDS], [u8; BANDS])> {
+	let mut source = WavSource::new(samples);
+	let mut analyser = Analyser::new();
+	let mut block = [0i16; FFT_SIZE];
+	let mut blocks = Vec::new();
+
+	while !source.is_finished() {
+		source.read(&mut block);
+		analyser.update(&audio::band_energy(&block));
+
+		blocks.push((analyser.levels, analyser.peaks));
+	}
+
+	blocks
+}
+
+/// Band that FFT bin `bin` is summed into
+fn band_of(bin: usize) -> usize {
+	(0..BANDS).find(|&band| bin >= BAND_EDGES[band] && bin < BAND_EDGES[band + 1]).unwrap()
+}
+
+#[test]
+fn tone_peaks_in_its_band() {
+	// 500Hz is bin 8, with each bin 62.5Hz wide
+	let band = band_of(500 * FFT_SIZE / SAMPLE_RATE as usize);
+
+	for (block, &(levels, _)) in analyse(tone(500.0, 30)).iter().enumerate() {
+		assert_eq!(levels[band], 255, "block {}", block);
+
+		let loudest = (0..BANDS).max_by_key(|&other| levels[other]).unwrap();
+
+		assert_eq!(loudest, band, "block {} loudest in band {}: {:?}", block, loudest, levels);
+
+		// Nothing much leaks more than a band away
+		for other in (0..BANDS).filter(|&other| (other as i32 - band as i32).abs() > 1) {
+			assert!(levels[other] < 128, "block {} band {} at {}", block, other, levels[other]);
+		}
+	}
+}
+
+#[test]
+fn silence_is_dark() {
+	for &(levels, peaks) in analyse(vec![0; 30 * FFT_SIZE]).iter() {
+		assert_eq!(levels, [0; BANDS]);
+		assert_eq!(peaks, [0; BANDS]);
+	}
+}
+
+#[test]
+fn quiet_noise_is_dark() {
+	let samples = noise(16, 60);
+
+	// It really is below the ceiling the automatic gain stops at
+	for block in samples.chunks(FFT_SIZE) {
+		let mut samples = [0i16; FFT_SIZE];
+
+		samples.copy_from_slice(block);
+
+		for &energy in audio::band_energy(&samples).iter() {
+			assert!(audio::log2(energy) < MIN_CEILING);
+		}
+	}
+
+	for (block, &(levels, _)) in analyse(samples).iter().enumerate() {
+		assert_eq!(levels, [0; BANDS], "block {}", block);
+	}
+}
+
+#[test]
+fn peaks_hold_then_fall() {
+	const TONE_BLOCKS: usize = 10;
+
+	let band = band_of(500 * FFT_SIZE / SAMPLE_RATE as usize);
+	let mut samples = tone(500.0, TONE_BLOCKS);
+
+	samples.extend(vec![0; 60 * FFT_SIZE]);
+
+	let blocks = analyse(samples);
+
+	// Held at the top for `PEAK_HOLD` blocks of silence
+	for (block, &(levels, peaks)) in blocks.iter().enumerate().skip(TONE_BLOCKS).take(PEAK_HOLD as usize) {
+		assert_eq!(peaks[band], 255, "block {}", block);
+		assert!(levels[band] < 255, "block {} level still up", block);
+	}
+
+	// Then falling by `PEAK_DECAY` each block until it's gone
+	let mut expected = 255u8;
+
+	for (block, &(_, peaks)) in blocks.iter().enumerate().skip(TONE_BLOCKS + PEAK_HOLD as usize) {
+		expected = expected.saturating_sub(PEAK_DECAY);
+
+		assert_eq!(peaks[band], expected, "block {}", block);
+	}
+
+	assert_eq!(expected, 0);
+}
@@ -0,0 +1,154 @@
+//! Blink codes, heartbeats and errors on the status LED
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::status::{ Lights, Status, BLINK_GAP_US, CODE_REPEAT_US, DIGIT_GAP_US, ERROR_US, HEARTBEAT_US, LONG_BLINK_US,
+	SHORT_BLINK_US };
+
+/// Every blink and gap is a multiple of this
+const STEP_US: u32 = 10_000;
+
+/// Timer counts to start from, including just before the timer wraps
+const STARTS: [u32; 3] = [ 0, 123_456_789, u32::MAX - 2_000_000 ];
+
+/// One run of the LED staying the same
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Run {
+	Blue(u32),
+	Dark(u32),
+	Other,
+}
+
+/// A pattern started at `start`, with the LED's runs of blue and dark over one repeat of the code
+fn runs(index: u8, start: u32) -> Vec<Run> {
+	let mut status = Status::new(0);
+	let mut runs = Vec::new();
+	let mut last: Option<(Lights, u32)> = None;
+
+	status.start_pattern(index, start);
+
+	let mut push = |lights: Lights, length: u32| {
+		runs.push(match (lights.red, lights.blue) {
+			(false, true) => Run::Blue(length),
+			(false, false) => Run::Dark(length),
+			_ => Run::Other,
+		});
+	};
+
+	for step in 0..CODE_REPEAT_US / STEP_US {
+		let lights = status.lights(start.wrapping_add(step * STEP_US));
+
+		last = match last {
+			Some((previous, length)) if previous == lights => Some((previous, length + STEP_US)),
+			Some((previous, length)) => {
+				push(previous, length);
+
+				Some((lights, STEP_US))
+			},
+			None => Some((lights, STEP_US)),
+		};
+	}
+
+	if let Some((lights, length)) = last {
+		push(lights, length);
+	}
+
+	runs
+}
+
+/// The runs that blink out a pattern's number, dropping the heartbeat after them
+fn code(index: u8, start: u32) -> Vec<Run> {
+	let runs = runs(index, start);
+	let heartbeat = runs.iter().position(|&run| run == Run::Blue(HEARTBEAT_US)).unwrap();
+
+	// Up to the end of the last blink, without the gap before the heartbeat
+	runs[..heartbeat - 1].to_vec()
+}
+
+/// What `code()` should be: `long` long blinks then `short` short ones
+fn expected(long: u32, short: u32) -> Vec<Run> {
+	let mut runs = Vec::new();
+
+	for _ in 0..long {
+		runs.push(Run::Blue(LONG_BLINK_US));
+		runs.push(Run::Dark(BLINK_GAP_US));
+	}
+
+	if long > 0 {
+		*runs.last_mut().unwrap() = Run::Dark(BLINK_GAP_US + DIGIT_GAP_US);
+	}
+
+	for _ in 0..short {
+		runs.push(Run::Blue(SHORT_BLINK_US));
+		runs.push(Run::Dark(BLINK_GAP_US));
+	}
+
+	runs.pop();
+
+	runs
+}
+
+#[test]
+fn units_are_short_blinks() {
+	for &start in STARTS.iter() {
+		assert_eq!(code(0, start), expected(0, 1));
+		assert_eq!(code(8, start), expected(0, 9));
+	}
+}
+
+#[test]
+fn tens_are_long_blinks() {
+	for &start in STARTS.iter() {
+		assert_eq!(code(11, start), expected(1, 2));
+		assert_eq!(code(18, start), expected(1, 9));
+	}
+}
+
+#[test]
+fn zero_is_ten_short_blinks() {
+	for &start in STARTS.iter() {
+		assert_eq!(code(9, start), expected(1, 10));
+		assert_eq!(code(19, start), expected(2, 10));
+	}
+}
+
+#[test]
+fn heartbeat_follows_the_code() {
+	for &start in STARTS.iter() {
+		let runs = runs(19, start);
+		let blinks = expected(2, 10).len();
+
+		// Then nothing but heartbeats until the code comes round again
+		for (position, &run) in runs.iter().enumerate().skip(blinks) {
+			match run {
+				Run::Blue(length) => assert_eq!(length, HEARTBEAT_US, "run {}", position),
+				Run::Dark(length) => assert!(length >= HEARTBEAT_US, "run {}", position),
+				Run::Other => panic!("run {} isn't blue or dark", position),
+			}
+		}
+
+		// And once the repeat is over the code starts again
+		let mut status = Status::new(0);
+
+		status.start_pattern(19, start);
+
+		assert!(status.lights(start.wrapping_add(CODE_REPEAT_US)) == Lights { red: false, blue: true });
+	}
+}
+
+#[test]
+fn errors_show_red_for_a_while() {
+	for &start in STARTS.iter() {
+		let mut status = Status::new(start);
+		let red = Lights { red: true, blue: false };
+
+		status.error(start.wrapping_add(1_000));
+
+		assert!(status.lights(start.wrapping_add(1_000)) == red);
+		assert!(status.lights(start.wrapping_add(1_000 + ERROR_US - 1)) == red);
+		assert!(status.lights(start.wrapping_add(1_000 + ERROR_US)) != red);
+
+		// Back to the code and heartbeat, however long until the timer comes round to the error again
+		assert!(status.lights(start.wrapping_add(1_000)) != red);
+	}
+}
@@ -0,0 +1,205 @@
+//! The streaming protocol, from the host's `Sender` through to the cube's `StreamParser`
+
+extern crate libc;
+extern crate zinc_cube_host;
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::Read;
+use std::mem;
+use std::os::unix::io::FromRawFd;
+
+use zinc_cube_host::apa106led::{ Apa106Led, OFF };
+use zinc_cube_host::sender::Sender;
+use zinc_cube_host::stream::{ encode_packet, StreamParser, PACKET_LEN, PAYLOAD_LEN };
+
+/// A frame where every channel is different, so misplaced bytes show up
+fn test_frame(seed: u8) -> [Apa106Led; 64] {
+	let mut frame = [OFF; 64];
+
+	for (index, voxel) in frame.iter_mut().enumerate() {
+		let base = (index as u8).wrapping_mul(3).wrapping_add(seed);
+
+		*voxel = Apa106Led { red: base, green: base.wrapping_add(1), blue: base.wrapping_add(2) };
+	}
+
+	frame
+}
+
+fn packet(frame: &[Apa106Led; 64]) -> [u8; PACKET_LEN] {
+	let mut packet = [0; PACKET_LEN];
+
+	encode_packet(frame, &mut packet);
+
+	packet
+}
+
+/// Push `bytes` and return the frames that came out
+fn parse(parser: &mut StreamParser, bytes: &[u8]) -> Vec<[Apa106Led; 64]> {
+	let mut frames = Vec::new();
+
+	for &byte in bytes.iter() {
+		if parser.push(byte) {
+			frames.push(*parser.frame());
+		}
+	}
+
+	frames
+}
+
+#[test]
+fn round_trip() {
+	let mut parser = StreamParser::new();
+	let frame = test_frame(7);
+	let packet = packet(&frame);
+
+	// Nothing until the last byte
+	for &byte in packet[..PACKET_LEN - 1].iter() {
+		assert!(!parser.push(byte));
+	}
+
+	assert!(parser.push(packet[PACKET_LEN - 1]));
+	assert!(*parser.frame() == frame);
+}
+
+#[test]
+fn back_to_back() {
+	let mut parser = StreamParser::new();
+	let mut bytes = Vec::new();
+
+	for seed in 0..3 {
+		bytes.extend_from_slice(&packet(&test_frame(seed)));
+	}
+
+	let frames = parse(&mut parser, &bytes);
+
+	assert_eq!(frames.len(), 3);
+
+	for (seed, frame) in frames.iter().enumerate() {
+		assert!(*frame == test_frame(seed as u8));
+	}
+}
+
+#[test]
+fn bad_crc() {
+	let mut parser = StreamParser::new();
+
+	let mut corrupt_payload = packet(&test_frame(1));
+	corrupt_payload[4 + 100] ^= 0x01;
+
+	assert!(parse(&mut parser, &corrupt_payload).is_empty());
+
+	let mut corrupt_crc = packet(&test_frame(1));
+	corrupt_crc[PACKET_LEN - 1] ^= 0x80;
+
+	assert!(parse(&mut parser, &corrupt_crc).is_empty());
+}
+
+#[test]
+fn bad_length() {
+	let mut parser = StreamParser::new();
+
+	// The CRC covers the length, so only the wrong length stops this one
+	for &length in [ PAYLOAD_LEN - 1, PAYLOAD_LEN + 1, 0 ].iter() {
+		let mut bytes = packet(&test_frame(2)).to_vec();
+
+		bytes[2] = (length & 0xff) as u8;
+		bytes[3] = (length >> 8) as u8;
+		bytes.truncate(4);
+		bytes.extend_from_slice(&vec![0x11; length]);
+
+		let mut crc = 0xffff;
+
+		for &byte in bytes[2..].iter() {
+			crc = zinc_cube_host::stream::crc16(crc, byte);
+		}
+
+		bytes.push((crc & 0xff) as u8);
+		bytes.push((crc >> 8) as u8);
+
+		assert!(parse(&mut parser, &bytes).is_empty(), "length {} accepted", length);
+	}
+}
+
+#[test]
+fn resync_after_garbage() {
+	let mut parser = StreamParser::new();
+	let frame = test_frame(3);
+
+	// Noise, including sync bytes on their own and a repeated first sync byte
+	let mut bytes = vec![ 0x00, 0xff, 0xA5, 0x5A, 0x00, 0x5A ];
+	bytes.extend_from_slice(&packet(&frame));
+
+	let frames = parse(&mut parser, &bytes);
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == frame);
+
+	// A corrupted packet is dropped and the one after it still gets through
+	let mut corrupt = packet(&test_frame(4));
+	corrupt[50] ^= 0xff;
+
+	let mut bytes = corrupt.to_vec();
+	bytes.extend_from_slice(&packet(&frame));
+
+	let frames = parse(&mut parser, &bytes);
+
+	assert_eq!(frames.len(), 1);
+	assert!(frames[0] == frame);
+}
+
+/// Open a pseudo-tty in raw mode, returning the master end and the path of the slave
+fn open_pty() -> (File, String) {
+	unsafe {
+		let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+
+		assert!(master >= 0, "posix_openpt failed");
+		assert_eq!(libc::grantpt(master), 0);
+		assert_eq!(libc::unlockpt(master), 0);
+
+		// Raw on the master applies to the pair, so nothing the sender writes gets translated
+		let mut termios: libc::termios = mem::zeroed();
+
+		assert_eq!(libc::tcgetattr(master, &mut termios), 0);
+		libc::cfmakeraw(&mut termios);
+		assert_eq!(libc::tcsetattr(master, libc::TCSANOW, &termios), 0);
+
+		let mut name = [0 as libc::c_char; 128];
+
+		assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+
+		let path = CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned();
+
+		(File::from_raw_fd(master), path)
+	}
+}
+
+#[test]
+fn pty_loopback() {
+	let (mut master, slave) = open_pty();
+	let mut sender = Sender::open(&slave).unwrap();
+
+	let sent: Vec<[Apa106Led; 64]> = (0..4).map(|seed| test_frame(seed * 40)).collect();
+
+	for frame in sent.iter() {
+		sender.send_frame(frame).unwrap();
+	}
+
+	let mut parser = StreamParser::new();
+	let mut received = Vec::new();
+	let mut buffer = [0; 256];
+
+	while received.len() < sent.len() {
+		let count = master.read(&mut buffer).unwrap();
+
+		assert!(count > 0, "pty closed early");
+
+		received.extend(parse(&mut parser, &buffer[..count]));
+	}
+
+	assert_eq!(received.len(), sent.len());
+
+	for (received, sent) in received.iter().zip(sent.iter()) {
+		assert!(received == sent);
+	}
+}
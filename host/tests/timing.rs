@@ -0,0 +1,174 @@
+//! The frame rate governor, driven by a pretend timer the way `TivaScheduler` drives it
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::frame_timing::FrameTiming;
+use zinc_cube_host::scheduler::{ Scheduler, FRAME_US, TARGET_FPS };
+use zinc_cube_host::sim::{ self, Simulator };
+
+/// How long a flush of the whole cube takes
+const FLUSH_US: u32 = 2_600;
+
+/// A pretend `TivaScheduler` and pattern: draw for `render_us`, flush for `flush_us`, then wait out
+/// whatever the governor says is left of `hold_us`
+struct Run {
+	timing: FrameTiming,
+	now: u32,
+	/// What the governor said to wait for the last frame
+	waited_us: u32,
+}
+
+impl Run {
+	fn new(start: u32) -> Run {
+		Run { timing: FrameTiming::new(), now: start, waited_us: 0 }
+	}
+
+	/// Show one frame, returning the delta the pattern gets afterwards
+	fn frame(&mut self, render_us: u32, flush_us: u32, hold_us: u32) -> u32 {
+		self.now = self.now.wrapping_add(render_us);
+		self.timing.start_flush(self.now);
+
+		self.now = self.now.wrapping_add(flush_us);
+		self.waited_us = self.timing.end_flush(self.now, hold_us);
+
+		self.now = self.now.wrapping_add(self.waited_us);
+		self.timing.end_hold(self.now);
+
+		self.timing.delta_us()
+	}
+}
+
+#[test]
+fn first_frame_is_held_as_asked() {
+	let mut run = Run::new(0);
+
+	assert_eq!(run.frame(4_000, FLUSH_US, FRAME_US), FRAME_US);
+	assert_eq!(run.waited_us, FRAME_US);
+}
+
+#[test]
+fn frames_are_evenly_spaced() {
+	// Once from zero and once across the timer wrapping round
+	for &start in [ 0, u32::MAX - 1_000_000 ].iter() {
+		let mut run = Run::new(start);
+
+		run.frame(4_000, FLUSH_US, FRAME_US);
+
+		// Drawing time varies from frame to frame
+		for frame in 0..600 {
+			assert_eq!(run.frame(1_000 + (frame * 7_919) % 12_000, FLUSH_US, FRAME_US), FRAME_US, "frame {} from {}", frame, start);
+		}
+
+		assert_eq!(run.timing.take_stats().dropped, 0);
+	}
+}
+
+#[test]
+fn late_frames_are_dropped() {
+	let mut run = Run::new(0);
+
+	run.frame(1_000, FLUSH_US, FRAME_US);
+
+	// Too slow to keep up: shown as soon as it's ready
+	assert_eq!(run.frame(20_000, FLUSH_US, FRAME_US), 22_600);
+	assert_eq!(run.waited_us, 0);
+
+	// Back on time, and counted from the late frame rather than trying to catch up
+	assert_eq!(run.frame(1_000, FLUSH_US, FRAME_US), FRAME_US);
+
+	let stats = run.timing.take_stats();
+
+	assert_eq!(stats.dropped, 1);
+	assert_eq!(stats.frames, 3);
+
+	// Exactly on time isn't late
+	assert_eq!(run.frame(FRAME_US - FLUSH_US, FLUSH_US, FRAME_US), FRAME_US);
+	assert_eq!(run.timing.take_stats().dropped, 0);
+}
+
+#[test]
+fn reset_falls_back_to_hold() {
+	let mut run = Run::new(0);
+
+	run.frame(1_000, FLUSH_US, FRAME_US);
+
+	// Streamed frames or sleep hold the pattern up for a long time
+	run.now += 5_000_000;
+	run.timing.reset();
+
+	// Held for all of what was asked after the flush, as there's no previous frame to count from
+	assert_eq!(run.frame(1_000, FLUSH_US, 30_000), 30_000);
+	assert_eq!(run.waited_us, 30_000);
+
+	let stats = run.timing.take_stats();
+
+	assert_eq!(stats.dropped, 0);
+	assert_eq!(stats.frames, 2);
+	assert_eq!(stats.rendered, 0, "drawing was timed across the reset");
+
+	// And counting from frames again after that
+	assert_eq!(run.frame(1_000, FLUSH_US, FRAME_US), FRAME_US);
+	assert_eq!(run.waited_us, FRAME_US - 1_000 - FLUSH_US);
+}
+
+#[test]
+fn end_flush_across_wraparound() {
+	// The hold ends after the timer has wrapped
+	let mut run = Run::new(u32::MAX - 25_000);
+
+	run.frame(1_000, FLUSH_US, FRAME_US);
+
+	let handed_back = run.now;
+
+	assert!(handed_back.wrapping_add(FRAME_US) < handed_back);
+	assert_eq!(run.frame(1_000, FLUSH_US, FRAME_US), FRAME_US);
+	assert_eq!(run.waited_us, FRAME_US - 1_000 - FLUSH_US);
+
+	// Late, with the flush finishing after the timer has wrapped
+	let mut run = Run::new(u32::MAX - 40_000);
+
+	run.frame(1_000, FLUSH_US, FRAME_US);
+
+	assert_eq!(run.frame(30_000, FLUSH_US, FRAME_US), 30_000 + FLUSH_US);
+	assert_eq!(run.waited_us, 0);
+	assert_eq!(run.timing.take_stats().dropped, 1);
+}
+
+#[test]
+fn stats() {
+	let mut run = Run::new(0);
+
+	for &(render_us, flush_us) in [ (1_000, 2_000), (3_000, 2_500), (8_000, 3_000), (4_000, 2_500) ].iter() {
+		run.frame(render_us, flush_us, FRAME_US);
+	}
+
+	let stats = run.timing.take_stats();
+
+	// The first frame's drawing time isn't known
+	assert_eq!(stats.render_average_us(), 5_000);
+	assert_eq!(stats.render_max_us, 8_000);
+	assert_eq!(stats.flush_average_us(), 2_500);
+	assert_eq!(stats.flush_max_us, 3_000);
+
+	// Taking them starts again
+	let empty = run.timing.take_stats();
+
+	assert_eq!(empty.frames, 0);
+	assert_eq!(empty.render_max_us, 0);
+}
+
+#[test]
+fn simulator_keeps_time() {
+	// Frames held for fractions of a millisecond don't drift
+	let mut cube = sim::cube();
+	let mut simulator = Simulator::new();
+
+	for _ in 0..TARGET_FPS * 10 {
+		simulator.show_frame(&mut cube);
+	}
+
+	let total: u32 = simulator.frames.iter().map(|frame| frame.delay_ms).sum();
+
+	assert_eq!(total, 10_000);
+	assert_eq!(simulator.delta_us(), FRAME_US);
+}
@@ -0,0 +1,153 @@
+//! Whole frame transforms
+
+extern crate zinc_cube_host;
+
+use zinc_cube_host::apa106led::{ Apa106Led, OFF };
+use zinc_cube_host::cube::Cube4;
+use zinc_cube_host::fixed::QUARTER_TURN;
+use zinc_cube_host::sim;
+use zinc_cube_host::transform::{ self, Axis, Edge };
+
+const FILL: Apa106Led = Apa106Led { red: 1, green: 2, blue: 3 };
+
+/// A cube with every voxel a different colour
+fn numbered_cube() -> Cube4<'static> {
+	let mut cube = sim::cube();
+	let mut frame = [OFF; 64];
+
+	for (index, voxel) in frame.iter_mut().enumerate() {
+		*voxel = Apa106Led { red: index as u8 + 10, green: 0, blue: 0 };
+	}
+
+	cube.set_frame(&frame);
+
+	cube
+}
+
+fn shifted(axis: Axis, amount: i8, edge: Edge) -> [Apa106Led; 64] {
+	let mut cube = numbered_cube();
+
+	transform::shift(&mut cube, axis, amount, edge);
+
+	cube.get_frame()
+}
+
+#[test]
+fn wrap_by_one() {
+	let frame = shifted(Axis::X, 1, Edge::Wrap);
+
+	// Voxel (0, 0, 0) comes from (3, 0, 0) and (1, 0, 0) from (0, 0, 0)
+	assert_eq!(frame[0].red, 3 + 10);
+	assert_eq!(frame[1].red, 10);
+}
+
+#[test]
+fn wrap_large_amounts() {
+	for &axis in [ Axis::X, Axis::Y, Axis::Z ].iter() {
+		for &amount in [ 127i8, -128, 126, -127, 100, -100, 5, -5, 4, -4 ].iter() {
+			// A whole number of cube sizes further is the same shift
+			let equivalent = ((amount as i16 % 4 + 4) % 4) as i8;
+
+			assert!(shifted(axis, amount, Edge::Wrap) == shifted(axis, equivalent, Edge::Wrap), "shift by {}", amount);
+		}
+	}
+}
+
+#[test]
+fn fill_large_amounts() {
+	for &axis in [ Axis::X, Axis::Y, Axis::Z ].iter() {
+		for &amount in [ 127i8, -128, 4, -4 ].iter() {
+			assert!(shifted(axis, amount, Edge::Fill(FILL)).iter().all(|&voxel| voxel == FILL), "shift by {}", amount);
+		}
+
+		// Three layers of fill and one of the original
+		let filled = shifted(axis, -3, Edge::Fill(FILL)).iter().filter(|&&voxel| voxel == FILL).count();
+
+		assert_eq!(filled, 48);
+	}
+
+	// Negative amounts move towards zero, filling in from the far side
+	let frame = shifted(Axis::X, -1, Edge::Fill(FILL));
+
+	assert_eq!(frame[0].red, 1 + 10);
+	assert!(frame[3] == FILL);
+
+	let frame = shifted(Axis::Z, -2, Edge::Fill(FILL));
+
+	assert_eq!(frame[16].red, 48 + 10);
+	assert!(frame[32..].iter().all(|&voxel| voxel == FILL));
+}
+
+#[test]
+fn mirror() {
+	for &axis in [ Axis::X, Axis::Y, Axis::Z ].iter() {
+		let mut cube = numbered_cube();
+
+		transform::mirror(&mut cube, axis);
+
+		for (index, voxel) in cube.get_frame().iter().enumerate() {
+			let (x, y, z) = (index % 4, index / 4 % 4, index / 16);
+
+			let source = match axis {
+				Axis::X => (3 - x) + y * 4 + z * 16,
+				Axis::Y => x + (3 - y) * 4 + z * 16,
+				Axis::Z => x + y * 4 + (3 - z) * 16,
+			};
+
+			assert_eq!(voxel.red as usize, source + 10, "voxel {}", index);
+		}
+
+		// Twice is back where it started
+		transform::mirror(&mut cube, axis);
+
+		assert!(cube.get_frame()[..] == numbered_cube().get_frame()[..]);
+	}
+}
+
+#[test]
+fn rotate_by_right_angles() {
+	for &axis in [ Axis::X, Axis::Y, Axis::Z ].iter() {
+		for turns in 0..4 {
+			let mut quarter_turns = numbered_cube();
+			let mut rotated = numbered_cube();
+
+			transform::rotate_90(&mut quarter_turns, axis, turns);
+			transform::rotate(&mut rotated, axis, turns as u16 * QUARTER_TURN, OFF);
+
+			assert!(rotated.get_frame()[..] == quarter_turns.get_frame()[..], "{} quarter turns", turns);
+
+			// And back round to where it started
+			transform::rotate(&mut rotated, axis, (4 - turns as u16) * QUARTER_TURN, OFF);
+
+			assert!(rotated.get_frame()[..] == numbered_cube().get_frame()[..], "{} quarter turns and back", turns);
+		}
+	}
+}
+
+#[test]
+fn rotate_by_eighth_turn() {
+	let colour = Apa106Led { red: 200, green: 0, blue: 0 };
+	let background = Apa106Led { red: 0, green: 0, blue: 200 };
+
+	let mut cube = sim::cube();
+
+	cube.fill(colour);
+	transform::rotate(&mut cube, Axis::Z, QUARTER_TURN / 2, background);
+
+	let frame = cube.get_frame();
+
+	for z in 0..4 {
+		let layer = &frame[z * 16..z * 16 + 16];
+
+		// The middle four voxels stay inside the cube
+		for &index in [ 5, 6, 9, 10 ].iter() {
+			assert!(layer[index] == colour, "voxel {} of layer {}", index, z);
+		}
+
+		// The corners sweep partly outside it and blend in the background, all by the same amount
+		for &index in [ 0, 3, 12, 15 ].iter() {
+			assert!(layer[index].blue > 0 && layer[index].red < 200, "voxel {} of layer {}", index, z);
+			assert!(layer[index] == layer[0], "voxel {} of layer {}", index, z);
+		}
+	}
+}
@@ -0,0 +1,165 @@
+//! Audio input through ADC0. Zinc doesn't have an ADC driver for the Tiva C, so like `serial` this
+//! pokes the peripheral registers directly.
+//!
+//! The microphone (or line in, biased to half of 3.3V) goes to PE3, which is analog input AIN0.
+//! Sample sequencer 3 takes one sample each time it's triggered, and the trigger is timed off the 1us
+//! timer so samples are evenly spaced. Samples can either be read a block at a time, waiting while
+//! they're captured, or polled for one at a time in between other work.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+use zinc::hal::timer::Timer;
+use zinc::hal::tiva_c::timer::{ Timer as TivaCTimer };
+
+use audio::{ AudioSource, FFT_SIZE, SAMPLE_RATE };
+
+const SYSCTL_RCGCGPIO: *mut u32 = 0x400F_E608 as *mut u32;
+const SYSCTL_RCGCADC: *mut u32 = 0x400F_E638 as *mut u32;
+
+const GPIOE_BASE: u32 = 0x4002_4000;
+
+const GPIO_DIR: *mut u32 = (GPIOE_BASE + 0x400) as *mut u32;
+const GPIO_AFSEL: *mut u32 = (GPIOE_BASE + 0x420) as *mut u32;
+const GPIO_DEN: *mut u32 = (GPIOE_BASE + 0x51C) as *mut u32;
+const GPIO_AMSEL: *mut u32 = (GPIOE_BASE + 0x528) as *mut u32;
+
+const ADC0_BASE: u32 = 0x4003_8000;
+
+const ADC_ACTSS: *mut u32 = (ADC0_BASE + 0x000) as *mut u32;
+const ADC_RIS: *const u32 = (ADC0_BASE + 0x004) as *const u32;
+const ADC_ISC: *mut u32 = (ADC0_BASE + 0x00C) as *mut u32;
+const ADC_EMUX: *mut u32 = (ADC0_BASE + 0x014) as *mut u32;
+const ADC_PSSI: *mut u32 = (ADC0_BASE + 0x028) as *mut u32;
+const ADC_SSMUX3: *mut u32 = (ADC0_BASE + 0x0A0) as *mut u32;
+const ADC_SSCTL3: *mut u32 = (ADC0_BASE + 0x0A4) as *mut u32;
+const ADC_SSFIFO3: *const u32 = (ADC0_BASE + 0x0A8) as *const u32;
+
+/// Clock gate bit for GPIO port E in RCGCGPIO
+const GPIO_PORT_E: u32 = 1 << 4;
+
+/// PE3
+const PIN: u32 = 1 << 3;
+
+/// Sample sequencer 3's bit in ACTSS, RIS, ISC and PSSI
+const SS3: u32 = 1 << 3;
+
+/// Trigger select bits for sequencer 3 in EMUX. Clearing them selects the processor (PSSI) trigger.
+const EMUX_SS3: u32 = 0xf << 12;
+
+/// SSCTL3: the first sample ends the sequence and raises the interrupt flag
+const SSCTL_END0_IE0: u32 = 0x6;
+
+/// Middle of the ADC's 12 bit range, where silence sits
+const MIDPOINT: i32 = 2048;
+
+const SAMPLE_PERIOD_US: u32 = 1_000_000 / SAMPLE_RATE;
+
+unsafe fn set_bits(register: *mut u32, bits: u32) {
+	volatile_store(register, volatile_load(register) | bits);
+}
+
+unsafe fn clear_bits(register: *mut u32, bits: u32) {
+	volatile_store(register, volatile_load(register) & !bits);
+}
+
+pub struct Microphone<'a> {
+	timer: &'a TivaCTimer,
+
+	/// Block being filled by `poll()`
+	block: [i16; FFT_SIZE],
+	filled: usize,
+	/// Timer count when `poll()` should take the next sample
+	due: u32,
+}
+
+impl<'a> Microphone<'a> {
+	/// Turn on ADC0 and set up PE3 as its input
+	pub fn new(timer: &'a TivaCTimer) -> Microphone<'a> {
+		unsafe {
+			set_bits(SYSCTL_RCGCADC, 1);
+			set_bits(SYSCTL_RCGCGPIO, GPIO_PORT_E);
+
+			// The peripherals take a few clocks to come out of reset
+			timer.wait_us(10);
+
+			clear_bits(GPIO_DIR, PIN);
+			set_bits(GPIO_AFSEL, PIN);
+			clear_bits(GPIO_DEN, PIN);
+			set_bits(GPIO_AMSEL, PIN);
+
+			// Sequencer 3 has to be disabled while it's configured
+			clear_bits(ADC_ACTSS, SS3);
+			clear_bits(ADC_EMUX, EMUX_SS3);
+			volatile_store(ADC_SSMUX3, 0);
+			volatile_store(ADC_SSCTL3, SSCTL_END0_IE0);
+			set_bits(ADC_ACTSS, SS3);
+		}
+
+		Microphone {
+			timer: timer,
+
+			block: [0; FFT_SIZE],
+			filled: 0,
+			due: timer.get_counter(),
+		}
+	}
+
+	/// Take one 12 bit sample
+	fn sample(&self) -> u16 {
+		unsafe {
+			volatile_store(ADC_PSSI, SS3);
+
+			while volatile_load(ADC_RIS) & SS3 == 0 {}
+
+			let value = volatile_load(ADC_SSFIFO3) & 0xfff;
+
+			volatile_store(ADC_ISC, SS3);
+
+			value as u16
+		}
+	}
+
+	/// Centre a sample on zero and scale it up to the full range of an i16
+	fn signed_sample(&self) -> i16 {
+		((self.sample() as i32 - MIDPOINT) << 4) as i16
+	}
+
+	/// Take a sample if the next one is due, without waiting. Returns the block once it's full.
+	pub fn poll(&mut self) -> Option<&[i16; FFT_SIZE]> {
+		let now = self.timer.get_counter();
+		let late = now.wrapping_sub(self.due);
+
+		// Not due yet (the subtraction wrapped)
+		if late > u32::max_value() / 2 {
+			return None;
+		}
+
+		self.block[self.filled] = self.signed_sample();
+		self.filled += 1;
+
+		// Carry on from now rather than rushing to catch up if something kept us away for a while
+		let from = if late > SAMPLE_PERIOD_US { now } else { self.due };
+
+		self.due = from.wrapping_add(SAMPLE_PERIOD_US);
+
+		if self.filled < FFT_SIZE {
+			return None;
+		}
+
+		self.filled = 0;
+
+		Some(&self.block)
+	}
+}
+
+impl<'a> AudioSource for Microphone<'a> {
+	fn read(&mut self, samples: &mut [i16; FFT_SIZE]) {
+		let start = self.timer.get_counter();
+
+		for (i, sample) in samples.iter_mut().enumerate() {
+			while self.timer.get_counter().wrapping_sub(start) < i as u32 * SAMPLE_PERIOD_US {}
+
+			*sample = self.signed_sample();
+		}
+	}
+}
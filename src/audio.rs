@@ -0,0 +1,235 @@
+//! Spectrum analysis for the audio reactive patterns. Blocks of `FFT_SIZE` samples go through a
+//! fixed point FFT and the bins are summed into `BANDS` roughly logarithmically spaced bands, one per
+//! column of the cube.
+//!
+//! Nothing here touches the hardware: samples come from an `AudioSource`, which is the ADC on the
+//! cube and a WAV file on the host. Schedulers with audio input listen while they hold each frame and
+//! hand the results to patterns through `Scheduler::bands()` and `Scheduler::beat()`.
+
+use beat::BeatTracker;
+use fixed;
+
+/// Samples per block. Must be a power of two.
+pub const FFT_SIZE: usize = 128;
+
+/// Sample rate in Hz. Each FFT bin is `SAMPLE_RATE / FFT_SIZE` = 62.5Hz wide.
+pub const SAMPLE_RATE: u32 = 8000;
+
+/// Time taken to capture one block: 16ms
+pub const BLOCK_US: u32 = FFT_SIZE as u32 * 1_000_000 / SAMPLE_RATE;
+
+pub const BANDS: usize = 16;
+
+/// First bin of each band, with the end of the last band at the end. Bin 0 (DC) is never used.
+pub const BAND_EDGES: [usize; BANDS + 1] = [ 1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 15, 19, 24, 30, 38, 48, 64 ];
+
+/// Band levels span this many powers of two of energy below the loudest recent band, in 8.8 fixed
+/// point. 10 is about 30dB, just above the Hann window's leakage into neighbouring bands.
+const RANGE: i32 = 10 << 8;
+
+/// The automatic gain never turns up past this, so silence and ADC noise stay dark. It's the level of
+/// a sine at about 1/16th of full scale.
+pub const MIN_CEILING: i32 = 32 << 8;
+
+/// How far the automatic gain ceiling drops per block, in 256ths of a power of two
+const CEILING_DECAY: i32 = 2;
+
+/// How much a band's displayed level drops per block, out of 255
+pub const LEVEL_DECAY: u8 = 20;
+
+/// Blocks a peak is held for before it starts falling
+pub const PEAK_HOLD: u8 = 24;
+
+/// How much a peak drops per block once the hold is over
+pub const PEAK_DECAY: u8 = 8;
+
+/// Anything that can capture a block of audio: signed samples at `SAMPLE_RATE`, full scale being the
+/// whole range of `i16`
+pub trait AudioSource {
+	/// Fill `samples` with the next block, waiting for it to be captured if need be
+	fn read(&mut self, samples: &mut [i16; FFT_SIZE]);
+}
+
+/// Sine and cosine of `step` `FFT_SIZE`ths of a turn, scaled to +/- 32767 (Q15)
+fn twiddle(step: usize) -> (i32, i32) {
+	let angle = (step * (0x1_0000 / FFT_SIZE)) as u16;
+	let q15 = |value: fixed::Fixed16| (value.0 * 32767 + 0x8000) >> 16;
+
+	(q15(fixed::sin(angle)), q15(fixed::cos(angle)))
+}
+
+/// In place radix 2 FFT of `FFT_SIZE` points. Outputs aren't scaled down, so they can grow to `FFT_SIZE`
+/// times the largest input.
+pub fn fft(re: &mut [i32; FFT_SIZE], im: &mut [i32; FFT_SIZE]) {
+	// Bit reversed reordering
+	let mut j = 0;
+
+	for i in 0..FFT_SIZE - 1 {
+		if i < j {
+			re.swap(i, j);
+			im.swap(i, j);
+		}
+
+		let mut bit = FFT_SIZE >> 1;
+
+		while j & bit != 0 {
+			j ^= bit;
+			bit >>= 1;
+		}
+
+		j |= bit;
+	}
+
+	let mut size = 2;
+
+	while size <= FFT_SIZE {
+		let half = size / 2;
+		let stride = FFT_SIZE / size;
+
+		let mut start = 0;
+
+		while start < FFT_SIZE {
+			for k in 0..half {
+				let (sin, cos) = twiddle(k * stride);
+				let (a, b) = (start + k, start + k + half);
+
+				// Multiply by e^(-i * angle)
+				let tr = ((re[b] as i64 * cos as i64 + im[b] as i64 * sin as i64) >> 15) as i32;
+				let ti = ((im[b] as i64 * cos as i64 - re[b] as i64 * sin as i64) >> 15) as i32;
+
+				re[b] = re[a] - tr;
+				im[b] = im[a] - ti;
+				re[a] += tr;
+				im[a] += ti;
+			}
+
+			start += size;
+		}
+
+		size *= 2;
+	}
+}
+
+/// Energy in each band of a block of samples. The block's DC offset is removed and a Hann window
+/// applied first.
+pub fn band_energy(samples: &[i16; FFT_SIZE]) -> [u64; BANDS] {
+	let mut re = [0i32; FFT_SIZE];
+	let mut im = [0i32; FFT_SIZE];
+
+	let mean = samples.iter().fold(0i32, |sum, &sample| sum + sample as i32) / FFT_SIZE as i32;
+
+	for (i, &sample) in samples.iter().enumerate() {
+		// 0.5 - 0.5 * cos, in Q15
+		let (_, cos) = twiddle(i);
+		let window = (32767 - cos) / 2;
+
+		re[i] = ((sample as i32 - mean) * window) >> 15;
+	}
+
+	fft(&mut re, &mut im);
+
+	let mut energy = [0u64; BANDS];
+
+	for band in 0..BANDS {
+		for bin in BAND_EDGES[band]..BAND_EDGES[band + 1] {
+			energy[band] += (re[bin] as i64 * re[bin] as i64 + im[bin] as i64 * im[bin] as i64) as u64;
+		}
+	}
+
+	energy
+}
+
+/// Base 2 logarithm in 8.8 fixed point. The fraction is a straight line between powers of two, which
+/// is never more than 0.09 out. `log2(0)` is 0.
+pub fn log2(value: u64) -> i32 {
+	if value == 0 {
+		return 0;
+	}
+
+	let whole = 63 - value.leading_zeros() as i32;
+
+	// The 8 bits below the leading one
+	let fraction = if whole >= 8 { (value >> (whole - 8)) & 0xff } else { (value << (8 - whole)) & 0xff };
+
+	(whole << 8) + fraction as i32
+}
+
+/// Turns blocks of audio into band levels for display, with automatic gain, falloff and peak hold
+pub struct Analyser {
+	/// Displayed level of each band, out of 255
+	pub levels: [u8; BANDS],
+	/// Recent peak of each band, out of 255
+	pub peaks: [u8; BANDS],
+	/// Blocks left before each peak starts to fall
+	hold: [u8; BANDS],
+	/// Log2 energy that shows as a full column, following the loudest band
+	ceiling: i32,
+}
+
+impl Analyser {
+	pub fn new() -> Analyser {
+		Analyser {
+			levels: [0; BANDS],
+			peaks: [0; BANDS],
+			hold: [0; BANDS],
+			ceiling: MIN_CEILING,
+		}
+	}
+
+	/// Update the levels from the band energies of the next block, from `band_energy()`
+	pub fn update(&mut self, energy: &[u64; BANDS]) {
+		let loudest = energy.iter().map(|&e| log2(e)).max().unwrap_or(0);
+
+		// Jump straight up to loud sounds but only turn the gain back up slowly
+		self.ceiling = if loudest > self.ceiling { loudest } else { self.ceiling - CEILING_DECAY };
+
+		if self.ceiling < MIN_CEILING {
+			self.ceiling = MIN_CEILING;
+		}
+
+		let floor = self.ceiling - RANGE;
+
+		for band in 0..BANDS {
+			let level = match log2(energy[band]) - floor {
+				above if above <= 0 => 0,
+				above if above >= RANGE => 255,
+				above => (above * 255 / RANGE) as u8,
+			};
+
+			// Rise straight away but fall gradually
+			let fallen = self.levels[band].saturating_sub(LEVEL_DECAY);
+
+			self.levels[band] = if level > fallen { level } else { fallen };
+
+			if self.levels[band] >= self.peaks[band] {
+				self.peaks[band] = self.levels[band];
+				self.hold[band] = PEAK_HOLD;
+			} else if self.hold[band] > 0 {
+				self.hold[band] -= 1;
+			} else {
+				self.peaks[band] = self.peaks[band].saturating_sub(PEAK_DECAY);
+			}
+		}
+	}
+}
+
+/// Everything a scheduler works out from the audio it hears: the latest band energies and the beat
+pub struct Listener {
+	pub energy: [u64; BANDS],
+	pub tracker: BeatTracker,
+}
+
+impl Listener {
+	pub fn new() -> Listener {
+		Listener {
+			energy: [0; BANDS],
+			tracker: BeatTracker::new(),
+		}
+	}
+
+	/// Analyse a block of samples captured `elapsed_us` after the previous one
+	pub fn listen(&mut self, samples: &[i16; FFT_SIZE], elapsed_us: u32) {
+		self.energy = band_energy(samples);
+		self.tracker.update(&self.energy, elapsed_us);
+	}
+}
@@ -0,0 +1,210 @@
+//! 3D cellular automata on the 4x4x4 grid. Every cell looks at its 26 neighbours each generation.
+//!
+//! Rules are written as birth/survival sets like 2D Life's B3/S23, with bit `n` set for `n` live
+//! neighbours. Rules with more than two states are "generations" rules: instead of dying straight away a
+//! cell that fails its survival test goes through the extra states as it decays and can't be reborn
+//! until it's back to dead. Brian's Brain is one of these with three states and no survival.
+
+use apa106led::Apa106Led;
+use colour_functions::scale;
+use cube::{ Cube4, Voxel, CUBE_SIZE };
+use embedded_rand::rand_range;
+
+#[derive(Copy, Clone)]
+pub struct Rule {
+	/// Bit `n` set means a dead cell with `n` live neighbours comes alive
+	pub birth: u32,
+	/// Bit `n` set means a live cell with `n` live neighbours stays alive
+	pub survival: u32,
+	/// Number of states including dead and alive. 2 for Life-like rules.
+	pub states: u8,
+	/// Treat opposite faces of the cube as touching
+	pub wrap: bool,
+}
+
+/// Bays' 3D Life 4555: born with 5 neighbours, survives with 4 or 5. Bays' rules are meant for much
+/// bigger grids and usually settle within 10 generations on this one.
+pub const LIFE_4555: Rule = Rule { birth: 1 << 5, survival: (1 << 4) | (1 << 5), states: 2, wrap: true };
+
+/// Bays' 3D Life 5766: born with 6 neighbours, survives with 5 to 7
+pub const LIFE_5766: Rule = Rule { birth: 1 << 6, survival: (1 << 5) | (1 << 6) | (1 << 7), states: 2, wrap: true };
+
+/// Born with 4 neighbours, survives with 5 to 7. Lasts longer than Bays' rules on a 4x4x4 board.
+pub const SMALL_LIFE: Rule = Rule { birth: 1 << 4, survival: (1 << 5) | (1 << 6) | (1 << 7), states: 2, wrap: true };
+
+/// Generations rule born with 3 or 4 neighbours and surviving with 7 to 9, with two decaying states
+/// that leave glowing embers behind
+pub const EMBERS: Rule = Rule { birth: (1 << 3) | (1 << 4), survival: (1 << 7) | (1 << 8) | (1 << 9), states: 4, wrap: true };
+
+/// Brian's Brain: born with exactly 2 firing neighbours, always spends one generation dying. It burns
+/// out in a few generations if the board wraps, so it doesn't.
+pub const BRIANS_BRAIN: Rule = Rule { birth: 1 << 2, survival: 0, states: 3, wrap: false };
+
+const DEAD: u8 = 0;
+const ALIVE: u8 = 1;
+
+/// How many previous generations are remembered to spot oscillators
+const HISTORY: usize = 4;
+
+fn index(x: u8, y: u8, z: u8) -> usize {
+	(x + y * CUBE_SIZE + z * CUBE_SIZE * CUBE_SIZE) as usize
+}
+
+pub struct Automaton {
+	pub rule: Rule,
+	states: [u8; 64],
+	/// Generations each cell has been alive for, carried on while it decays so it keeps its colour
+	ages: [u8; 64],
+	/// Hashes of the last few generations
+	history: [u32; HISTORY],
+	/// Number of generations since the last seed
+	pub generation: u16,
+}
+
+impl Automaton {
+	pub fn new(rule: Rule) -> Automaton {
+		Automaton {
+			rule: rule,
+			states: [DEAD; 64],
+			ages: [0; 64],
+			history: [0; HISTORY],
+			generation: 0,
+		}
+	}
+
+	/// Fill the board at random. `density` is the chance of each cell starting alive, out of 255.
+	pub fn seed(&mut self, density: u8) {
+		for cell in 0..64 {
+			self.states[cell] = if rand_range(0, 254) < density as u32 { ALIVE } else { DEAD };
+			self.ages[cell] = 0;
+		}
+
+		self.history = [0; HISTORY];
+		self.generation = 0;
+	}
+
+	/// Number of live cells, not counting decaying ones
+	pub fn population(&self) -> u8 {
+		self.states.iter().filter(|&&state| state == ALIVE).count() as u8
+	}
+
+	fn live_neighbours(&self, x: u8, y: u8, z: u8) -> u8 {
+		let size = CUBE_SIZE as i8;
+		let mut count = 0;
+
+		for dz in -1..2 {
+			for dy in -1..2 {
+				for dx in -1..2 {
+					if dx == 0 && dy == 0 && dz == 0 {
+						continue;
+					}
+
+					let (mut nx, mut ny, mut nz) = (x as i8 + dx, y as i8 + dy, z as i8 + dz);
+
+					if self.rule.wrap {
+						nx = (nx + size) % size;
+						ny = (ny + size) % size;
+						nz = (nz + size) % size;
+					} else if nx < 0 || ny < 0 || nz < 0 || nx >= size || ny >= size || nz >= size {
+						continue;
+					}
+
+					if self.states[index(nx as u8, ny as u8, nz as u8)] == ALIVE {
+						count += 1;
+					}
+				}
+			}
+		}
+
+		count
+	}
+
+	fn hash(&self) -> u32 {
+		// FNV-1a
+		self.states.iter().fold(2166136261, |hash, &state| (hash ^ state as u32).wrapping_mul(16777619))
+	}
+
+	/// Work out the next generation
+	pub fn step(&mut self) {
+		let mut next = self.states;
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					let cell = index(x, y, z);
+					let neighbours = self.live_neighbours(x, y, z);
+
+					next[cell] = match self.states[cell] {
+						DEAD if self.rule.birth & (1 << neighbours) != 0 => {
+							self.ages[cell] = 0;
+
+							ALIVE
+						},
+						DEAD => DEAD,
+						ALIVE if self.rule.survival & (1 << neighbours) != 0 => {
+							self.ages[cell] = self.ages[cell].saturating_add(1);
+
+							ALIVE
+						},
+						// Decay through the extra states, or die straight away in two state rules
+						state if state + 1 < self.rule.states => state + 1,
+						_ => DEAD,
+					};
+				}
+			}
+		}
+
+		for slot in (1..HISTORY).rev() {
+			self.history[slot] = self.history[slot - 1];
+		}
+
+		self.history[0] = self.hash();
+		self.states = next;
+		self.generation = self.generation.saturating_add(1);
+	}
+
+	/// True once everything has died or the board has settled into a still life or an oscillator with a
+	/// short period. Reseed it to keep things interesting.
+	pub fn is_stable(&self) -> bool {
+		if self.states.iter().all(|&state| state == DEAD) {
+			return true;
+		}
+
+		// The history doesn't fill up until a few generations after seeding
+		if (self.generation as usize) < HISTORY {
+			return false;
+		}
+
+		let hash = self.hash();
+
+		self.history.contains(&hash)
+	}
+
+	/// Draw the board. Live cells are coloured from `palette` by how many generations they've been
+	/// alive for, sticking at the last colour. Decaying cells fade out.
+	pub fn render(&self, cube: &mut Cube4, palette: &[Apa106Led]) {
+		let off = Apa106Led { red: 0, green: 0, blue: 0 };
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					let cell = index(x, y, z);
+					let age = self.ages[cell] as usize;
+
+					let colour = match palette.len() {
+						0 => off,
+						len => palette[if age < len { age } else { len - 1 }],
+					};
+
+					let colour = match self.states[cell] {
+						DEAD => off,
+						ALIVE => colour,
+						state => scale(colour, ((self.rule.states - state) as u16 * 255 / self.rule.states as u16) as u8),
+					};
+
+					cube.set_at_coord(Voxel { x: x, y: y, z: z }, colour);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,303 @@
+//! Beat detection and tempo estimation from the band energies `audio` works out for each block.
+//!
+//! Onsets are found with spectral flux: how much louder each band got since the last block, summed
+//! over the bands, with the kick drum's bands counting extra. The tempo is the lag at which the recent
+//! history of that flux best lines up with itself (autocorrelation), and the beat clock's phase is
+//! where a comb of pulses at that tempo best lines up with the history.
+//!
+//! Tempos are reported between `MIN_BPM` and `MAX_BPM`, which is one octave, so 174BPM drum and bass
+//! shows up as 87BPM. Either is fine for animations.
+
+use audio::{ log2, BANDS };
+
+pub const MIN_BPM: u32 = 80;
+pub const MAX_BPM: u32 = 160;
+
+/// Blocks of onset strength kept for the autocorrelation. 256 is about 4 seconds.
+const HISTORY: usize = 256;
+
+/// Longest lag the autocorrelation looks at. Tempos are scored at two beats as well as one, so this is
+/// enough for `MIN_BPM` with blocks as short as 12ms.
+const MAX_LAG: usize = 128;
+
+/// Blocks between tempo estimates
+const TEMPO_INTERVAL: u8 = 16;
+
+/// Bands quieter than this (log2 energy in 8.8 fixed point) are treated as silent, so noise doesn't
+/// make onsets
+const QUIET: i32 = 24 << 8;
+
+/// Bands that make up the kick drum: up to about 250Hz
+const KICK_BANDS: usize = 3;
+
+/// How many times over flux in the kick drum's bands counts. Kicks usually mark the beat, where
+/// hi-hats are just as likely to be on the off beats.
+const KICK_WEIGHT: i32 = 3;
+
+/// A kick has to be this many times the average flux in its bands, in 16ths...
+const KICK_RATIO: i32 = 28;
+
+/// ...and at least this big, so quiet, steady music doesn't trigger kicks on every wobble
+const MIN_KICK: i32 = 3 << 8;
+
+/// Score (the sum of three normalised autocorrelations, out of 768) needed before a tempo is reported
+const MIN_CONFIDENCE: i32 = 150;
+
+/// Beat clock state handed to patterns
+#[derive(Copy, Clone)]
+pub struct Beat {
+	/// Beats since listening started. Patterns can watch for it changing to do something on the beat.
+	pub count: u32,
+	/// How far through the current beat, out of 256
+	pub phase: u8,
+	/// Tempo in beats per minute, or 0 if there's no music with a steady beat
+	pub bpm: u16,
+	/// A kick drum was heard since the last time the beat was read
+	pub kick: bool,
+}
+
+/// Tracks onsets, tempo and beat phase as blocks of audio come in
+pub struct BeatTracker {
+	/// Log2 energy of each band in the previous block
+	previous: [i32; BANDS],
+	/// Onset strength of recent blocks, as a ring buffer
+	history: [u16; HISTORY],
+	next: usize,
+	/// Running average of the flux in the kick drum's bands, in 8.8 fixed point
+	average_kick: i32,
+	/// Smoothed, normalised autocorrelation of the onset history at each lag, out of 256
+	correlation: [i32; MAX_LAG + 1],
+	until_tempo: u8,
+	/// Average time between blocks
+	block_us: u32,
+	/// Length of a beat, or 0 if there's no tempo yet
+	period_us: u32,
+	/// Time since the last beat on the beat clock
+	since_beat_us: u32,
+	/// Time since the last kick, to keep one kick from being heard in consecutive blocks
+	since_kick_us: u32,
+	count: u32,
+	kick: bool,
+}
+
+impl BeatTracker {
+	pub fn new() -> BeatTracker {
+		BeatTracker {
+			previous: [QUIET; BANDS],
+			history: [0; HISTORY],
+			next: 0,
+			average_kick: 0,
+			correlation: [0; MAX_LAG + 1],
+			until_tempo: TEMPO_INTERVAL,
+			block_us: 16_000,
+			period_us: 0,
+			since_beat_us: 0,
+			since_kick_us: 0,
+			count: 0,
+			kick: false,
+		}
+	}
+
+	/// Feed in the band energies of the next block. `elapsed_us` is the time since the previous block.
+	pub fn update(&mut self, energy: &[u64; BANDS], elapsed_us: u32) {
+		let mut flux = 0;
+		let mut kick_flux = 0;
+
+		for band in 0..BANDS {
+			let level = log2(energy[band]);
+			let level = if level < QUIET { QUIET } else { level };
+			let rise = level - self.previous[band];
+
+			if rise > 0 {
+				flux += rise;
+
+				if band < KICK_BANDS {
+					kick_flux += rise;
+				}
+			}
+
+			self.previous[band] = level;
+		}
+
+		let strength = flux + (KICK_WEIGHT - 1) * kick_flux;
+
+		self.history[self.next] = if strength > 0xffff { 0xffff } else { strength as u16 };
+		self.next = (self.next + 1) % HISTORY;
+
+		// Blocks are evenly spaced on the host but not quite on the cube, where they stop while frames
+		// are drawn
+		self.block_us = self.block_us - self.block_us / 16 + elapsed_us / 16;
+
+		self.advance_clock(elapsed_us);
+
+		// A quarter of a beat between kicks, or 100ms until there's a tempo
+		let gap_us = if self.period_us > 0 { self.period_us / 4 } else { 100_000 };
+
+		if kick_flux >= MIN_KICK && kick_flux * 16 > self.average_kick * KICK_RATIO && self.since_kick_us >= gap_us {
+			self.since_kick_us = 0;
+			self.kick = true;
+		}
+
+		self.average_kick += (kick_flux - self.average_kick) / 16;
+
+		self.until_tempo -= 1;
+
+		if self.until_tempo == 0 {
+			self.until_tempo = TEMPO_INTERVAL;
+			self.estimate_tempo();
+		}
+	}
+
+	fn advance_clock(&mut self, elapsed_us: u32) {
+		self.since_kick_us = self.since_kick_us.saturating_add(elapsed_us);
+
+		if self.period_us == 0 {
+			return;
+		}
+
+		self.since_beat_us += elapsed_us;
+
+		while self.since_beat_us >= self.period_us {
+			self.since_beat_us -= self.period_us;
+			self.count = self.count.wrapping_add(1);
+		}
+	}
+
+	/// Onset strength `age` blocks ago, with `age` 0 being the latest
+	fn onset_history(&self, age: usize) -> i32 {
+		self.history[(self.next + HISTORY - 1 - age) % HISTORY] as i32
+	}
+
+	fn estimate_tempo(&mut self) {
+		// Smooth the history a little, latest first. Beats rarely fall on a whole number of blocks, and
+		// without this the autocorrelation peaks are so narrow that the ones that do are favoured.
+		let mut smoothed = [0i32; HISTORY];
+
+		for age in 0..HISTORY {
+			let newer = self.onset_history(if age > 0 { age - 1 } else { age });
+			let older = self.onset_history(if age < HISTORY - 1 { age + 1 } else { age });
+
+			smoothed[age] = (newer + 2 * self.onset_history(age) + older) / 4;
+		}
+
+		let mut total = 0;
+
+		for value in smoothed.iter() {
+			total += *value;
+		}
+
+		let mean = total / HISTORY as i32;
+
+		for value in smoothed.iter_mut() {
+			*value -= mean;
+		}
+
+		let correlate = |lag: usize| {
+			(lag..HISTORY).fold(0i64, |sum, age| sum + smoothed[age] as i64 * smoothed[age - lag] as i64)
+		};
+
+		let energy = correlate(0);
+
+		for lag in 1..MAX_LAG + 1 {
+			let normalised = if energy > 0 { (correlate(lag) * 256 / energy) as i32 } else { 0 };
+
+			self.correlation[lag] += (normalised - self.correlation[lag]) / 4;
+		}
+
+		// Lags that fit between the tempo limits
+		let shortest = (60_000_000 / MAX_BPM / self.block_us) as usize;
+		let longest = (60_000_000 / MIN_BPM / self.block_us) as usize;
+		let shortest = if shortest < 2 { 2 } else { shortest };
+		let longest = if longest > MAX_LAG / 2 - 1 { MAX_LAG / 2 - 1 } else { longest };
+
+		// Music that repeats every beat usually repeats every half beat and every two beats as well, while
+		// the other fractions and multiples of a beat that can line up (like one and a half beats, when
+		// there are hi-hats on the off beats) don't. Scoring all three picks out the real tempo.
+		let mut score = [0i32; MAX_LAG / 2 + 1];
+
+		for lag in 1..MAX_LAG / 2 + 1 {
+			score[lag] = self.correlation[lag / 2] + self.correlation[lag] + self.correlation[lag * 2];
+		}
+
+		let mut best = shortest;
+
+		for lag in shortest..longest + 1 {
+			if score[lag] > score[best] {
+				best = lag;
+			}
+		}
+
+		if score[best] < MIN_CONFIDENCE {
+			self.period_us = 0;
+
+			return;
+		}
+
+		// Fit a parabola through the peak and its neighbours to get the lag to a fraction of a block
+		let (before, peak, after) = (score[best - 1], score[best], score[best + 1]);
+		let curve = before - 2 * peak + after;
+		let offset = if curve < 0 { (before - after) * 128 / curve } else { 0 };
+		let offset = if offset > 128 { 128 } else if offset < -128 { -128 } else { offset };
+
+		// In 256ths of a block
+		let lag = (best as i32 * 256 + offset) as usize;
+
+		// Find how long ago the last beat was by trying a comb of pulses a beat apart at each offset
+		let mut best_phase = 0;
+		let mut best_sum = i32::min_value();
+
+		for phase in 0..lag / 256 + 1 {
+			let mut sum = 0;
+			let mut tooth = phase * 256;
+
+			while tooth / 256 < HISTORY {
+				sum += smoothed[tooth / 256];
+				tooth += lag;
+			}
+
+			if sum > best_sum {
+				best_phase = phase;
+				best_sum = sum;
+			}
+		}
+
+		self.period_us = lag as u32 * self.block_us / 256;
+
+		// Onsets are heard at the end of the block they're in, on average half a block after they happen
+		let since_beat = (best_phase as u32 * self.block_us + self.block_us / 2) % self.period_us;
+
+		self.pull_phase(since_beat);
+	}
+
+	/// Move the beat clock halfway towards `since_beat_us`, the shortest way round
+	fn pull_phase(&mut self, since_beat_us: u32) {
+		let period = self.period_us as i32;
+		let mut error = since_beat_us as i32 - self.since_beat_us as i32;
+
+		if error > period / 2 {
+			error -= period;
+		} else if error < -period / 2 {
+			error += period;
+		}
+
+		let adjusted = self.since_beat_us as i32 + error / 2;
+
+		// Don't go back past a beat that's already been counted. Going forward past one is fine, the
+		// clock will count it on the next block.
+		self.since_beat_us = if adjusted < 0 { 0 } else { adjusted as u32 };
+	}
+
+	/// Read the beat clock. Clears the kick flag.
+	pub fn beat(&mut self) -> Beat {
+		let (phase, bpm) = match self.period_us {
+			0 => (0, 0),
+			period => ((self.since_beat_us as u64 * 256 / period as u64) as u8, ((60_000_000 + period / 2) / period) as u16),
+		};
+
+		let beat = Beat { count: self.count, phase: phase, bpm: bpm, kick: self.kick };
+
+		self.kick = false;
+
+		beat
+	}
+}
@@ -0,0 +1,173 @@
+//! Calendar dates and times of day, for the schedule and for patterns that follow the time.
+//!
+//! Times are counted in seconds since the start of 1970, like Unix time, but there are no time zones:
+//! the count is whatever local time was set over the console. Nothing here touches the hardware; see
+//! `rtc` for the clock itself.
+
+use console::Text;
+
+pub const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+pub const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// Years that can be set. The count of seconds runs out in 2106.
+pub const FIRST_YEAR: u16 = 2000;
+pub const LAST_YEAR: u16 = 2099;
+
+/// Any count below this can't have been set, as it's before `FIRST_YEAR`
+pub const EARLIEST_SET: u32 = 946_684_800;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct DateTime {
+	pub year: u16,
+	/// 1 for January to 12 for December
+	pub month: u8,
+	/// Day of the month, from 1
+	pub day: u8,
+	pub hour: u8,
+	pub minute: u8,
+	pub second: u8,
+}
+
+fn is_leap_year(year: u16) -> bool {
+	(year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_year(year: u16) -> u32 {
+	if is_leap_year(year) { 366 } else { 365 }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+	match month {
+		2 => if is_leap_year(year) { 29 } else { 28 },
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+impl DateTime {
+	pub fn from_seconds(seconds: u32) -> DateTime {
+		let mut days = seconds / SECONDS_PER_DAY;
+		let time = seconds % SECONDS_PER_DAY;
+
+		let mut year = 1970;
+
+		while days >= days_in_year(year) {
+			days -= days_in_year(year);
+			year += 1;
+		}
+
+		let mut month = 1;
+
+		while days >= days_in_month(year, month) as u32 {
+			days -= days_in_month(year, month) as u32;
+			month += 1;
+		}
+
+		DateTime {
+			year: year,
+			month: month,
+			day: days as u8 + 1,
+			hour: (time / 3600) as u8,
+			minute: (time / 60 % 60) as u8,
+			second: (time % 60) as u8,
+		}
+	}
+
+	pub fn to_seconds(&self) -> u32 {
+		let mut days = 0;
+
+		for year in 1970..self.year {
+			days += days_in_year(year);
+		}
+
+		for month in 1..self.month {
+			days += days_in_month(self.year, month) as u32;
+		}
+
+		days += self.day as u32 - 1;
+
+		days * SECONDS_PER_DAY + self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+	}
+
+	/// Minutes since midnight
+	pub fn minute_of_day(&self) -> u16 {
+		self.hour as u16 * 60 + self.minute as u16
+	}
+
+	/// Write as `YYYY-MM-DD HH:MM:SS`, the way `parse()` reads it
+	pub fn format(&self) -> Text {
+		let mut bytes = [0u8; 19];
+
+		write_digits(self.year as u32, &mut bytes[0..4]);
+		bytes[4] = b'-';
+		write_digits(self.month as u32, &mut bytes[5..7]);
+		bytes[7] = b'-';
+		write_digits(self.day as u32, &mut bytes[8..10]);
+		bytes[10] = b' ';
+		write_digits(self.hour as u32, &mut bytes[11..13]);
+		bytes[13] = b':';
+		write_digits(self.minute as u32, &mut bytes[14..16]);
+		bytes[16] = b':';
+		write_digits(self.second as u32, &mut bytes[17..19]);
+
+		Text::new(&bytes)
+	}
+}
+
+/// Fill `buffer` with the last digits of `number`, padding with zeros
+fn write_digits(mut number: u32, buffer: &mut [u8]) {
+	for byte in buffer.iter_mut().rev() {
+		*byte = b'0' + (number % 10) as u8;
+		number /= 10;
+	}
+}
+
+/// A number written with exactly `text.len()` digits
+fn parse_digits(text: &[u8]) -> Option<u32> {
+	let mut number = 0;
+
+	for &byte in text {
+		match byte {
+			b'0'...b'9' => number = number * 10 + (byte - b'0') as u32,
+			_ => return None,
+		}
+	}
+
+	Some(number)
+}
+
+/// Parse a date and time typed at the console: `YYYY-MM-DD HH:MM`, optionally followed by `:SS`
+pub fn parse(text: &[u8]) -> Option<DateTime> {
+	if (text.len() != 16 && text.len() != 19) || text[4] != b'-' || text[7] != b'-' || text[10] != b' ' || text[13] != b':' {
+		return None;
+	}
+
+	if text.len() == 19 && text[16] != b':' {
+		return None;
+	}
+
+	let second = if text.len() == 19 { parse_digits(&text[17..19]) } else { Some(0) };
+
+	let fields = (parse_digits(&text[0..4]), parse_digits(&text[5..7]), parse_digits(&text[8..10]),
+		parse_digits(&text[11..13]), parse_digits(&text[14..16]), second);
+
+	let time = match fields {
+		(Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) => DateTime {
+			year: year as u16,
+			month: month as u8,
+			day: day as u8,
+			hour: hour as u8,
+			minute: minute as u8,
+			second: second as u8,
+		},
+		_ => return None,
+	};
+
+	if time.year < FIRST_YEAR || time.year > LAST_YEAR || time.month < 1 || time.month > 12 || time.day < 1
+		|| time.day > days_in_month(time.year, time.month) || time.hour > 23 || time.minute > 59 || time.second > 59 {
+		return None;
+	}
+
+	Some(time)
+}
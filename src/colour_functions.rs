@@ -1,5 +1,6 @@
-use tables::BLACKBODY_MAP;
 use apa106led::Apa106Led;
+use fixed::{ Fixed8, Fixed16 };
+use tables::BLACKBODY_MAP;
 
 pub fn rgb_wheel(wheelpos: u8) -> Apa106Led {
 	let mut thingy = wheelpos;
@@ -35,11 +36,25 @@ pub fn christmas_wheel(wheelpos: u8) -> Apa106Led {
 	}
 }
 
-pub fn temp_to_rgb(kelvin: u32) -> Apa106Led {
-	let interval = 20;
+/// Colour of a black body at `kelvin` from `BLACKBODY_MAP`, rounded down to a 20K step. Cheaper than
+/// `temp_to_rgb()` for colouring every voxel of a frame, as `fire` does. Temperatures are clamped to
+/// 500K to 16000K.
+pub fn blackbody(kelvin: u32) -> Apa106Led {
+	let kelvin = if kelvin < 500 {
+		500
+	} else if kelvin > 16000 {
+		16000
+	} else {
+		kelvin
+	};
+
+	BLACKBODY_MAP[((kelvin - 500) / 20) as usize]
+}
 
-	// Clamp
-	let temp = if kelvin < 500 {
+/// Colour of a black body at `kelvin`, using Tanner Helland's fit to the CIE colour matching functions.
+/// Temperatures are clamped to 500K to 16000K.
+pub fn temp_to_rgb(kelvin: u32) -> Apa106Led {
+	let kelvin = if kelvin < 500 {
 		500
 	} else if kelvin > 16000 {
 		16000
@@ -47,45 +62,67 @@ pub fn temp_to_rgb(kelvin: u32) -> Apa106Led {
 		kelvin
 	};
 
-	let index = (temp - 500) / interval;
-
-	BLACKBODY_MAP[index as usize]
-
-	// Round to nearest unit (in our case, 20)
-	// (n + 4) / 5 * 5
-
-	// let nearest =
-
-	// Actual good implementation using lots of intrinsics that isn't supported well/at all/something else on ARM CPUs
-	//
-	// let temp: f32 = kelvin as f32 / 100.0;
-
-	// if temp <= 66.0 {
-	// 	Apa106Led {
-	// 		red: 255,
-	// 		green: clamp_to_u8(99.4708025861 * unsafe { intrinsics::logf32(temp) } - 161.1195681661),
-	// 		blue:
-	// 			if temp <= 19.0 {
-	// 				0
-	// 			} else {
-	// 				clamp_to_u8(138.5177312231 * unsafe { intrinsics::logf32(temp - 10.0) } - 305.0447927307)
-	// 			},
-	// 	}
-	// } else {
-	// 	Apa106Led {
-	// 		red: clamp_to_u8(329.698727446 * unsafe { intrinsics::powf32(temp - 60.0, -0.1332047592) }),
-	// 		green: clamp_to_u8(288.1221695283 * unsafe { intrinsics::powf32(temp - 60.0, -0.1332047592) }),
-	// 		blue: clamp_to_u8(255.0),
-	// 	}
-	// }
+	let temp = Fixed16::from_ratio(kelvin as i32, 100);
+
+	if temp <= Fixed16::from_int(66) {
+		// 99.4708025861 * ln(temp) - 161.1195681661
+		let green = Fixed16(6_518_919) * temp.ln() - Fixed16(10_559_132);
+
+		// 138.5177312231 * ln(temp - 10) - 305.0447927307
+		let blue = if temp <= Fixed16::from_int(19) {
+			0
+		} else {
+			clamp_channel(Fixed16(9_077_898) * (temp - Fixed16::from_int(10)).ln() - Fixed16(19_991_416))
+		};
+
+		Apa106Led { red: 255, green: clamp_channel(green), blue: blue }
+	} else {
+		let temp = temp - Fixed16::from_int(60);
+
+		Apa106Led {
+			// 329.698727446 * temp ^ -0.1332047592
+			red: clamp_channel(Fixed16(21_607_136) * temp.pow(Fixed16(-8_730))),
+			// 288.1221695283 * temp ^ -0.0755148492
+			green: clamp_channel(Fixed16(18_882_375) * temp.pow(Fixed16(-4_949))),
+			blue: 255,
+		}
+	}
 }
 
-pub fn fade(colour: Apa106Led, multiplier: f32) -> Apa106Led {
-	let divisor: u8 = (1.0 / multiplier) as u8;
+/// Round a channel value down, keeping it between 0 and 255
+fn clamp_channel(value: Fixed16) -> u8 {
+	let value = value.floor();
+
+	if value < 0 { 0 } else if value > 255 { 255 } else { value as u8 }
+}
 
+/// Scale a colour's brightness by `multiplier`, which is usually between 0 and 1
+pub fn fade(colour: Apa106Led, multiplier: Fixed8) -> Apa106Led {
 	Apa106Led {
-		red: (colour.red / divisor) as u8,
-		green: (colour.green / divisor) as u8,
-		blue: (colour.blue / divisor) as u8,
+		red: multiplier.scale(colour.red),
+		green: multiplier.scale(colour.green),
+		blue: multiplier.scale(colour.blue),
 	}
-}
\ No newline at end of file
+}
+
+/// Scale a colour's brightness by `amount / 255`
+pub fn scale(colour: Apa106Led, amount: u8) -> Apa106Led {
+	let channel = |value: u8| ((value as u16 * amount as u16 + 127) / 255) as u8;
+
+	Apa106Led {
+		red: channel(colour.red),
+		green: channel(colour.green),
+		blue: channel(colour.blue),
+	}
+}
+
+/// Blend between two colours. `t` of 0 is all `from`, 255 is all `to`
+pub fn lerp_colour(from: Apa106Led, to: Apa106Led, t: u8) -> Apa106Led {
+	let channel = |a: u8, b: u8| (a as i32 + ((b as i32 - a as i32) * t as i32 + 127) / 255) as u8;
+
+	Apa106Led {
+		red: channel(from.red, to.red),
+		green: channel(from.green, to.green),
+		blue: channel(from.blue, to.blue),
+	}
+}
@@ -0,0 +1,87 @@
+//! Line based text commands over the UART, e.g. `text HELLO` to change what the marquee scrolls. The
+//! same bytes also go to the frame protocol parsers. Bytes that arrive partway through a frame are
+//! kept away from the console and the line they interrupt is thrown away, as are lines containing
+//! anything other than printable ASCII, so frame data never runs as a command.
+
+pub const TEXT_LEN: usize = 64;
+
+/// A short string that doesn't need an allocator
+#[derive(Copy, Clone)]
+pub struct Text {
+	bytes: [u8; TEXT_LEN],
+	len: usize,
+}
+
+impl Text {
+	/// Copy `bytes` into a new `Text`, cutting it off at `TEXT_LEN` bytes
+	pub fn new(bytes: &[u8]) -> Text {
+		let mut text = Text { bytes: [0; TEXT_LEN], len: 0 };
+
+		for &byte in bytes.iter().take(TEXT_LEN) {
+			text.bytes[text.len] = byte;
+			text.len += 1;
+		}
+
+		text
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes[..self.len]
+	}
+}
+
+pub struct Console {
+	line: [u8; TEXT_LEN],
+	len: usize,
+	/// Set when the current line is too long or contains a byte that can't be part of a command
+	discard: bool,
+}
+
+impl Console {
+	pub fn new() -> Console {
+		Console {
+			line: [0; TEXT_LEN],
+			len: 0,
+			discard: false,
+		}
+	}
+
+	/// Throw away the line received so far, up to the next CR or LF
+	pub fn discard(&mut self) {
+		self.discard = true;
+	}
+
+	/// Feed in one received byte. Returns the line once a CR or LF ends it. Empty and discarded lines
+	/// aren't returned.
+	pub fn push(&mut self, byte: u8) -> Option<Text> {
+		match byte {
+			b'\r' | b'\n' => {
+				let line = if self.discard || self.len == 0 { None } else { Some(Text::new(&self.line[..self.len])) };
+
+				self.len = 0;
+				self.discard = false;
+
+				line
+			},
+			0x20...0x7e if self.len < TEXT_LEN => {
+				self.line[self.len] = byte;
+				self.len += 1;
+
+				None
+			},
+			_ => {
+				self.discard = true;
+
+				None
+			},
+		}
+	}
+}
+
+/// Split a line into its first word and the rest of the line, without the space between them
+pub fn split_command(line: &[u8]) -> (&[u8], &[u8]) {
+	match line.iter().position(|&byte| byte == b' ') {
+		Some(space) => (&line[..space], &line[space + 1..]),
+		None => (line, &[]),
+	}
+}
@@ -0,0 +1,119 @@
+//! Buttons and rotary encoder. Like `adc`, this pokes the GPIO registers directly because zinc can't
+//! turn on pull ups or unlock PF0.
+//!
+//! SW1 and SW2 on the LaunchPad are PF4 and PF0, and short to ground when pressed. PF0 doubles as an
+//! NMI input, so it's locked against changes until the magic value is written to its port's lock
+//! register. The encoder is optional: its A and B pins go to PB0 and PB1 and its push switch to PB2,
+//! with the common pins to ground. Unconnected, the pull ups hold them all high and it never turns.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+use zinc::hal::timer::Timer;
+use zinc::hal::tiva_c::timer::{ Timer as TivaCTimer };
+
+use input::{ Action, Inputs, Pins };
+
+const SYSCTL_BASE: u32 = 0x400F_E000;
+const SYSCTL_RCGCGPIO: u32 = 0x608;
+
+const GPIOB_BASE: u32 = 0x4000_5000;
+const GPIOF_BASE: u32 = 0x4002_5000;
+
+// Register offsets within a port
+/// Reads through the data register are masked by bits 2 to 9 of the address; this offset reads all 8
+/// pins.
+const GPIO_DATA: u32 = 0x3FC;
+const GPIO_DIR: u32 = 0x400;
+const GPIO_AFSEL: u32 = 0x420;
+const GPIO_PUR: u32 = 0x510;
+const GPIO_DEN: u32 = 0x51C;
+const GPIO_LOCK: u32 = 0x520;
+const GPIO_CR: u32 = 0x524;
+const GPIO_AMSEL: u32 = 0x528;
+
+/// Written to GPIOLOCK to allow changes to the commit register
+const UNLOCK: u32 = 0x4C4F_434B;
+
+/// Clock gate bits for GPIO ports B and F in RCGCGPIO
+const GPIO_PORT_B: u32 = 1 << 1;
+const GPIO_PORT_F: u32 = 1 << 5;
+
+const SW1: u32 = 1 << 4;
+const SW2: u32 = 1 << 0;
+
+const ENCODER_A: u32 = 1 << 0;
+const ENCODER_B: u32 = 1 << 1;
+const ENCODER_BUTTON: u32 = 1 << 2;
+
+fn register(base: u32, offset: u32) -> *mut u32 {
+	(base + offset) as *mut u32
+}
+
+unsafe fn set_bits(base: u32, offset: u32, bits: u32) {
+	volatile_store(register(base, offset), volatile_load(register(base, offset)) | bits);
+}
+
+unsafe fn clear_bits(base: u32, offset: u32, bits: u32) {
+	volatile_store(register(base, offset), volatile_load(register(base, offset)) & !bits);
+}
+
+/// Make `pins` digital inputs with pull ups
+unsafe fn pull_up_inputs(base: u32, pins: u32) {
+	clear_bits(base, GPIO_DIR, pins);
+	clear_bits(base, GPIO_AFSEL, pins);
+	clear_bits(base, GPIO_AMSEL, pins);
+	set_bits(base, GPIO_PUR, pins);
+	set_bits(base, GPIO_DEN, pins);
+}
+
+pub struct Controls<'a> {
+	timer: &'a TivaCTimer,
+
+	inputs: Inputs,
+}
+
+impl<'a> Controls<'a> {
+	/// Set up the button and encoder pins
+	pub fn new(timer: &'a TivaCTimer) -> Controls<'a> {
+		unsafe {
+			set_bits(SYSCTL_BASE, SYSCTL_RCGCGPIO, GPIO_PORT_B | GPIO_PORT_F);
+
+			// The ports take a few clocks to come out of reset
+			timer.wait_us(10);
+
+			volatile_store(register(GPIOF_BASE, GPIO_LOCK), UNLOCK);
+			set_bits(GPIOF_BASE, GPIO_CR, SW2);
+
+			pull_up_inputs(GPIOF_BASE, SW1 | SW2);
+			pull_up_inputs(GPIOB_BASE, ENCODER_A | ENCODER_B | ENCODER_BUTTON);
+
+			// Lock PF0 again
+			volatile_store(register(GPIOF_BASE, GPIO_LOCK), 0);
+		}
+
+		Controls {
+			timer: timer,
+
+			inputs: Inputs::new(),
+		}
+	}
+
+	/// Read the pins, returning anything they ask for. Call this every millisecond or so; buttons are
+	/// debounced over 20ms.
+	pub fn poll(&mut self) -> Option<Action> {
+		let (port_b, port_f) = unsafe {
+			(volatile_load(register(GPIOB_BASE, GPIO_DATA)), volatile_load(register(GPIOF_BASE, GPIO_DATA)))
+		};
+
+		// Everything is pulled up and shorted to ground when active
+		let pins = Pins {
+			sw1: port_f & SW1 == 0,
+			sw2: port_f & SW2 == 0,
+			encoder_a: port_b & ENCODER_A != 0,
+			encoder_b: port_b & ENCODER_B != 0,
+			encoder_button: port_b & ENCODER_BUTTON == 0,
+		};
+
+		self.inputs.update(pins, self.timer.get_counter())
+	}
+}
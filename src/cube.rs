@@ -2,11 +2,28 @@ use zinc::hal::tiva_c;
 use zinc::hal::spi::Spi;
 
 use apa106led::Apa106Led;
+use tables::GAMMA_MAP;
+
+/// Number of voxels along each edge of the cube
+pub const CUBE_SIZE: u8 = 4;
+
+/// `set_brightness()` level that shows colours exactly as they're drawn
+pub const NORMAL_BRIGHTNESS: u8 = 16;
+
+/// How colours are adjusted on their way to the LEDs
+#[derive(Copy, Clone, PartialEq)]
+pub enum Correction {
+	/// Sent as drawn, which is what the patterns are drawn for
+	None,
+	/// Through `GAMMA_MAP`, so evenly spaced levels look evenly spaced. Suits frames streamed from PC
+	/// software, but makes the patterns very dim unless the brightness is turned up.
+	Gamma,
+}
 
 const ON_BYTE: u8 = 0b1111_1100;
 const OFF_BYTE: u8 = 0b1100_0000;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct Voxel {
 	pub x: u8,
 	pub y: u8,
@@ -17,6 +34,10 @@ pub struct Cube4<'a> {
 	spi: &'a tiva_c::spi::Spi,
 
 	cube_frame: [Apa106Led; 64],
+
+	/// Scale applied to every colour as it's sent, in 16ths
+	brightness: u8,
+	correction: Correction,
 }
 
 impl<'a> Cube4<'a> {
@@ -26,7 +47,10 @@ impl<'a> Cube4<'a> {
 		Cube4 {
 			spi: spi,
 
-			cube_frame: blank_frame
+			cube_frame: blank_frame,
+
+			brightness: NORMAL_BRIGHTNESS,
+			correction: Correction::None,
 		}
 	}
 
@@ -65,6 +89,18 @@ impl<'a> Cube4<'a> {
 		self.cube_frame[idx] = colour;
 	}
 
+	/// Add `colour` to whatever is already at `coord`, saturating at full brightness
+	pub fn add_at_coord(&mut self, coord: Voxel, colour: Apa106Led) {
+		let idx = self.coord_to_index(coord);
+		let current = self.cube_frame[idx];
+
+		self.cube_frame[idx] = Apa106Led {
+			red: current.red.saturating_add(colour.red),
+			green: current.green.saturating_add(colour.green),
+			blue: current.blue.saturating_add(colour.blue),
+		};
+	}
+
 	pub fn get_at_coord(&self, coord: Voxel) -> Apa106Led {
 		let idx = self.coord_to_index(coord);
 
@@ -105,40 +141,89 @@ impl<'a> Cube4<'a> {
 		}
 	}
 
+	/// Replace the whole frame. `frame` is in coordinate order (`x + 4 * y + 16 * z`), not the
+	/// order the LEDs are chained in.
+	pub fn set_frame(&mut self, frame: &[Apa106Led; 64]) {
+		for z in 0..4 {
+			for y in 0..4 {
+				for x in 0..4 {
+					let colour = frame[(x + y * 4 + z * 16) as usize];
+
+					self.set_at_coord(Voxel { x: x, y: y, z: z }, colour);
+				}
+			}
+		}
+	}
+
+	/// Copy of the whole frame in coordinate order, the same layout `set_frame` takes
+	pub fn get_frame(&self) -> [Apa106Led; 64] {
+		let mut frame = [Apa106Led { red: 0, green: 0, blue: 0 }; 64];
+
+		for z in 0..4 {
+			for y in 0..4 {
+				for x in 0..4 {
+					frame[(x + y * 4 + z * 16) as usize] = self.get_at_coord(Voxel { x: x, y: y, z: z });
+				}
+			}
+		}
+
+		frame
+	}
+
+	/// Scale every colour by `brightness` 16ths when it's flushed, saturating at full brightness. The
+	/// frame itself is left alone, so turning the brightness down and back up loses nothing.
+	pub fn set_brightness(&mut self, brightness: u8) {
+		self.brightness = brightness;
+	}
+
+	/// Colour correction applied when flushing, after the brightness
+	pub fn set_correction(&mut self, correction: Correction) {
+		self.correction = correction;
+	}
+
 	pub fn flush(&self) {
 		for led in self.cube_frame.into_iter() {
-			for byte in colour_to_raw(led).into_iter() {
+			let led = scale_brightness(led, self.brightness);
+
+			let led = match self.correction {
+				Correction::None => led,
+				Correction::Gamma => Apa106Led {
+					red: GAMMA_MAP[led.red as usize],
+					green: GAMMA_MAP[led.green as usize],
+					blue: GAMMA_MAP[led.blue as usize],
+				},
+			};
+
+			for byte in colour_to_raw(&led).into_iter() {
 				self.spi.write(*byte);
 			}
 		}
 	}
 }
 
+fn scale_brightness(input: &Apa106Led, brightness: u8) -> Apa106Led {
+	let channel = |value: u8| {
+		let scaled = value as u16 * brightness as u16 / NORMAL_BRIGHTNESS as u16;
+
+		if scaled > 255 { 255 } else { scaled as u8 }
+	};
+
+	Apa106Led {
+		red: channel(input.red),
+		green: channel(input.green),
+		blue: channel(input.blue),
+	}
+}
+
 fn bit_is_set(byte: u8, bit_index: u8) -> bool {
 	(byte & (1 << bit_index)) != 0
 }
 
-fn colour_to_raw(input: &Apa106Led) -> [u8; 24] {
+/// The 24 SPI bytes that send `input` to one LED, one byte per bit of colour
+pub fn colour_to_raw(input: &Apa106Led) -> [u8; 24] {
 	let mut bytes: [u8; 24] = [0; 24];
 
-	// Gamma correct colours
-	// let gamma_corrected_input = Apa106Led {
-	// 	red: GAMMA_MAP[input.red as usize],
-	// 	green: GAMMA_MAP[input.green as usize],
-	// 	blue: GAMMA_MAP[input.blue as usize],
-	// };
-
 	// SPI transmits MSB first
-	// Gamma correction
-	// for pos in 0..8 {
-	// 	bytes[7 - pos as usize] = if bit_is_set(gamma_corrected_input.red, pos as u8) { ON_BYTE } else { OFF_BYTE };
-
-	// 	bytes[8 + (7 - pos as usize)] = if bit_is_set(gamma_corrected_input.green, pos as u8) { ON_BYTE } else { OFF_BYTE };
-
-	// 	bytes[16 + (7 - pos as usize)] = if bit_is_set(gamma_corrected_input.blue, pos as u8) { ON_BYTE } else { OFF_BYTE };
-	// }
-
-	// No gamma correction
 	for pos in 0..8 {
 		bytes[7 - pos as usize] = if bit_is_set(input.red, pos as u8) { ON_BYTE } else { OFF_BYTE };
 
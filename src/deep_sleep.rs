@@ -0,0 +1,121 @@
+//! Deep sleep until a button is pressed or something arrives on the UART.
+//!
+//! The button and UART interrupts are turned on just long enough to wake the processor, with interrupts
+//! masked so no handlers run; a masked interrupt still ends `wfi` once it's pending. Everything is put
+//! back as it was before returning.
+//!
+//! In deep sleep the PLL is off and the processor runs from the 16MHz internal oscillator, so the UART
+//! is at the wrong baud rate and the bytes that wake it are garbled. They're thrown away. The watchdog's
+//! clock stops too, so it can't reset the cube while it sleeps.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+use serial;
+
+const SYSCTL_BASE: u32 = 0x400F_E000;
+const SYSCTL_DSLPCLKCFG: u32 = 0x144;
+const SYSCTL_DCGCGPIO: u32 = 0x808;
+const SYSCTL_DCGCUART: u32 = 0x818;
+
+/// Deep sleep clock source field in DSLPCLKCFG, and its value for the internal oscillator
+const DSLPCLKCFG_SOURCE: u32 = 0x7 << 4;
+const DSLPCLKCFG_PIOSC: u32 = 0x1 << 4;
+
+const GPIOB_BASE: u32 = 0x4000_5000;
+const GPIOF_BASE: u32 = 0x4002_5000;
+
+// Interrupt registers within a GPIO port. Sense, both edges and event all default to a falling edge.
+const GPIO_IM: u32 = 0x410;
+const GPIO_ICR: u32 = 0x41C;
+
+const UART0_BASE: u32 = 0x4000_C000;
+
+const UART_IM: u32 = 0x038;
+const UART_ICR: u32 = 0x044;
+
+/// Receive, receive timeout and framing error interrupts in UARTIM
+const UART_WAKE_INTERRUPTS: u32 = (1 << 4) | (1 << 6) | (1 << 7);
+
+const NVIC_EN0: *mut u32 = 0xE000_E100 as *mut u32;
+const NVIC_DIS0: *mut u32 = 0xE000_E180 as *mut u32;
+const NVIC_UNPEND0: *mut u32 = 0xE000_E280 as *mut u32;
+
+/// Interrupt numbers of GPIO ports B and F and UART0
+const NVIC_WAKE_INTERRUPTS: u32 = (1 << 1) | (1 << 30) | (1 << 5);
+
+const SCB_SCR: *mut u32 = 0xE000_ED10 as *mut u32;
+const SCR_SLEEPDEEP: u32 = 1 << 2;
+
+/// Clock gate bits for GPIO ports B and F, and UART0
+const GPIO_PORT_B: u32 = 1 << 1;
+const GPIO_PORT_F: u32 = 1 << 5;
+const UART0: u32 = 1 << 0;
+
+/// SW1 and SW2 on port F, and the encoder's push switch on port B. Turning the encoder doesn't wake it.
+const PORT_F_BUTTONS: u32 = (1 << 4) | (1 << 0);
+const PORT_B_BUTTONS: u32 = 1 << 2;
+
+fn register(base: u32, offset: u32) -> *mut u32 {
+	(base + offset) as *mut u32
+}
+
+unsafe fn set_bits(base: u32, offset: u32, bits: u32) {
+	volatile_store(register(base, offset), volatile_load(register(base, offset)) | bits);
+}
+
+unsafe fn clear_bits(base: u32, offset: u32, bits: u32) {
+	volatile_store(register(base, offset), volatile_load(register(base, offset)) & !bits);
+}
+
+/// Clear anything the wake up sources have flagged
+unsafe fn clear_wake_interrupts() {
+	volatile_store(register(GPIOF_BASE, GPIO_ICR), PORT_F_BUTTONS);
+	volatile_store(register(GPIOB_BASE, GPIO_ICR), PORT_B_BUTTONS);
+	volatile_store(register(UART0_BASE, UART_ICR), UART_WAKE_INTERRUPTS);
+	volatile_store(NVIC_UNPEND0, NVIC_WAKE_INTERRUPTS);
+}
+
+/// Sleep until SW1, SW2 or the encoder button is pressed, or a byte arrives on the UART. `controls` and
+/// the platformtree must have set up the pins and UART already.
+pub fn sleep_until_woken() {
+	unsafe {
+		// Keep the wake up sources clocked while asleep
+		set_bits(SYSCTL_BASE, SYSCTL_DCGCGPIO, GPIO_PORT_B | GPIO_PORT_F);
+		set_bits(SYSCTL_BASE, SYSCTL_DCGCUART, UART0);
+
+		let clock = volatile_load(register(SYSCTL_BASE, SYSCTL_DSLPCLKCFG));
+
+		volatile_store(register(SYSCTL_BASE, SYSCTL_DSLPCLKCFG), (clock & !DSLPCLKCFG_SOURCE) | DSLPCLKCFG_PIOSC);
+
+		asm!("cpsid i" :::: "volatile");
+
+		// Whatever's in the receive FIFO now would wake it straight away
+		while serial::read_byte().is_some() {}
+
+		clear_wake_interrupts();
+
+		set_bits(GPIOF_BASE, GPIO_IM, PORT_F_BUTTONS);
+		set_bits(GPIOB_BASE, GPIO_IM, PORT_B_BUTTONS);
+		set_bits(UART0_BASE, UART_IM, UART_WAKE_INTERRUPTS);
+		volatile_store(NVIC_EN0, NVIC_WAKE_INTERRUPTS);
+
+		volatile_store(SCB_SCR, volatile_load(SCB_SCR) | SCR_SLEEPDEEP);
+
+		asm!("wfi" :::: "volatile");
+
+		volatile_store(SCB_SCR, volatile_load(SCB_SCR) & !SCR_SLEEPDEEP);
+
+		volatile_store(NVIC_DIS0, NVIC_WAKE_INTERRUPTS);
+		clear_bits(GPIOF_BASE, GPIO_IM, PORT_F_BUTTONS);
+		clear_bits(GPIOB_BASE, GPIO_IM, PORT_B_BUTTONS);
+		clear_bits(UART0_BASE, UART_IM, UART_WAKE_INTERRUPTS);
+
+		// Throw away the garbled bytes that woke it
+		while serial::read_byte().is_some() {}
+		serial::take_errors();
+
+		clear_wake_interrupts();
+
+		asm!("cpsie i" :::: "volatile");
+	}
+}
@@ -0,0 +1,193 @@
+//! 3D drawing primitives. Everything is clipped to the cube, so shapes can be partly (or entirely)
+//! outside it without any checks in the calling pattern.
+
+use apa106led::Apa106Led;
+use cube::{ Cube4, Voxel, CUBE_SIZE };
+
+/// A position that may lie outside the cube
+#[derive(Copy, Clone)]
+pub struct Point {
+	pub x: i8,
+	pub y: i8,
+	pub z: i8,
+}
+
+fn in_cube(value: i16) -> bool {
+	value >= 0 && value < CUBE_SIZE as i16
+}
+
+fn plot_xyz(cube: &mut Cube4, x: i16, y: i16, z: i16, colour: Apa106Led) {
+	if in_cube(x) && in_cube(y) && in_cube(z) {
+		cube.set_at_coord(Voxel { x: x as u8, y: y as u8, z: z as u8 }, colour);
+	}
+}
+
+/// Set a single voxel, doing nothing if it's outside the cube
+pub fn plot(cube: &mut Cube4, point: Point, colour: Apa106Led) {
+	plot_xyz(cube, point.x as i16, point.y as i16, point.z as i16, colour);
+}
+
+fn abs(value: i16) -> i16 {
+	if value < 0 { -value } else { value }
+}
+
+fn signum(value: i16) -> i16 {
+	if value < 0 { -1 } else if value > 0 { 1 } else { 0 }
+}
+
+/// 3D Bresenham line between two points, inclusive of both ends
+pub fn line(cube: &mut Cube4, from: Point, to: Point, colour: Apa106Led) {
+	// Work in i16 so lines between far away points can't overflow
+	let (mut x, mut y, mut z) = (from.x as i16, from.y as i16, from.z as i16);
+	let (dx, dy, dz) = (to.x as i16 - x, to.y as i16 - y, to.z as i16 - z);
+	let (sx, sy, sz) = (signum(dx), signum(dy), signum(dz));
+	let (dx, dy, dz) = (abs(dx), abs(dy), abs(dz));
+
+	plot_xyz(cube, x, y, z, colour);
+
+	if dx >= dy && dx >= dz {
+		let mut error_y = 2 * dy - dx;
+		let mut error_z = 2 * dz - dx;
+
+		for _ in 0..dx {
+			x += sx;
+
+			if error_y >= 0 { y += sy; error_y -= 2 * dx; }
+			if error_z >= 0 { z += sz; error_z -= 2 * dx; }
+
+			error_y += 2 * dy;
+			error_z += 2 * dz;
+
+			plot_xyz(cube, x, y, z, colour);
+		}
+	} else if dy >= dx && dy >= dz {
+		let mut error_x = 2 * dx - dy;
+		let mut error_z = 2 * dz - dy;
+
+		for _ in 0..dy {
+			y += sy;
+
+			if error_x >= 0 { x += sx; error_x -= 2 * dy; }
+			if error_z >= 0 { z += sz; error_z -= 2 * dy; }
+
+			error_x += 2 * dx;
+			error_z += 2 * dz;
+
+			plot_xyz(cube, x, y, z, colour);
+		}
+	} else {
+		let mut error_x = 2 * dx - dz;
+		let mut error_y = 2 * dy - dz;
+
+		for _ in 0..dz {
+			z += sz;
+
+			if error_x >= 0 { x += sx; error_x -= 2 * dz; }
+			if error_y >= 0 { y += sy; error_y -= 2 * dz; }
+
+			error_x += 2 * dx;
+			error_y += 2 * dy;
+
+			plot_xyz(cube, x, y, z, colour);
+		}
+	}
+}
+
+fn min(a: i8, b: i8) -> i8 {
+	if a < b { a } else { b }
+}
+
+fn max(a: i8, b: i8) -> i8 {
+	if a > b { a } else { b }
+}
+
+/// Solid box with opposite corners `a` and `b`
+pub fn filled_box(cube: &mut Cube4, a: Point, b: Point, colour: Apa106Led) {
+	for z in max(min(a.z, b.z), 0)..min(max(a.z, b.z), CUBE_SIZE as i8 - 1) + 1 {
+		for y in max(min(a.y, b.y), 0)..min(max(a.y, b.y), CUBE_SIZE as i8 - 1) + 1 {
+			for x in max(min(a.x, b.x), 0)..min(max(a.x, b.x), CUBE_SIZE as i8 - 1) + 1 {
+				plot(cube, Point { x: x, y: y, z: z }, colour);
+			}
+		}
+	}
+}
+
+/// The 12 edges of a box with opposite corners `a` and `b`
+pub fn wire_box(cube: &mut Cube4, a: Point, b: Point, colour: Apa106Led) {
+	let corner = |x: bool, y: bool, z: bool| Point {
+		x: if x { b.x } else { a.x },
+		y: if y { b.y } else { a.y },
+		z: if z { b.z } else { a.z },
+	};
+
+	for &(y, z) in [ (false, false), (true, false), (false, true), (true, true) ].iter() {
+		line(cube, corner(false, y, z), corner(true, y, z), colour);
+		line(cube, corner(y, false, z), corner(y, true, z), colour);
+		line(cube, corner(y, z, false), corner(y, z, true), colour);
+	}
+}
+
+/// Squared distance from the centre of voxel `(x, y, z)` to a point
+fn distance_sq(x: i8, y: i8, z: i8, centre: (f32, f32, f32)) -> f32 {
+	let (dx, dy, dz) = (x as f32 - centre.0, y as f32 - centre.1, z as f32 - centre.2);
+
+	dx * dx + dy * dy + dz * dz
+}
+
+/// Solid sphere. `centre` is in voxels and doesn't have to be a whole number, e.g. `(1.5, 1.5, 1.5)`
+/// is the middle of the cube.
+pub fn sphere(cube: &mut Cube4, centre: (f32, f32, f32), radius: f32, colour: Apa106Led) {
+	for z in 0..CUBE_SIZE as i8 {
+		for y in 0..CUBE_SIZE as i8 {
+			for x in 0..CUBE_SIZE as i8 {
+				if distance_sq(x, y, z, centre) <= radius * radius {
+					plot(cube, Point { x: x, y: y, z: z }, colour);
+				}
+			}
+		}
+	}
+}
+
+/// Hollow sphere one voxel thick: voxels whose centres are within half a voxel of the surface
+pub fn shell(cube: &mut Cube4, centre: (f32, f32, f32), radius: f32, colour: Apa106Led) {
+	let inner = if radius > 0.5 { radius - 0.5 } else { 0.0 };
+	let outer = radius + 0.5;
+
+	for z in 0..CUBE_SIZE as i8 {
+		for y in 0..CUBE_SIZE as i8 {
+			for x in 0..CUBE_SIZE as i8 {
+				let distance = distance_sq(x, y, z, centre);
+
+				if distance >= inner * inner && distance <= outer * outer {
+					plot(cube, Point { x: x, y: y, z: z }, colour);
+				}
+			}
+		}
+	}
+}
+
+/// Plane through `point` with the given `normal`, which doesn't need to be normalised. Voxels whose
+/// centres lie within `thickness / 2` of the plane are set; a thickness of 1.0 gives a plane one voxel thick.
+pub fn plane(cube: &mut Cube4, point: (f32, f32, f32), normal: (f32, f32, f32), thickness: f32, colour: Apa106Led) {
+	let (nx, ny, nz) = normal;
+	let length_sq = nx * nx + ny * ny + nz * nz;
+
+	if length_sq == 0.0 {
+		return;
+	}
+
+	// Compare squared distances so there's no need for a square root
+	let limit = thickness * thickness / 4.0 * length_sq;
+
+	for z in 0..CUBE_SIZE as i8 {
+		for y in 0..CUBE_SIZE as i8 {
+			for x in 0..CUBE_SIZE as i8 {
+				let distance = (x as f32 - point.0) * nx + (y as f32 - point.1) * ny + (z as f32 - point.2) * nz;
+
+				if distance * distance <= limit {
+					plot(cube, Point { x: x, y: y, z: z }, colour);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,107 @@
+//! The TM4C123's 2KB of on-chip EEPROM, as 32 blocks of 16 words. Zinc doesn't have a driver for it,
+//! so like `adc` this pokes the registers directly.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+use settings::Eeprom;
+
+const SYSCTL_SREEPROM: *mut u32 = 0x400F_E558 as *mut u32;
+const SYSCTL_RCGCEEPROM: *mut u32 = 0x400F_E658 as *mut u32;
+
+const EEPROM_BASE: u32 = 0x400A_F000;
+
+const EE_BLOCK: *mut u32 = (EEPROM_BASE + 0x004) as *mut u32;
+const EE_OFFSET: *mut u32 = (EEPROM_BASE + 0x008) as *mut u32;
+const EE_RDWR: *mut u32 = (EEPROM_BASE + 0x010) as *mut u32;
+const EE_DONE: *const u32 = (EEPROM_BASE + 0x018) as *const u32;
+const EE_SUPP: *const u32 = (EEPROM_BASE + 0x01C) as *const u32;
+
+/// Set in EEDONE while a write or erase is in progress
+const DONE_WORKING: u32 = 1 << 0;
+
+/// Set in EESUPP if a program or erase from before the last reset needs retrying
+const SUPP_RETRY: u32 = (1 << 3) | (1 << 2);
+
+const WORDS_PER_BLOCK: u32 = 16;
+
+fn wait_until_done() {
+	unsafe {
+		while volatile_load(EE_DONE) & DONE_WORKING != 0 {}
+	}
+}
+
+/// Spin for at least the six system clocks the EEPROM needs after its clock or reset changes
+fn settle() {
+	for _ in 0..6 {
+		unsafe {
+			volatile_load(SYSCTL_RCGCEEPROM);
+		}
+	}
+}
+
+pub struct TivaEeprom {
+	/// False if the EEPROM didn't come up properly. Reads then return what erased EEPROM does and writes
+	/// do nothing, so settings fall back to their defaults.
+	usable: bool,
+}
+
+impl TivaEeprom {
+	/// Turn on the EEPROM and wait for it to finish anything left over from before the last reset
+	pub fn new() -> TivaEeprom {
+		unsafe {
+			volatile_store(SYSCTL_RCGCEEPROM, 1);
+			settle();
+			wait_until_done();
+
+			if volatile_load(EE_SUPP) & SUPP_RETRY != 0 {
+				return TivaEeprom { usable: false };
+			}
+
+			// The datasheet asks for a reset of the module once it's settled
+			volatile_store(SYSCTL_SREEPROM, 1);
+			settle();
+			volatile_store(SYSCTL_SREEPROM, 0);
+			settle();
+			wait_until_done();
+
+			TivaEeprom { usable: volatile_load(EE_SUPP) & SUPP_RETRY == 0 }
+		}
+	}
+
+	pub fn is_usable(&self) -> bool {
+		self.usable
+	}
+
+	fn select(&self, address: u32) {
+		unsafe {
+			volatile_store(EE_BLOCK, address / WORDS_PER_BLOCK);
+			volatile_store(EE_OFFSET, address % WORDS_PER_BLOCK);
+		}
+	}
+}
+
+impl Eeprom for TivaEeprom {
+	fn read(&self, address: u32) -> u32 {
+		if !self.usable {
+			return 0xffff_ffff;
+		}
+
+		self.select(address);
+
+		unsafe { volatile_load(EE_RDWR) }
+	}
+
+	fn write(&mut self, address: u32, value: u32) {
+		if !self.usable {
+			return;
+		}
+
+		self.select(address);
+
+		unsafe {
+			volatile_store(EE_RDWR, value);
+		}
+
+		wait_until_done();
+	}
+}
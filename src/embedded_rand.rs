@@ -4,7 +4,7 @@ static mut seed: u32 = 348723;
 
 pub fn rand() -> u32 {
 	unsafe {
-		seed = (1103515245 * seed + 12345) % 429496729;
+		seed = 1103515245u32.wrapping_mul(seed).wrapping_add(12345) % 429496729;
 
 		seed
 	}
@@ -0,0 +1,380 @@
+//! Fixed point numbers, for maths that would otherwise need floats. The Cortex-M4's FPU only does
+//! single precision adds and multiplies; anything like `logf` or `powf` needs a maths library the
+//! firmware doesn't have.
+//!
+//! * `Fixed8` is Q8.8: 8 integer bits (with the sign) and 8 fraction bits in an `i16`. Plenty for
+//!   colour multipliers, and cheap.
+//! * `Fixed16` is Q16.16 in an `i32`, from -32768 to 32767 in steps of about 0.000015. It also has
+//!   square roots, sines and cosines and logs to within a step or two, and exponents and powers to
+//!   within a step or a hundred-millionth of the result, whichever is more.
+//!
+//! Results that don't fit saturate rather than wrapping, including division by zero.
+
+use core::ops::{ Add, Div, Mul, Neg, Sub };
+
+use tables::QUARTER_SINE_TABLE;
+
+/// 1.0 in each format's raw value
+pub const FIXED8_ONE: i16 = 1 << 8;
+pub const FIXED16_ONE: i32 = 1 << 16;
+
+/// Angles for `sin()` and `cos()` in 65536ths of a turn, like `procedural::sine()`'s 256ths
+pub const QUARTER_TURN: u16 = 1 << 14;
+
+/// log2(e) in Q2.30 and ln(2) in Q0.32, more precise than Q16.16 could hold them
+const LOG2_E: i64 = 1_549_082_005;
+const LN_2: i64 = 2_977_044_472;
+
+/// 2^(2^-n) for n from 1 to 16, in Q2.30. `exp2()` multiplies together the ones for each set bit of
+/// the fraction.
+const EXP2_FRACTION_BITS: [u64; 16] = [
+	1_518_500_250, 1_276_901_417, 1_170_923_762, 1_121_280_436, 1_097_253_708, 1_085_434_106,
+	1_079_572_136, 1_076_653_033, 1_075_196_443, 1_074_468_888, 1_074_105_294, 1_073_923_544,
+	1_073_832_680, 1_073_787_251, 1_073_764_537, 1_073_753_181,
+];
+
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+pub struct Fixed8(pub i16);
+
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+pub struct Fixed16(pub i32);
+
+fn saturate_i16(value: i32) -> i16 {
+	if value > 0x7fff {
+		0x7fff
+	} else if value < -0x8000 {
+		-0x8000
+	} else {
+		value as i16
+	}
+}
+
+fn saturate_i32(value: i64) -> i32 {
+	if value > 0x7fff_ffff {
+		0x7fff_ffff
+	} else if value < -0x8000_0000 {
+		-0x8000_0000
+	} else {
+		value as i32
+	}
+}
+
+/// `numerator / denominator` rounded to the nearest, or as far as possible the right way if the
+/// denominator is zero
+fn divide_rounded(numerator: i64, denominator: i64) -> i64 {
+	if denominator == 0 {
+		return if numerator < 0 { -0x8000_0000_0000 } else if numerator > 0 { 0x7fff_ffff_ffff } else { 0 };
+	}
+
+	// Division rounds towards zero, so push the numerator half a step further away from it
+	let half = denominator.abs() / 2;
+
+	(numerator + if numerator < 0 { -half } else { half }) / denominator
+}
+
+impl Fixed8 {
+	pub fn from_int(value: i16) -> Fixed8 {
+		Fixed8(saturate_i16((value as i32) << 8))
+	}
+
+	/// `numerator / denominator`, e.g. `Fixed8::from_ratio(MAX_BRIGHTNESS as i32, 255)`
+	pub fn from_ratio(numerator: i32, denominator: i32) -> Fixed8 {
+		Fixed8(saturate_i16(saturate_i32(divide_rounded((numerator as i64) << 8, denominator as i64))))
+	}
+
+	/// Round down to a whole number
+	pub fn floor(self) -> i16 {
+		self.0 >> 8
+	}
+
+	/// Round to the nearest whole number
+	pub fn round(self) -> i16 {
+		((self.0 as i32 + 0x80) >> 8) as i16
+	}
+
+	/// `from` when `t` is 0, `to` when it's 1
+	pub fn lerp(from: Fixed8, to: Fixed8, t: Fixed8) -> Fixed8 {
+		from + (to - from) * t
+	}
+
+	/// Multiply a colour channel, rounding and keeping the result between 0 and 255
+	pub fn scale(self, value: u8) -> u8 {
+		let scaled = (value as i32 * self.0 as i32 + 0x80) >> 8;
+
+		if scaled < 0 { 0 } else if scaled > 255 { 255 } else { scaled as u8 }
+	}
+
+	pub fn to_fixed16(self) -> Fixed16 {
+		Fixed16((self.0 as i32) << 8)
+	}
+}
+
+impl Add for Fixed8 {
+	type Output = Fixed8;
+
+	fn add(self, other: Fixed8) -> Fixed8 {
+		Fixed8(self.0.saturating_add(other.0))
+	}
+}
+
+impl Sub for Fixed8 {
+	type Output = Fixed8;
+
+	fn sub(self, other: Fixed8) -> Fixed8 {
+		Fixed8(self.0.saturating_sub(other.0))
+	}
+}
+
+impl Mul for Fixed8 {
+	type Output = Fixed8;
+
+	fn mul(self, other: Fixed8) -> Fixed8 {
+		Fixed8(saturate_i16((self.0 as i32 * other.0 as i32 + 0x80) >> 8))
+	}
+}
+
+impl Div for Fixed8 {
+	type Output = Fixed8;
+
+	fn div(self, other: Fixed8) -> Fixed8 {
+		Fixed8::from_ratio(self.0 as i32, other.0 as i32)
+	}
+}
+
+impl Neg for Fixed8 {
+	type Output = Fixed8;
+
+	fn neg(self) -> Fixed8 {
+		Fixed8(saturate_i16(-(self.0 as i32)))
+	}
+}
+
+impl Fixed16 {
+	pub fn from_int(value: i32) -> Fixed16 {
+		Fixed16(saturate_i32((value as i64) << 16))
+	}
+
+	/// `numerator / denominator`, e.g. `Fixed16::from_ratio(kelvin as i32, 100)`
+	pub fn from_ratio(numerator: i32, denominator: i32) -> Fixed16 {
+		Fixed16(saturate_i32(divide_rounded((numerator as i64) << 16, denominator as i64)))
+	}
+
+	/// Round down to a whole number
+	pub fn floor(self) -> i32 {
+		self.0 >> 16
+	}
+
+	/// Round to the nearest whole number
+	pub fn round(self) -> i32 {
+		((self.0 as i64 + 0x8000) >> 16) as i32
+	}
+
+	/// `from` when `t` is 0, `to` when it's 1
+	pub fn lerp(from: Fixed16, to: Fixed16, t: Fixed16) -> Fixed16 {
+		from + (to - from) * t
+	}
+
+	pub fn to_fixed8(self) -> Fixed8 {
+		Fixed8(saturate_i16((self.0 + 0x80) >> 8))
+	}
+
+	/// Square root, or 0 for negative numbers
+	pub fn sqrt(self) -> Fixed16 {
+		if self.0 <= 0 {
+			return Fixed16(0);
+		}
+
+		// The integer square root of the value shifted up another 16 bits has 16 fraction bits
+		let value = (self.0 as u64) << 16;
+		let mut root: u64 = 0;
+		let mut bit: u64 = 1 << 46;
+
+		while bit > value {
+			bit >>= 2;
+		}
+
+		let mut remainder = value;
+
+		while bit != 0 {
+			if remainder >= root + bit {
+				remainder -= root + bit;
+				root = (root >> 1) + bit;
+			} else {
+				root >>= 1;
+			}
+
+			bit >>= 2;
+		}
+
+		// Round to the nearest
+		if remainder > root {
+			root += 1;
+		}
+
+		Fixed16(root as i32)
+	}
+
+	/// Base 2 logarithm. Zero and negative numbers give the most negative value there is.
+	pub fn log2(self) -> Fixed16 {
+		if self.0 <= 0 {
+			return Fixed16(-0x8000_0000);
+		}
+
+		Fixed16((((self.log2_q30() >> 13) + 1) >> 1) as i32)
+	}
+
+	/// Base 2 logarithm of a positive number with 30 fraction bits, for `log2()` to round and `pow()` to
+	/// use as it is
+	fn log2_q30(self) -> i64 {
+		let top_bit = 31 - self.0.leading_zeros() as i64;
+
+		// Scale into [1, 2) with 31 fraction bits
+		let mut mantissa = (self.0 as u64) << (31 - top_bit);
+		let mut result = (top_bit - 16) << 30;
+
+		// Squaring the mantissa doubles its log, so each time it reaches 2 there's another 1 bit
+		for bit in 0..30 {
+			mantissa = (mantissa * mantissa) >> 31;
+
+			if mantissa >= 2 << 31 {
+				mantissa >>= 1;
+				result |= 1 << (29 - bit);
+			}
+		}
+
+		result
+	}
+
+	/// Natural logarithm. Zero and negative numbers give the most negative value there is.
+	pub fn ln(self) -> Fixed16 {
+		if self.0 <= 0 {
+			return Fixed16(-0x8000_0000);
+		}
+
+		Fixed16(((self.log2().0 as i64 * LN_2 + (1 << 31)) >> 32) as i32)
+	}
+
+	/// 2 to the power of this
+	pub fn exp2(self) -> Fixed16 {
+		exp2_q30((self.0 as i64) << 14)
+	}
+
+	/// e to the power of this
+	pub fn exp(self) -> Fixed16 {
+		exp2_q30((self.0 as i64 * LOG2_E + (1 << 15)) >> 16)
+	}
+
+	/// This to the power of `exponent`. Only defined for positive numbers; anything else gives 0.
+	pub fn pow(self, exponent: Fixed16) -> Fixed16 {
+		if self.0 <= 0 {
+			return Fixed16(0);
+		}
+
+		// In two halves, as the whole product can be too big for an i64
+		let log = self.log2_q30();
+		let whole = (exponent.0 >> 16) as i64;
+		let fraction = (exponent.0 & 0xffff) as i64;
+
+		exp2_q30(log * whole + ((log * fraction + 0x8000) >> 16))
+	}
+}
+
+/// 2 to the power of `exponent`, which has 30 fraction bits. Rounding the exponents of `exp()` and
+/// `pow()` to Q16.16 first would cost more than a step once the result is more than a few hundred.
+fn exp2_q30(exponent: i64) -> Fixed16 {
+	let whole = exponent >> 30;
+	let fraction = exponent & 0x3fff_ffff;
+
+	if whole >= 15 {
+		return Fixed16(0x7fff_ffff);
+	} else if whole < -17 {
+		return Fixed16(0);
+	}
+
+	let mut power: u64 = 1 << 30;
+
+	for bit in 0..16 {
+		if fraction & (1 << (29 - bit)) != 0 {
+			power = (power * EXP2_FRACTION_BITS[bit]) >> 30;
+		}
+	}
+
+	// What's left is under 2^-16, where 2^x is 1 + x ln(2) to well within the precision here
+	let rest = ((fraction & 0x3fff) as u64 * LN_2 as u64) >> 30;
+
+	power += (power * rest + (1 << 31)) >> 32;
+
+	// From Q2.30 to Q16.16, times 2^whole, rounded
+	let shift = 14 - whole;
+	let half = if shift > 0 { 1 << (shift - 1) } else { 0 };
+
+	Fixed16(saturate_i32(((power + half) >> shift) as i64))
+}
+
+impl Add for Fixed16 {
+	type Output = Fixed16;
+
+	fn add(self, other: Fixed16) -> Fixed16 {
+		Fixed16(self.0.saturating_add(other.0))
+	}
+}
+
+impl Sub for Fixed16 {
+	type Output = Fixed16;
+
+	fn sub(self, other: Fixed16) -> Fixed16 {
+		Fixed16(self.0.saturating_sub(other.0))
+	}
+}
+
+impl Mul for Fixed16 {
+	type Output = Fixed16;
+
+	fn mul(self, other: Fixed16) -> Fixed16 {
+		Fixed16(saturate_i32((self.0 as i64 * other.0 as i64 + 0x8000) >> 16))
+	}
+}
+
+impl Div for Fixed16 {
+	type Output = Fixed16;
+
+	fn div(self, other: Fixed16) -> Fixed16 {
+		Fixed16::from_ratio(self.0, other.0)
+	}
+}
+
+impl Neg for Fixed16 {
+	type Output = Fixed16;
+
+	fn neg(self) -> Fixed16 {
+		Fixed16(saturate_i32(-(self.0 as i64)))
+	}
+}
+
+/// Sine of `angle` in 65536ths of a turn, from the quarter wave table with linear interpolation
+pub fn sin(angle: u16) -> Fixed16 {
+	let within = angle & (QUARTER_TURN - 1);
+
+	// The second and fourth quarters run the table backwards
+	let position = (if angle & QUARTER_TURN != 0 { QUARTER_TURN - within } else { within }) as usize;
+
+	let index = position >> 7;
+	let fraction = (position & 0x7f) as i32;
+
+	let value = if index == 128 {
+		QUARTER_SINE_TABLE[128]
+	} else {
+		let from = QUARTER_SINE_TABLE[index];
+		let to = QUARTER_SINE_TABLE[index + 1];
+
+		from + (((to - from) * fraction + 0x40) >> 7)
+	};
+
+	// The second half is negative
+	if angle & (QUARTER_TURN << 1) != 0 { Fixed16(-value) } else { Fixed16(value) }
+}
+
+/// Cosine of `angle` in 65536ths of a turn
+pub fn cos(angle: u16) -> Fixed16 {
+	sin(angle.wrapping_add(QUARTER_TURN))
+}
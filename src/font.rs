@@ -0,0 +1,204 @@
+//! Bitmap fonts for drawing text on the faces of the cube. Each glyph is a `u16` bitmap, one row after
+//! another from the top, with the most significant bit at the top left. Glyphs cover ASCII space to
+//! `_`; lower case letters are drawn as capitals.
+
+pub struct Font {
+	pub width: u8,
+	pub height: u8,
+	/// Glyphs for ASCII `FIRST_CHAR` onwards
+	pub glyphs: &'static [u16],
+}
+
+const FIRST_CHAR: u8 = b' ';
+
+/// Gap between characters, in columns
+pub const LETTER_SPACING: u8 = 1;
+
+impl Font {
+	/// Bitmap for a character. Anything without a glyph comes out as `?`.
+	pub fn glyph(&self, character: u8) -> u16 {
+		let character = match character {
+			b'a'...b'z' => character - 32,
+			_ => character,
+		};
+
+		if character < FIRST_CHAR || (character - FIRST_CHAR) as usize >= self.glyphs.len() {
+			return self.glyphs[(b'?' - FIRST_CHAR) as usize];
+		}
+
+		self.glyphs[(character - FIRST_CHAR) as usize]
+	}
+
+	/// One column of a character as a bitmask of rows, with bit 0 the top row
+	pub fn column(&self, character: u8, x: u8) -> u8 {
+		let glyph = self.glyph(character);
+		let mut column = 0;
+
+		for y in 0..self.height {
+			let bit = (self.height - 1 - y) * self.width + (self.width - 1 - x);
+
+			if glyph & (1 << bit) != 0 {
+				column |= 1 << y;
+			}
+		}
+
+		column
+	}
+
+	/// Width of a character ignoring blank columns on its right, so text can be proportionally spaced.
+	/// Spaces are half the font's width.
+	pub fn advance(&self, character: u8) -> u8 {
+		for x in (0..self.width).rev() {
+			if self.column(character, x) != 0 {
+				return x + 1;
+			}
+		}
+
+		self.width / 2
+	}
+}
+
+/// 4x4 font that fits a whole face of the cube
+pub const FONT_4X4: Font = Font {
+	width: 4,
+	height: 4,
+	glyphs: &[
+	0x0000, //  
+	0x4404, // !
+	0xaa00, // "
+	0x5f5f, // #
+	0x7a5e, // $
+	0x9249, // %
+	0x4a5b, // &
+	0x4400, // '
+	0x2442, // (
+	0x4224, // )
+	0xa4a0, // *
+	0x04e4, // +
+	0x0048, // ,
+	0x0e00, // -
+	0x0008, // .
+	0x1248, // /
+	0x6bd6, // 0
+	0x4c4e, // 1
+	0xc24e, // 2
+	0xe62e, // 3
+	0xaae2, // 4
+	0xec2c, // 5
+	0x8eae, // 6
+	0xe244, // 7
+	0xeeae, // 8
+	0xeae2, // 9
+	0x0808, // :
+	0x0408, // ;
+	0x2420, // <
+	0xe0e0, // =
+	0x8480, // >
+	0xc244, // ?
+	0x6b86, // @
+	0x69f9, // A
+	0xce9e, // B
+	0x7887, // C
+	0xe99e, // D
+	0xfe8f, // E
+	0xf8e8, // F
+	0x78b7, // G
+	0x9f99, // H
+	0xe44e, // I
+	0x3196, // J
+	0xaca9, // K
+	0x888f, // L
+	0x9ff9, // M
+	0x9db9, // N
+	0x6996, // O
+	0xe9e8, // P
+	0x69a5, // Q
+	0xe9e9, // R
+	0x7c3e, // S
+	0xe444, // T
+	0x9996, // U
+	0x9966, // V
+	0x99f6, // W
+	0x9669, // X
+	0xaa44, // Y
+	0xf24f, // Z
+	0xc88c, // [
+	0x8421, // \
+	0xc44c, // ]
+	0x4a00, // ^
+	0x000f, // _
+	],
+};
+
+/// 3x5 font. It's easier to read than `FONT_4X4`, but it's a row taller than a 4x4x4 cube, so drawing
+/// it on a face cuts off the bottom row.
+pub const FONT_3X5: Font = Font {
+	width: 3,
+	height: 5,
+	glyphs: &[
+	0x0000, //  
+	0x2482, // !
+	0x5a00, // "
+	0x5f7d, // #
+	0x388e, // $
+	0x52a5, // %
+	0x2aab, // &
+	0x2400, // '
+	0x1491, // (
+	0x4494, // )
+	0x0aa8, // *
+	0x05d0, // +
+	0x0014, // ,
+	0x01c0, // -
+	0x0004, // .
+	0x12a4, // /
+	0x7b6f, // 0
+	0x2c97, // 1
+	0x73e7, // 2
+	0x73cf, // 3
+	0x5bc9, // 4
+	0x79cf, // 5
+	0x79ef, // 6
+	0x7249, // 7
+	0x7bef, // 8
+	0x7bcf, // 9
+	0x0820, // :
+	0x0414, // ;
+	0x1511, // <
+	0x0e38, // =
+	0x4454, // >
+	0x72c2, // ?
+	0x2be3, // @
+	0x2bed, // A
+	0x6bae, // B
+	0x3923, // C
+	0x6b6e, // D
+	0x79a7, // E
+	0x79a4, // F
+	0x396b, // G
+	0x5bed, // H
+	0x7497, // I
+	0x126a, // J
+	0x5bad, // K
+	0x4927, // L
+	0x5fed, // M
+	0x6b6d, // N
+	0x2b6a, // O
+	0x6ba4, // P
+	0x2b73, // Q
+	0x6bad, // R
+	0x388e, // S
+	0x7492, // T
+	0x5b6f, // U
+	0x5b6a, // V
+	0x5bfd, // W
+	0x5aad, // X
+	0x5a92, // Y
+	0x72a7, // Z
+	0x6926, // [
+	0x4889, // \
+	0x324b, // ]
+	0x2a00, // ^
+	0x0007, // _
+	],
+};
@@ -0,0 +1,148 @@
+//! Frame rate governor: spaces frames evenly however long each took to draw and flush, and keeps
+//! statistics on how long that was.
+//!
+//! Patterns ask for each frame to be held for a while. Counting that hold from when the frame has been
+//! flushed would make the time between frames the hold plus drawing plus flushing, which varies from
+//! pattern to pattern and frame to frame. Instead it's counted from when the pattern got control back
+//! after the previous frame, so drawing and flushing come out of it. A frame that isn't ready by then is
+//! shown straight away and counted as dropped.
+//!
+//! Like `power`, nothing here touches the hardware; the scheduler passes in timer counts.
+
+/// Timings since the statistics were last taken, all in microseconds
+#[derive(Copy, Clone, PartialEq)]
+pub struct Stats {
+	pub frames: u32,
+	/// Frames that were ready after they should have been shown
+	pub dropped: u32,
+	/// Frames whose drawing time is known, which isn't the first after a `reset()`
+	pub rendered: u32,
+	pub render_total_us: u64,
+	pub render_max_us: u32,
+	pub flush_total_us: u64,
+	pub flush_max_us: u32,
+}
+
+impl Stats {
+	pub fn new() -> Stats {
+		Stats {
+			frames: 0,
+			dropped: 0,
+			rendered: 0,
+			render_total_us: 0,
+			render_max_us: 0,
+			flush_total_us: 0,
+			flush_max_us: 0,
+		}
+	}
+
+	pub fn render_average_us(&self) -> u32 {
+		if self.rendered == 0 { 0 } else { (self.render_total_us / self.rendered as u64) as u32 }
+	}
+
+	pub fn flush_average_us(&self) -> u32 {
+		if self.frames == 0 { 0 } else { (self.flush_total_us / self.frames as u64) as u32 }
+	}
+}
+
+/// Call `start_flush()`, `end_flush()` and `end_hold()` in that order for each frame
+pub struct FrameTiming {
+	/// When the pattern got control back after the last frame, and so started drawing this one
+	drawing_since: Option<u32>,
+	flush_started: u32,
+	/// What the pattern asked the current frame to be held for
+	hold_us: u32,
+	/// Time between the last two frames being handed back
+	delta_us: u32,
+	stats: Stats,
+}
+
+impl FrameTiming {
+	pub fn new() -> FrameTiming {
+		FrameTiming {
+			drawing_since: None,
+			flush_started: 0,
+			hold_us: 0,
+			delta_us: 0,
+			stats: Stats::new(),
+		}
+	}
+
+	/// Call when a pattern hands over a frame, before flushing it
+	pub fn start_flush(&mut self, now_us: u32) {
+		if let Some(since) = self.drawing_since {
+			let render_us = now_us.wrapping_sub(since);
+
+			self.stats.rendered += 1;
+			self.stats.render_total_us += render_us as u64;
+
+			if render_us > self.stats.render_max_us {
+				self.stats.render_max_us = render_us;
+			}
+		}
+
+		self.flush_started = now_us;
+	}
+
+	/// Call once the frame's been flushed, with what the pattern asked it to be held for. Returns how
+	/// much of that is left to wait, which is none if the frame is late.
+	pub fn end_flush(&mut self, now_us: u32, hold_us: u32) -> u32 {
+		let flush_us = now_us.wrapping_sub(self.flush_started);
+
+		self.stats.frames += 1;
+		self.stats.flush_total_us += flush_us as u64;
+
+		if flush_us > self.stats.flush_max_us {
+			self.stats.flush_max_us = flush_us;
+		}
+
+		self.hold_us = hold_us;
+
+		// Without the previous frame to count from, hold it as long as asked
+		let since = match self.drawing_since {
+			Some(since) => since,
+			None => now_us,
+		};
+
+		let remaining = since.wrapping_add(hold_us).wrapping_sub(now_us) as i32;
+
+		if remaining < 0 {
+			self.stats.dropped += 1;
+
+			0
+		} else {
+			remaining as u32
+		}
+	}
+
+	/// Call when handing control back to the pattern
+	pub fn end_hold(&mut self, now_us: u32) {
+		self.delta_us = match self.drawing_since {
+			Some(since) => now_us.wrapping_sub(since),
+			None => self.hold_us,
+		};
+
+		self.drawing_since = Some(now_us);
+	}
+
+	/// Forget when the last frame was shown, after the pattern has been held up by something else
+	/// (streamed frames, sleep, a new pattern starting) that shouldn't count against the next frame
+	pub fn reset(&mut self) {
+		self.drawing_since = None;
+	}
+
+	/// Time between the last two frames being handed back to the pattern, or what the last frame asked
+	/// to be held for after a `reset()`
+	pub fn delta_us(&self) -> u32 {
+		self.delta_us
+	}
+
+	/// Statistics since this was last called
+	pub fn take_stats(&mut self) -> Stats {
+		let stats = self.stats;
+
+		self.stats = Stats::new();
+
+		stats
+	}
+}
@@ -0,0 +1,235 @@
+//! Debouncing and gesture detection for push buttons, and decoding for a quadrature rotary encoder.
+//!
+//! Nothing here touches the hardware: `controls` reads the pins and feeds them in along with the
+//! time, so the same logic runs on the host. Times are counts of the 1us timer and are only ever
+//! subtracted, so it doesn't matter when the timer wraps.
+
+/// A button has to read the same for this long before a press or release counts
+const DEBOUNCE_US: u32 = 20_000;
+
+/// A press held this long is a long press. It's reported as soon as it's been held long enough rather
+/// than on release, so it's obvious when to let go.
+const LONG_PRESS_US: u32 = 700_000;
+
+/// A second click starting within this long of the first one ending makes a double click
+const DOUBLE_CLICK_US: u32 = 300_000;
+
+/// Quadrature steps from one click of the encoder to the next. Most cheap encoders go through all four
+/// states per click.
+const STEPS_PER_DETENT: i8 = 4;
+
+/// Change in position for each transition, indexed by the previous state of the A and B pins times 4
+/// plus the new state. Transitions where both pins change at once (a missed reading, or bounce) can't
+/// be told apart, so they count as nothing.
+const TRANSITIONS: [i8; 16] = [ 0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0 ];
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Press {
+	Short,
+	Long,
+	Double,
+}
+
+#[derive(Copy, Clone)]
+enum Gesture {
+	Idle,
+	/// Down since `since`. `second` is set if this is the second click of a double click.
+	Down { since: u32, second: bool },
+	/// Let go of at `since`, waiting to see if a second click follows
+	Released { since: u32 },
+	/// A long press has been reported; waiting for the button to be let go
+	Held,
+}
+
+/// One push button
+pub struct Button {
+	/// Reports double clicks. Without them there's no need to wait and see whether a second click is
+	/// coming, so short presses are reported as soon as the button is let go.
+	double_click: bool,
+
+	/// Latest raw reading and when it changed
+	reading: bool,
+	changed_at: u32,
+	/// Reading once it's been steady for `DEBOUNCE_US`
+	pressed: bool,
+
+	gesture: Gesture,
+}
+
+impl Button {
+	pub fn new(double_click: bool) -> Button {
+		Button {
+			double_click: double_click,
+
+			reading: false,
+			changed_at: 0,
+			pressed: false,
+
+			gesture: Gesture::Idle,
+		}
+	}
+
+	/// Feed in whether the button is down at `now`. Should be called at least every few milliseconds.
+	pub fn update(&mut self, down: bool, now: u32) -> Option<Press> {
+		if down != self.reading {
+			self.reading = down;
+			self.changed_at = now;
+		}
+
+		if self.reading != self.pressed && now.wrapping_sub(self.changed_at) >= DEBOUNCE_US {
+			self.pressed = self.reading;
+
+			return if self.pressed { self.press(now) } else { self.release(now) };
+		}
+
+		match self.gesture {
+			Gesture::Down { since, .. } if now.wrapping_sub(since) >= LONG_PRESS_US => {
+				self.gesture = Gesture::Held;
+
+				Some(Press::Long)
+			},
+			Gesture::Released { since } if now.wrapping_sub(since) >= DOUBLE_CLICK_US => {
+				self.gesture = Gesture::Idle;
+
+				Some(Press::Short)
+			},
+			_ => None,
+		}
+	}
+
+	fn press(&mut self, now: u32) -> Option<Press> {
+		self.gesture = match self.gesture {
+			Gesture::Released { .. } => Gesture::Down { since: now, second: true },
+			_ => Gesture::Down { since: now, second: false },
+		};
+
+		None
+	}
+
+	fn release(&mut self, now: u32) -> Option<Press> {
+		let (gesture, press) = match self.gesture {
+			Gesture::Down { second: true, .. } => (Gesture::Idle, Some(Press::Double)),
+			Gesture::Down { .. } if self.double_click => (Gesture::Released { since: now }, None),
+			Gesture::Down { .. } => (Gesture::Idle, Some(Press::Short)),
+			_ => (Gesture::Idle, None),
+		};
+
+		self.gesture = gesture;
+
+		press
+	}
+}
+
+/// A quadrature rotary encoder
+pub struct Encoder {
+	/// A and B pins as bits 1 and 0
+	state: u8,
+	/// Steps since the last whole click
+	steps: i8,
+}
+
+impl Encoder {
+	pub fn new() -> Encoder {
+		// Encoders rest with both pins open, which the pull ups read as high
+		Encoder { state: 0b11, steps: 0 }
+	}
+
+	/// Feed in the levels of the A and B pins. Returns 1 for a click clockwise, -1 for a click
+	/// anticlockwise and 0 otherwise. If it turns the wrong way, swap A and B.
+	pub fn update(&mut self, a: bool, b: bool) -> i8 {
+		let state = (a as u8) << 1 | b as u8;
+
+		self.steps += TRANSITIONS[(self.state << 2 | state) as usize];
+		self.state = state;
+
+		if self.steps >= STEPS_PER_DETENT {
+			self.steps -= STEPS_PER_DETENT;
+
+			1
+		} else if self.steps <= -STEPS_PER_DETENT {
+			self.steps += STEPS_PER_DETENT;
+
+			-1
+		} else {
+			0
+		}
+	}
+}
+
+/// What the controls ask the cube to do
+#[derive(Copy, Clone, PartialEq)]
+pub enum Action {
+	NextPattern,
+	BrightnessUp,
+	BrightnessDown,
+	DefaultBrightness,
+	/// Turn the LEDs off, or back on again
+	Power,
+}
+
+/// Levels of every input pin at one moment. Buttons are true while held down.
+#[derive(Copy, Clone)]
+pub struct Pins {
+	pub sw1: bool,
+	pub sw2: bool,
+	pub encoder_a: bool,
+	pub encoder_b: bool,
+	pub encoder_button: bool,
+}
+
+/// Turns the LaunchPad's two buttons and an optional rotary encoder into `Action`s:
+///
+/// * SW1 (or pushing the encoder) moves on to the next pattern. Holding it turns the cube off.
+/// * SW2 turns the brightness up, and double clicking it turns it down. Holding it goes back to the
+///   default brightness.
+/// * Turning the encoder turns the brightness up or down.
+///
+/// With the cube off, any press turns it back on.
+pub struct Inputs {
+	sw1: Button,
+	sw2: Button,
+	encoder: Encoder,
+	encoder_button: Button,
+}
+
+impl Inputs {
+	pub fn new() -> Inputs {
+		Inputs {
+			sw1: Button::new(false),
+			sw2: Button::new(true),
+			encoder: Encoder::new(),
+			encoder_button: Button::new(false),
+		}
+	}
+
+	/// Feed in the pins read at `now`. If more than one thing happens at once, only the first is
+	/// returned.
+	pub fn update(&mut self, pins: Pins, now: u32) -> Option<Action> {
+		let sw1 = self.sw1.update(pins.sw1, now);
+		let sw2 = self.sw2.update(pins.sw2, now);
+		let turn = self.encoder.update(pins.encoder_a, pins.encoder_b);
+		let encoder_button = self.encoder_button.update(pins.encoder_button, now);
+
+		let sw1 = match (sw1, encoder_button) {
+			(Some(press), _) | (None, Some(press)) => match press {
+				Press::Short | Press::Double => Some(Action::NextPattern),
+				Press::Long => Some(Action::Power),
+			},
+			(None, None) => None,
+		};
+
+		let sw2 = sw2.map(|press| match press {
+			Press::Short => Action::BrightnessUp,
+			Press::Double => Action::BrightnessDown,
+			Press::Long => Action::DefaultBrightness,
+		});
+
+		let turn = match turn {
+			1 => Some(Action::BrightnessUp),
+			-1 => Some(Action::BrightnessDown),
+			_ => None,
+		};
+
+		sw1.or(sw2).or(turn)
+	}
+}
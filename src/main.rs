@@ -1,5 +1,6 @@
 #![crate_type = "staticlib"]
-#![feature(plugin, start, core_intrinsics)]
+#![feature(plugin, start, core_intrinsics, asm)]
+#![cfg_attr(feature = "panic_handler", feature(lang_items))]
 #![no_std]
 #![plugin(macro_platformtree)]
 
@@ -7,23 +8,71 @@ extern crate zinc;
 
 // use zinc::hal::spi::Spi;
 use zinc::hal::cortex_m4::fpu;
+use zinc::hal::pin::Gpio;
 use zinc::hal::timer::Timer;
 use zinc::drivers::chario::CharIO;
 use zinc::hal::tiva_c;
 // use core::intrinsics;
 
 mod cube;
+mod adc;
 mod apa106led;
+mod audio;
+mod automata;
+mod beat;
 mod tables;
+mod clock;
 mod colour_functions;
+mod console;
+mod controls;
+mod deep_sleep;
+mod draw;
+mod eeprom;
 mod embedded_rand;
+mod fixed;
+mod font;
+mod frame_timing;
+mod input;
+#[cfg(feature = "panic_handler")]
+mod panic;
+mod particles;
+mod pattern_list;
 mod patterns;
+mod playback;
+mod power;
+mod procedural;
+mod protocols;
+mod rtc;
+mod schedule;
+mod scheduler;
+mod serial;
+mod settings;
+mod smooth;
+mod snake;
+mod sprites;
+mod status;
+mod stream;
+mod tiva_scheduler;
+mod transform;
+mod watchdog;
 
 use colour_functions::{ christmas_wheel, fade };
 use apa106led::{ Apa106Led, WARM_WHITE, OFF };
+use automata::{ BRIANS_BRAIN, EMBERS, SMALL_LIFE };
+use console::Text;
 use cube::{ Cube4, Voxel };
+use eeprom::TivaEeprom;
 use embedded_rand::{ rand_range };
-use patterns::{ MAX_BRIGHTNESS };
+use fixed::Fixed8;
+use font::FONT_4X4;
+use pattern_list::{ self, EVERY_PATTERN, PATTERN_COUNT };
+use patterns::{ MAX_BRIGHTNESS, MARQUEE_PALETTE, MARQUEE_TEXT };
+use power::LED_POWER_UP_MS;
+use procedural::Noise;
+use rtc::Rtc;
+use settings::Store;
+use tiva_scheduler::TivaScheduler;
+use watchdog::{ ResetCause, Retained, Watchdog };
 
 platformtree!(
 	tiva_c@mcu {
@@ -36,9 +85,24 @@ platformtree!(
 		}
 
 		gpio {
+			// SW1 and SW2 need pull ups and PF0 needs unlocking, which `controls` does itself
 			f {
-				led1@1 { direction = "out"; }
-				led2@2 { direction = "out"; }
+				sw2@0 { direction = "in"; }
+				status_red@1 { direction = "out"; }
+				status_blue@2 { direction = "out"; }
+				sw1@4 { direction = "in"; }
+			}
+
+			// Optional rotary encoder, also set up by `controls`
+			b {
+				encoder_a@0 { direction = "in"; }
+				encoder_b@1 { direction = "in"; }
+				encoder_button@2 { direction = "in"; }
+			}
+
+			// Optional MOSFET switching the LEDs' power, high for on
+			e {
+				led_power@1 { direction = "out"; }
 			}
 
 			a {
@@ -82,19 +146,93 @@ platformtree!(
 				timer = &timer;
 				spi_tx = &spi_tx;
 				uart = &uart;
+				status_red = &status_red;
+				status_blue = &status_blue;
+				led_power = &led_power;
 			}
 		}
 	}
 );
 
-fn clamp_to_u8(value: f32) -> u8 {
-	let ret = if value > 255.0 {
-		255
-	} else {
-		value as u8
-	};
+/// The cube resets if a frame takes longer than this to draw and flush
+const WATCHDOG_TIMEOUT_MS: u32 = 2000;
+
+fn run_pattern(index: u8, cube: &mut Cube4, sched: &mut TivaScheduler, raindrop_colour: Apa106Led) {
+	match index {
+		// Rainbow
+		pattern_list::RAINBOW => {
+			for _ in 0..4 {
+				patterns::christmas_rainbow(cube, sched);
+			}
+		},
+
+		// Fadey slices thing
+		pattern_list::SLICES => {
+			for _ in 0..4 {
+				patterns::animated_slices(cube, sched);
+			}
+		},
+
+		// Rain
+		pattern_list::RAIN => patterns::rain(cube, sched, raindrop_colour),
+
+		// Fountain
+		pattern_list::FOUNTAIN => patterns::fountain(cube, sched),
+
+		// Fireworks
+		pattern_list::FIREWORKS => patterns::firework(cube, sched),
+
+		// Snow
+		pattern_list::SNOW => patterns::snow(cube, sched),
+
+		// Scrolling text, either set over the UART or the default
+		pattern_list::MARQUEE => {
+			let message = sched.message().unwrap_or(Text::new(MARQUEE_TEXT));
+
+			patterns::marquee(cube, sched, message.as_bytes(), &FONT_4X4, &MARQUEE_PALETTE);
+		},
+
+		// Generative patterns
+		pattern_list::PLASMA => patterns::plasma(cube, sched, 2, 40),
+		pattern_list::NOISE_FIELD => patterns::noise_field(cube, sched, Noise::Simplex, 4, 80),
+		pattern_list::FIRE => patterns::fire(cube, sched, 12, 90),
+
+		// Spectrum analyser from the microphone
+		pattern_list::SPECTRUM => patterns::spectrum(cube, sched),
+
+		// Cellular automata
+		pattern_list::SMALL_LIFE => patterns::automaton(cube, sched, SMALL_LIFE, 40, 60),
+		pattern_list::EMBERS => patterns::automaton(cube, sched, EMBERS, 70, 60),
+		pattern_list::BRIANS_BRAIN => patterns::automaton(cube, sched, BRIANS_BRAIN, 40, 60),
+
+		// Snake, playable over the UART
+		pattern_list::SNAKE => patterns::snake(cube, sched),
+
+		// Holiday sprites
+		pattern_list::SPRITE_SHOW => patterns::sprite_show(cube, sched),
+
+		// Blender
+		pattern_list::BLENDER => {
+			for _ in 0..16 {
+				patterns::blender(cube, sched, raindrop_colour);
+			}
+		},
+
+		// Smooth blender
+		pattern_list::SMOOTH_BLENDER => {
+			for _ in 0..16 {
+				patterns::smooth_blender(cube, sched, raindrop_colour);
+			}
+		},
+
+		// Colour temperature following the time of day
+		pattern_list::DAYLIGHT => patterns::daylight(cube, sched),
+
+		// Recorded animation
+		pattern_list::SPIRAL => patterns::spiral(cube, sched),
 
-	ret
+		_ => (),
+	}
 }
 
 fn run(args: &pt::run_args) {
@@ -102,6 +240,50 @@ fn run(args: &pt::run_args) {
 
 	args.uart.puts("Started\r\n");
 
+	let cause = watchdog::reset_cause();
+	let retained = Retained::new();
+
+	serial::write_str("Reset by ");
+	serial::write_str(cause.name());
+	serial::write_str("\r\n");
+
+	match (cause, retained.pattern()) {
+		(ResetCause::PowerOn, _) => retained.clear_hung(),
+		// Only the panic handler resets the chip itself
+		(ResetCause::Watchdog, Some(index)) | (ResetCause::Software, Some(index)) => {
+			retained.mark_hung(index);
+
+			// Counting from 1, like the status LED
+			serial::write_str("Pattern ");
+			serial::write_decimal(index as u32 + 1);
+			serial::write_str(if cause == ResetCause::Watchdog { " hung" } else { " panicked" });
+			serial::write_str("; skipping it until the cube is turned off\r\n");
+		},
+		_ => (),
+	}
+
+	let hung = retained.hung_patterns();
+
+	// Until a pattern starts, a reset is start up's fault
+	retained.clear_pattern();
+
+	let rtc = Rtc::start(&retained);
+
+	match rtc.now() {
+		Some(time) => {
+			serial::write_str("Time is ");
+			serial::write_bytes(time.format().as_bytes());
+			serial::write_str("\r\n");
+		},
+		None => serial::write_str("Clock not set; the schedule is off until it is\r\n"),
+	}
+
+	let eeprom = TivaEeprom::new();
+
+	if !eeprom.is_usable() {
+		serial::write_str("EEPROM isn't working; settings won't be saved\r\n");
+	}
+
 	let spi = tiva_c::spi::Spi::new(tiva_c::spi::SpiConf {
 		peripheral: tiva_c::spi::SpiId::Spi0,
 
@@ -110,34 +292,43 @@ fn run(args: &pt::run_args) {
 
 	let mut cube = Cube4::new(&spi);
 
+	args.led_power.set_high();
+	args.timer.wait_ms(LED_POWER_UP_MS);
+
 	cube.fill(Apa106Led { red: 2, green: 0, blue: 0 });
 
 	cube.flush();
 	args.timer.wait_ms(1);
 
-	let raindrop_colour = fade(WARM_WHITE, MAX_BRIGHTNESS as f32 / 255.0);
+	let raindrop_colour = fade(WARM_WHITE, Fixed8::from_ratio(MAX_BRIGHTNESS as i32, 255));
+
+	// Started after the slow parts of start up, which would otherwise use up most of its timeout
+	let watchdog = Watchdog::start(WATCHDOG_TIMEOUT_MS);
 
-	let mut counter = 0;
+	let mut sched = TivaScheduler::new(args.timer, args.status_red, args.status_blue, args.led_power, watchdog, retained, Store::open(eeprom), rtc);
+
+	// Carry on from whatever was showing when the cube was last turned off
+	let mut index = sched.settings().last_pattern;
 
 	loop {
-		// Rainbow
-		for _ in 0..4 {
-			patterns::christmas_rainbow(&mut cube, &args.timer);
+		if index >= PATTERN_COUNT {
+			index = 0;
 		}
 
-		// Fadey slices thing
-		for _ in 0..4 {
-			patterns::animated_slices(&mut cube, &args.timer);
-		}
+		// With nothing left to play, play everything that hasn't hung. If that's nothing too, give every
+		// pattern another go rather than going round here without feeding the watchdog.
+		let playlist = match (sched.playlist() & EVERY_PATTERN & !hung, EVERY_PATTERN & !hung) {
+			(0, 0) => EVERY_PATTERN,
+			(0, working) => working,
+			(playlist, _) => playlist,
+		};
 
-		// Rain
-		for _ in 0..16 {
-			patterns::rain(&mut cube, &args.timer, raindrop_colour);
-		}
+		if playlist & 1 << index != 0 {
+			sched.start_pattern(index);
 
-		// Blender
-		for _ in 0..16 {
-			patterns::blender(&mut cube, &args.timer, raindrop_colour);
+			run_pattern(index, &mut cube, &mut sched, raindrop_colour);
 		}
+
+		index += 1;
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,105 @@
+//! Panic handler: blank the cube, say what went wrong over the UART, then reset.
+//!
+//! A panic can happen anywhere (an out of bounds voxel is the usual one), so nothing here relies on
+//! the handles `run()` sets up. The SPI, UART and status LED are all driven through their registers,
+//! which are still configured from before the panic.
+//!
+//! Zinc has its own `panic_fmt` that just spins, leaving whatever frame was last flushed lit until the
+//! watchdog resets the cube. Only one crate can provide it and zinc's can't be overridden from here, so
+//! this one is behind the `panic_handler` feature and needs a zinc without it; see the README.
+
+use core::fmt::{ self, Write };
+use core::intrinsics::{ volatile_load, volatile_store };
+
+use apa106led::OFF;
+use cube::colour_to_raw;
+use serial;
+
+const SSI0_BASE: u32 = 0x4000_8000;
+
+const SSI_DR: *mut u32 = (SSI0_BASE + 0x008) as *mut u32;
+const SSI_SR: *const u32 = (SSI0_BASE + 0x00C) as *const u32;
+
+/// Transmit FIFO not full and busy flags in SSISR
+const SR_TNF: u32 = 1 << 1;
+const SR_BSY: u32 = 1 << 4;
+
+/// Data register for PF1 only: GPIODATA masked by bits 2 to 9 of the address
+const GPIOF_DATA_PF1: *mut u32 = (0x4002_5000 + ((1 << 1) << 2)) as *mut u32;
+
+/// Application interrupt and reset control register, and the value that requests a system reset
+const NVIC_APINT: *mut u32 = 0xE000_ED0C as *mut u32;
+const APINT_SYSRESETREQ: u32 = 0x05FA_0004;
+
+/// Busy loop iterations in roughly a millisecond at 80MHz. Each one is a few instructions and a volatile
+/// load, so this doesn't need to be exact.
+const LOOPS_PER_MS: u32 = 10_000;
+
+/// How long to keep the status LED red before resetting, so there's time to see it. Shorter than
+/// `WATCHDOG_TIMEOUT_MS` in `main.rs`, so this reset comes first and the next boot knows it was a panic.
+const RESET_AFTER_MS: u32 = 1000;
+
+/// Set while handling a panic, so a panic in the handler doesn't go round again
+static mut PANICKING: bool = false;
+
+struct SerialWriter;
+
+impl Write for SerialWriter {
+	fn write_str(&mut self, string: &str) -> fmt::Result {
+		serial::write_str(string);
+
+		Ok(())
+	}
+}
+
+fn delay_ms(ms: u32) {
+	for _ in 0..ms * LOOPS_PER_MS {
+		unsafe {
+			volatile_load(SSI_SR);
+		}
+	}
+}
+
+/// Flush an all off frame straight through SSI0
+fn blank_cube() {
+	let raw = colour_to_raw(&OFF);
+
+	for _ in 0..64 {
+		for &byte in raw.iter() {
+			unsafe {
+				while volatile_load(SSI_SR) & SR_TNF == 0 {}
+
+				volatile_store(SSI_DR, byte as u32);
+			}
+		}
+	}
+
+	unsafe {
+		while volatile_load(SSI_SR) & SR_BSY != 0 {}
+	}
+
+	// Let the LEDs latch
+	delay_ms(1);
+}
+
+#[lang = "panic_fmt"]
+#[no_mangle]
+pub extern fn rust_begin_unwind(message: fmt::Arguments, file: &'static str, line: u32) -> ! {
+	unsafe {
+		if !PANICKING {
+			PANICKING = true;
+
+			blank_cube();
+
+			volatile_store(GPIOF_DATA_PF1, 0xff);
+
+			let _ = write!(SerialWriter, "\r\npanicked at {}:{}: {}\r\n", file, line, message);
+
+			delay_ms(RESET_AFTER_MS);
+		}
+
+		volatile_store(NVIC_APINT, APINT_SYSRESETREQ);
+	}
+
+	loop {}
+}
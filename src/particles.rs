@@ -0,0 +1,201 @@
+//! Fixed capacity particle system. Particles have a sub-voxel position and velocity, are pulled
+//! around by gravity, drag and turbulence, and die when they reach the end of their lifetime or leave
+//! the cube. They're drawn with `smooth::splat`, so they move smoothly between voxels, and the previous
+//! frame can be faded out instead of cleared to leave trails behind them.
+//!
+//! Positions and velocities use the same 8.8 fixed point as `smooth`; velocities are in sub-voxels per
+//! tick, where a tick is one call to `update()`.
+
+use apa106led::Apa106Led;
+use colour_functions::scale;
+use cube::{ Cube4, CUBE_SIZE };
+use embedded_rand::rand_range;
+use smooth::{ self, SubPoint, SUBVOXEL_ONE };
+
+pub const MAX_PARTICLES: usize = 48;
+
+#[derive(Copy, Clone)]
+pub struct Velocity {
+	pub x: i16,
+	pub y: i16,
+	pub z: i16,
+}
+
+#[derive(Copy, Clone)]
+pub struct Particle {
+	pub position: SubPoint,
+	pub velocity: Velocity,
+	/// Ticks since the particle was spawned
+	pub age: u16,
+	/// The particle dies when `age` reaches this
+	pub lifetime: u16,
+	/// Free for the pattern to use, e.g. as a hue to colour the particle with
+	pub tag: u8,
+}
+
+impl Particle {
+	pub fn is_alive(&self) -> bool {
+		self.age < self.lifetime
+	}
+
+	/// How far through its life the particle is, from 0 when it's spawned to 255 when it dies
+	pub fn life(&self) -> u8 {
+		if self.lifetime == 0 {
+			255
+		} else {
+			(self.age as u32 * 255 / self.lifetime as u32) as u8
+		}
+	}
+}
+
+/// Spawns particles around a point. Every jitter field is the largest random amount added to or
+/// subtracted from the matching value for each new particle.
+#[derive(Copy, Clone)]
+pub struct Emitter {
+	pub position: SubPoint,
+	pub position_jitter: SubPoint,
+	pub velocity: Velocity,
+	pub velocity_jitter: Velocity,
+	pub lifetime: u16,
+	pub lifetime_jitter: u16,
+	/// New particles per tick in 8.8 fixed point, so an emitter can spawn less than one particle per tick
+	pub rate: u16,
+	pub tag: u8,
+}
+
+fn jitter(value: i16, amount: i16) -> i16 {
+	if amount <= 0 {
+		return value;
+	}
+
+	value + rand_range(0, amount as u32 * 2) as i16 - amount
+}
+
+pub struct ParticleSystem {
+	particles: [Particle; MAX_PARTICLES],
+	/// Added to every particle's Z velocity each tick. Negative values pull particles down.
+	pub gravity: i16,
+	/// How much velocity particles lose each tick, out of 256
+	pub drag: u8,
+	/// Largest random amount added to each particle's X and Y velocity each tick
+	pub turbulence: i16,
+	/// How much of the previous frame is kept when rendering, out of 255. 0 clears the cube each frame,
+	/// higher values leave longer trails.
+	pub trail: u8,
+}
+
+impl ParticleSystem {
+	pub fn new() -> ParticleSystem {
+		let dead = Particle {
+			position: SubPoint { x: 0, y: 0, z: 0 },
+			velocity: Velocity { x: 0, y: 0, z: 0 },
+			age: 0,
+			lifetime: 0,
+			tag: 0,
+		};
+
+		ParticleSystem {
+			particles: [dead; MAX_PARTICLES],
+			gravity: 0,
+			drag: 0,
+			turbulence: 0,
+			trail: 0,
+		}
+	}
+
+	/// Add a particle to the system. Returns false if it's already full.
+	pub fn spawn(&mut self, particle: Particle) -> bool {
+		match self.particles.iter_mut().find(|p| !p.is_alive()) {
+			Some(slot) => {
+				*slot = particle;
+
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Spawn one tick's worth of particles from an emitter
+	pub fn emit(&mut self, emitter: &Emitter) {
+		// Whole particles are always spawned, the fractional part of the rate is a probability
+		let mut count = emitter.rate >> 8;
+
+		if rand_range(0, 255) < (emitter.rate & 0xff) as u32 {
+			count += 1;
+		}
+
+		for _ in 0..count {
+			let lifetime = jitter(emitter.lifetime as i16, emitter.lifetime_jitter as i16);
+
+			let spawned = self.spawn(Particle {
+				position: SubPoint {
+					x: jitter(emitter.position.x, emitter.position_jitter.x),
+					y: jitter(emitter.position.y, emitter.position_jitter.y),
+					z: jitter(emitter.position.z, emitter.position_jitter.z),
+				},
+				velocity: Velocity {
+					x: jitter(emitter.velocity.x, emitter.velocity_jitter.x),
+					y: jitter(emitter.velocity.y, emitter.velocity_jitter.y),
+					z: jitter(emitter.velocity.z, emitter.velocity_jitter.z),
+				},
+				age: 0,
+				lifetime: if lifetime > 0 { lifetime as u16 } else { 1 },
+				tag: emitter.tag,
+			});
+
+			if !spawned {
+				break;
+			}
+		}
+	}
+
+	/// Move every particle on by one tick and kill any that have expired or left the cube
+	pub fn update(&mut self) {
+		// Particles can go up to a voxel outside the cube before they're killed, so they fade out
+		// smoothly instead of vanishing at the edge. They're allowed to go above it to fall back in.
+		let low = -SUBVOXEL_ONE;
+		let high = CUBE_SIZE as i16 * SUBVOXEL_ONE;
+
+		let (gravity, turbulence, keep) = (self.gravity, self.turbulence, 256 - self.drag as i32);
+		let drag = |value: i16| (value as i32 * keep / 256) as i16;
+
+		for particle in self.particles.iter_mut().filter(|p| p.is_alive()) {
+			particle.velocity.x = jitter(drag(particle.velocity.x), turbulence);
+			particle.velocity.y = jitter(drag(particle.velocity.y), turbulence);
+			particle.velocity.z = drag(particle.velocity.z) + gravity;
+
+			particle.position.x = particle.position.x.saturating_add(particle.velocity.x);
+			particle.position.y = particle.position.y.saturating_add(particle.velocity.y);
+			particle.position.z = particle.position.z.saturating_add(particle.velocity.z);
+
+			particle.age += 1;
+
+			let position = particle.position;
+
+			if position.x < low || position.y < low || position.z < low || position.x > high || position.y > high {
+				particle.lifetime = 0;
+			}
+		}
+	}
+
+	/// Fade the previous frame by `trail` and draw every live particle in the colour returned by
+	/// `colour`, which gets the particle so it can change colour over its life.
+	pub fn render<F>(&self, cube: &mut Cube4, colour: F) where F: Fn(&Particle) -> Apa106Led {
+		let mut frame = cube.get_frame();
+
+		for voxel in frame.iter_mut() {
+			*voxel = scale(*voxel, self.trail);
+		}
+
+		cube.set_frame(&frame);
+
+		for particle in self.particles.iter().filter(|p| p.is_alive()) {
+			smooth::splat(cube, particle.position, colour(particle));
+		}
+	}
+
+	/// Number of live particles
+	pub fn count(&self) -> usize {
+		self.particles.iter().filter(|p| p.is_alive()).count()
+	}
+}
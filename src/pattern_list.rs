@@ -0,0 +1,29 @@
+//! The patterns `run_pattern()` in `main` knows about. Playlists pick patterns by bit, with bit 0 for
+//! pattern 0, which people see counted from 1.
+
+pub const RAINBOW: u8 = 0;
+pub const SLICES: u8 = 1;
+pub const RAIN: u8 = 2;
+pub const FOUNTAIN: u8 = 3;
+pub const FIREWORKS: u8 = 4;
+pub const SNOW: u8 = 5;
+pub const MARQUEE: u8 = 6;
+pub const PLASMA: u8 = 7;
+pub const NOISE_FIELD: u8 = 8;
+pub const FIRE: u8 = 9;
+pub const SPECTRUM: u8 = 10;
+pub const SMALL_LIFE: u8 = 11;
+pub const EMBERS: u8 = 12;
+pub const BRIANS_BRAIN: u8 = 13;
+pub const SNAKE: u8 = 14;
+pub const SPRITE_SHOW: u8 = 15;
+pub const BLENDER: u8 = 16;
+pub const SMOOTH_BLENDER: u8 = 17;
+pub const DAYLIGHT: u8 = 18;
+pub const SPIRAL: u8 = 19;
+
+/// Number of patterns `run_pattern()` knows, which are shown in order
+pub const PATTERN_COUNT: u8 = 20;
+
+/// Bit mask with a bit for each pattern, like the playlist setting
+pub const EVERY_PATTERN: u32 = (1 << PATTERN_COUNT) - 1;
@@ -1,61 +1,281 @@
-use zinc::hal::timer::Timer;
-use zinc::hal::tiva_c::timer::{ Timer as TivaCTimer };
-
-use colour_functions::{ christmas_wheel, fade };
+use audio::{ Analyser, BANDS };
+use automata::{ Automaton, Rule };
+use clock::MINUTES_PER_DAY;
+use colour_functions::{ self, blackbody, christmas_wheel, fade, lerp_colour, rgb_wheel, scale, temp_to_rgb };
 use apa106led::{ Apa106Led, WARM_WHITE, OFF };
-use cube::{ Cube4, Voxel };
-use embedded_rand::{ rand_range };
+use cube::{ Cube4, Voxel, CUBE_SIZE };
+use draw::{ self, Point };
+use fixed::Fixed8;
+use font::{ Font, FONT_4X4, LETTER_SPACING };
+use embedded_rand::{ rand_range, rand_u8 };
+use particles::{ Emitter, Particle, ParticleSystem, Velocity };
+use playback::Playback;
+use procedural::{ self, Noise, NOISE_ONE };
+use schedule;
+use scheduler::Scheduler;
+use smooth::{ self, SubPoint, SUBVOXEL_ONE };
+use snake::{ Direction, Snake, Step };
+use sprites::{ self, NO_TINT };
+use transform::{ self, Axis };
 
 pub const MAX_BRIGHTNESS: u8 = 25;
 
-pub fn rain(cube: &mut Cube4, timer: &TivaCTimer, raindrop_colour: Apa106Led) {
-	// Spawn some new raindrops
-	for index in 0..16 {
-		cube.set_at_index((index + 16 * 3) as usize, if rand_range(0, 64) < 16 { raindrop_colour } else { OFF });
+/// How long patterns that move by elapsed time rather than by frame run for
+const TIMED_PATTERN_US: u32 = 12_000_000;
+
+/// Speeds of those patterns are given per this long, the frame time they were first written for
+const SPEED_STEP_MS: u32 = 30;
+
+/// How far something moving `speed` every `SPEED_STEP_MS` has gone after `elapsed_us`
+fn travelled(elapsed_us: u32, speed: u8) -> u32 {
+	elapsed_us / 1000 * speed as u32 / SPEED_STEP_MS
+}
+
+/// Text scrolled by `marquee` until something else is set over the UART console
+pub const MARQUEE_TEXT: &'static [u8] = b"MERRY CHRISTMAS";
+
+/// Colours used for each character of the marquee in turn
+pub const MARQUEE_PALETTE: [Apa106Led; 3] = [
+	Apa106Led { red: MAX_BRIGHTNESS, green: 0, blue: 0 },
+	Apa106Led { red: 0, green: MAX_BRIGHTNESS, blue: 0 },
+	Apa106Led { red: MAX_BRIGHTNESS, green: MAX_BRIGHTNESS * 3 / 4, blue: MAX_BRIGHTNESS / 4 },
+];
+
+/// Raindrops fall from random columns on the top layer, speeding up as they go and leaving a fading tail.
+/// With music playing they fall in bursts on the kick drum.
+pub fn rain<S: Scheduler>(cube: &mut Cube4, sched: &mut S, raindrop_colour: Apa106Led) {
+	const TICKS: u16 = 320;
+	const TICK_MS: u32 = 30;
+	// Stop spawning drops this many ticks before the end so the last ones can fall out of the cube
+	const RUN_OUT: u16 = 24;
+
+	let mut system = ParticleSystem::new();
+
+	system.gravity = -4;
+	system.trail = 128;
+
+	for tick in 0..TICKS {
+		if tick < TICKS - RUN_OUT {
+			let drops = match sched.beat() {
+				// A burst of drops on each kick drum while there's music playing
+				Some(beat) if beat.bpm > 0 => if beat.kick { 3 } else { 0 },
+				_ => if rand_range(0, 255) < 50 { 1 } else { 0 },
+			};
+
+			for _ in 0..drops {
+				system.spawn(Particle {
+					position: SubPoint::from_voxel(rand_range(0, 3) as u8, rand_range(0, 3) as u8, 3),
+					velocity: Velocity { x: 0, y: 0, z: -SUBVOXEL_ONE / 8 },
+					age: 0,
+					lifetime: 32,
+					tag: 0,
+				});
+			}
+		}
+
+		system.update();
+		system.render(cube, |_| raindrop_colour);
+
+		sched.show(cube, TICK_MS);
 	}
+}
 
-	cube.flush();
+/// Water shoots up from the middle of the bottom layer and falls back down, turning from white to blue
+pub fn fountain<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const TICKS: u16 = 400;
+	const TICK_MS: u32 = 30;
+	const RUN_OUT: u16 = 45;
+
+	let spout = Emitter {
+		position: SubPoint { x: 3 * SUBVOXEL_ONE / 2, y: 3 * SUBVOXEL_ONE / 2, z: 0 },
+		position_jitter: SubPoint { x: 0, y: 0, z: 0 },
+		velocity: Velocity { x: 0, y: 0, z: 100 },
+		velocity_jitter: Velocity { x: 14, y: 14, z: 10 },
+		lifetime: 45,
+		lifetime_jitter: 0,
+		rate: 256,
+		tag: 0,
+	};
+
+	let spray = Apa106Led { red: MAX_BRIGHTNESS / 2, green: MAX_BRIGHTNESS, blue: MAX_BRIGHTNESS };
+	let water = Apa106Led { red: 0, green: MAX_BRIGHTNESS / 5, blue: MAX_BRIGHTNESS };
+
+	let mut system = ParticleSystem::new();
+
+	system.gravity = -6;
+	system.trail = 64;
+
+	for tick in 0..TICKS {
+		if tick < TICKS - RUN_OUT {
+			system.emit(&spout);
+		}
 
-	timer.wait_ms(120);
+		system.update();
+		system.render(cube, |particle| lerp_colour(spray, water, particle.life()));
 
-	for _ in 0..4 {
-		// Move existing raindrops down
-		for z in (1..4) {
-			for x in 0..4 {
-				for y in 0..4 {
-					let current_position = Voxel { x: x, y: y, z: z };
-					let next_position = Voxel { x: x, y: y, z: z - 1 };
-					let current_col = cube.get_at_coord(current_position);
-
-					// cube.set_at_coord(current_position, fade(current_col, 0.35));
-					cube.set_at_coord(current_position, OFF);
-					cube.set_at_coord(next_position, current_col);
+		sched.show(cube, TICK_MS);
+	}
+}
+
+/// Rockets rise from the bottom of the cube and burst into a ball of coloured sparks
+pub fn firework<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const TICKS: u16 = 400;
+	const TICK_MS: u32 = 30;
+	const ROCKET_GRAVITY: i16 = -5;
+	const SPARKS: u8 = 24;
+	const SPARK_SPEED: i16 = 40;
+
+	let rocket_colour = Apa106Led { red: MAX_BRIGHTNESS, green: MAX_BRIGHTNESS / 2, blue: 0 };
+	let flash = Apa106Led { red: 255, green: 255, blue: 255 };
+
+	let mut system = ParticleSystem::new();
+	let mut rocket: Option<Particle> = None;
+
+	system.gravity = -2;
+	system.drag = 12;
+	system.trail = 160;
+
+	for tick in 0..TICKS {
+		rocket = match rocket {
+			// Burst at the top of the rocket's flight
+			Some(r) if r.velocity.z <= 0 => {
+				let hue = rand_u8();
+
+				for _ in 0..SPARKS {
+					// Pick a random direction by throwing away velocities outside a sphere
+					let velocity = loop {
+						let random = || rand_range(0, SPARK_SPEED as u32 * 2) as i16 - SPARK_SPEED;
+						let v = Velocity { x: random(), y: random(), z: random() };
+
+						if (v.x as i32 * v.x as i32) + (v.y as i32 * v.y as i32) + (v.z as i32 * v.z as i32) <= SPARK_SPEED as i32 * SPARK_SPEED as i32 {
+							break v;
+						}
+					};
+
+					system.spawn(Particle {
+						position: r.position,
+						velocity: velocity,
+						age: 0,
+						lifetime: rand_range(20, 30) as u16,
+						tag: hue,
+					});
 				}
-			}
+
+				None
+			},
+			Some(mut r) => {
+				r.position.z += r.velocity.z;
+				r.velocity.z += ROCKET_GRAVITY;
+
+				Some(r)
+			},
+			// Wait for the last burst to mostly fade before launching another, and don't launch one that
+			// won't have time to burst
+			None if system.count() < SPARKS as usize / 4 && tick < TICKS - 60 && rand_range(0, 255) < 40 => {
+				Some(Particle {
+					position: SubPoint::from_voxel(rand_range(1, 2) as u8, rand_range(1, 2) as u8, 0),
+					velocity: Velocity { x: 0, y: 0, z: rand_range(70, 85) as i16 },
+					age: 0,
+					lifetime: 1,
+					tag: 0,
+				})
+			},
+			None => None,
+		};
+
+		system.update();
+
+		system.render(cube, |particle| {
+			let colour = lerp_colour(flash, rgb_wheel(particle.tag), particle.life().saturating_mul(4));
+
+			scale(scale(colour, 255 - particle.life()), MAX_BRIGHTNESS)
+		});
+
+		if let Some(r) = rocket {
+			smooth::splat(cube, r.position, rocket_colour);
 		}
 
+		sched.show(cube, TICK_MS);
+	}
+}
 
-		cube.flush();
+/// Snowflakes drift slowly down from the top of the cube
+pub fn snow<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const TICKS: u16 = 400;
+	const TICK_MS: u32 = 40;
+	const RUN_OUT: u16 = 140;
+
+	let cloud = Emitter {
+		position: SubPoint { x: 3 * SUBVOXEL_ONE / 2, y: 3 * SUBVOXEL_ONE / 2, z: 3 * SUBVOXEL_ONE },
+		position_jitter: SubPoint { x: 3 * SUBVOXEL_ONE / 2, y: 3 * SUBVOXEL_ONE / 2, z: 0 },
+		velocity: Velocity { x: 0, y: 0, z: -8 },
+		velocity_jitter: Velocity { x: 4, y: 4, z: 2 },
+		lifetime: 140,
+		lifetime_jitter: 0,
+		rate: 72,
+		tag: 0,
+	};
+
+	let flake = Apa106Led { red: MAX_BRIGHTNESS * 4 / 5, green: MAX_BRIGHTNESS * 9 / 10, blue: MAX_BRIGHTNESS };
+
+	let mut system = ParticleSystem::new();
+
+	// Drag stops the flakes speeding up, so they settle at `8 * gravity` sub-voxels per tick
+	system.gravity = -1;
+	system.drag = 32;
+	system.turbulence = 3;
+
+	for tick in 0..TICKS {
+		if tick < TICKS - RUN_OUT {
+			system.emit(&cloud);
+		}
+
+		system.update();
+		system.render(cube, |_| flake);
 
-		timer.wait_ms(120);
+		sched.show(cube, TICK_MS);
 	}
 }
 
-pub fn christmas_rainbow(cube: &mut Cube4, timer: &TivaCTimer) {
-	for counter in 0..255 {
+/// Christmas colours cycling through the cube. The colours drift round slowly, or step round on the beat
+/// while there's music playing. Outside December (going by `schedule`) it uses the whole colour wheel.
+pub fn christmas_rainbow<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const BEAT_STEP: u8 = 32;
+
+	let mut hue: u8 = 0;
+	let mut last_beat = None;
+
+	let wheel: fn(u8) -> Apa106Led = if schedule::christmas(sched.time()) { christmas_wheel } else { rgb_wheel };
+	let brightness = Fixed8::from_ratio(MAX_BRIGHTNESS as i32, 255);
+
+	for _ in 0..255 {
 		for index in 0..64 {
-			let wheel_col = christmas_wheel(((index * 4) + counter as u8) & 255);
+			let wheel_col = wheel(((index * 4) + hue) & 255);
 
-			cube.set_at_index(index as usize, fade(wheel_col, (MAX_BRIGHTNESS as f32 / 255.0)));
+			cube.set_at_index(index as usize, fade(wheel_col, brightness));
 		}
 
-		cube.flush();
+		sched.show(cube, 16);
+
+		let step = match sched.beat() {
+			Some(beat) if beat.bpm > 0 => {
+				let step = match last_beat {
+					Some(count) if count != beat.count => BEAT_STEP,
+					_ => 0,
+				};
+
+				last_beat = Some(beat.count);
 
-		timer.wait_ms(16);
+				step
+			},
+			_ => 1,
+		};
+
+		hue = hue.wrapping_add(step);
 	}
 }
 
-pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
+pub fn animated_slices<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
 	const FRAME_TIME: u32 = 40;
 
 	// Fade red panels up
@@ -63,9 +283,7 @@ pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
 		for i in 0..MAX_BRIGHTNESS {
 			cube.fill_panel(panel, Apa106Led { red: i, green: 0, blue: 0 });
 
-			cube.flush();
-
-			timer.wait_ms(FRAME_TIME);
+			sched.show(cube, FRAME_TIME);
 		}
 	}
 
@@ -75,9 +293,7 @@ pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
 			cube.fill_panel(panel, Apa106Led { red: i, green: 0, blue: 0 });
 		}
 
-		cube.flush();
-
-		timer.wait_ms(FRAME_TIME);
+		sched.show(cube, FRAME_TIME);
 	}
 
 	// Fade green slices up
@@ -85,9 +301,7 @@ pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
 		for i in 0..MAX_BRIGHTNESS {
 			cube.fill_slice(slice, Apa106Led { red: 0, green: i, blue: 0 });
 
-			cube.flush();
-
-			timer.wait_ms(FRAME_TIME);
+			sched.show(cube, FRAME_TIME);
 		}
 	}
 
@@ -97,9 +311,7 @@ pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
 			cube.fill_slice(slice, Apa106Led { red: 0, green: i, blue: 0 });
 		}
 
-		cube.flush();
-
-		timer.wait_ms(FRAME_TIME);
+		sched.show(cube, FRAME_TIME);
 	}
 
 	// Fade white layers  up
@@ -107,9 +319,7 @@ pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
 		for i in 0..MAX_BRIGHTNESS {
 			cube.fill_layer(layer, Apa106Led { red: i, green: i, blue: i });
 
-			cube.flush();
-
-			timer.wait_ms(FRAME_TIME);
+			sched.show(cube, FRAME_TIME);
 		}
 	}
 
@@ -119,43 +329,556 @@ pub fn animated_slices(cube: &mut Cube4, timer: &TivaCTimer) {
 			cube.fill_layer(layer, Apa106Led { red: i, green: i, blue: i });
 		}
 
-		cube.flush();
+		sched.show(cube, FRAME_TIME);
+	}
+}
 
-		timer.wait_ms(FRAME_TIME);
+/// Frame time for an animation that takes `steps` frames per beat while there's music playing, or
+/// `default_ms` without a steady beat
+fn tempo_frame_ms<S: Scheduler>(sched: &mut S, steps: u32, default_ms: u32) -> u32 {
+	match sched.beat() {
+		Some(beat) if beat.bpm > 0 => 60_000 / (beat.bpm as u32 * steps),
+		_ => default_ms,
 	}
 }
 
-pub fn blender(cube: &mut Cube4, timer: &TivaCTimer, fill_colour: Apa106Led) {
+/// A blade spinning round the middle of the cube, half a turn per beat while there's music playing
+pub fn blender<S: Scheduler>(cube: &mut Cube4, sched: &mut S, fill_colour: Apa106Led) {
 	for offs in 0..6 {
-		for i in 0..64 {
-			cube.set_at_index(i, Apa106Led { red: 0, green: 0, blue: 0 });
+		cube.fill(OFF);
+
+		// One end of the blade walks round the outside of the cube, the other end is opposite it
+		let end = if offs < 4 {
+			Point { x: 3 - offs, y: 0, z: 0 }
+		} else {
+			Point { x: 0, y: offs - 3, z: 0 }
+		};
 
-			timer.wait_us(1);
+		for z in 0..4 {
+			draw::line(cube, Point { z: z, ..end }, Point { x: 3 - end.x, y: 3 - end.y, z: z }, fill_colour);
 		}
 
-		// Inside ring
-		match offs {
-			0|1|5 => {
-				cube.fill_column(Voxel { x: 1, y: 2, z: 0 }, fill_colour);
-				cube.fill_column(Voxel { x: 2, y: 1, z: 0 }, fill_colour);
-			},
-			_ => {
-				cube.fill_column(Voxel { x: 1, y: 1, z: 0 }, fill_colour);
-				cube.fill_column(Voxel { x: 2, y: 2, z: 0 }, fill_colour);
+		let frame_ms = tempo_frame_ms(sched, 6, 100);
+
+		sched.show(cube, frame_ms);
+	}
+}
+
+/// Same blade as `blender`, but drawn with sub-voxel precision so it sweeps round smoothly
+pub fn smooth_blender<S: Scheduler>(cube: &mut Cube4, sched: &mut S, fill_colour: Apa106Led) {
+	const STEPS: i16 = 8;
+
+	// Where `blender` puts the end of the blade for each offset. Offset 6 is the same blade as offset 0
+	// seen from the other end, which keeps the last step moving in the same direction.
+	let blade_end = |offs: u8| if offs < 4 {
+		SubPoint::from_voxel(3 - offs, 0, 0)
+	} else {
+		SubPoint::from_voxel(0, offs - 3, 0)
+	};
+
+	for offs in 0..6 {
+		for step in 0..STEPS {
+			let end = blade_end(offs).lerp(blade_end(offs + 1), step * SUBVOXEL_ONE / STEPS);
+			let opposite = SubPoint { x: 3 * SUBVOXEL_ONE - end.x, y: 3 * SUBVOXEL_ONE - end.y, z: 0 };
+
+			cube.fill(OFF);
+
+			for z in 0..4 {
+				for i in 0..4 {
+					let point = end.lerp(opposite, i * SUBVOXEL_ONE / 3);
+
+					smooth::splat(cube, SubPoint { z: z * SUBVOXEL_ONE, ..point }, fill_colour);
+				}
 			}
+
+			let frame_ms = tempo_frame_ms(sched, 6 * STEPS as u32, 100 / STEPS as u32);
+
+			sched.show(cube, frame_ms);
 		}
+	}
+}
 
-		// Outside ring
-		if offs < 4 {
-			cube.fill_column(Voxel { x: 3 - offs, y: 0, z: 0 }, fill_colour);
-			cube.fill_column(Voxel { x: offs, y: 3, z: 0 }, fill_colour);
-		} else {
-			cube.fill_column(Voxel { x: 0, y: offs - 3, z: 0 }, fill_colour);
-			cube.fill_column(Voxel { x: 3, y: 3 - (offs - 3), z: 0 }, fill_colour);
+/// Holiday shapes: a beating heart, rising arrows, a spinning snowflake and "HI" scrolling past
+pub fn sprite_show<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	// Heart beat, two quick pulses then a rest
+	for _ in 0..4 {
+		for &pulse in [ 255u16, 160, 255, 160, 120, 120 ].iter() {
+			let brightness = (MAX_BRIGHTNESS as u16 * pulse / 255) as u8;
+
+			cube.fill(OFF);
+
+			for y in 1..3 {
+				sprites::blit(cube, &sprites::HEART, Point { x: 0, y: y, z: 0 }, NO_TINT, brightness);
+			}
+
+			sched.show(cube, 120);
+		}
+	}
+
+	// Arrows flying up through the cube, changing colour each time
+	for arrow in 0..6 {
+		let tint = rgb_wheel(arrow * 42);
+
+		for z in -3..4 {
+			cube.fill(OFF);
+
+			sprites::blit(cube, &sprites::ARROW_UP, Point { x: 0, y: 0, z: z }, tint, MAX_BRIGHTNESS);
+
+			sched.show(cube, 80);
+		}
+	}
+
+	// Spinning snowflake
+	for step in 0..72u32 {
+		cube.fill(OFF);
+
+		sprites::blit(cube, &sprites::SNOWFLAKE, Point { x: 0, y: 0, z: 0 }, NO_TINT, MAX_BRIGHTNESS);
+		transform::rotate(cube, Axis::Z, (step * 0x1_0000 / 72) as u16, OFF);
+
+		sched.show(cube, 40);
+	}
+
+	// "HI" sliding in from the right and out of the left, on the front face
+	for x in (-9..5).rev() {
+		cube.fill(OFF);
+
+		sprites::blit(cube, &sprites::LETTER_H, Point { x: x, y: 0, z: 0 }, WARM_WHITE, MAX_BRIGHTNESS);
+		sprites::blit(cube, &sprites::LETTER_I, Point { x: x + 5, y: 0, z: 0 }, WARM_WHITE, MAX_BRIGHTNESS);
+
+		sched.show(cube, 150);
+	}
+}
+
+/// Colours for cellular automaton cells by age: newborn cells are white, turning blue, then green, then amber
+const AGE_PALETTE: [Apa106Led; 5] = [
+	Apa106Led { red: MAX_BRIGHTNESS, green: MAX_BRIGHTNESS, blue: MAX_BRIGHTNESS },
+	Apa106Led { red: 0, green: MAX_BRIGHTNESS / 2, blue: MAX_BRIGHTNESS },
+	Apa106Led { red: 0, green: MAX_BRIGHTNESS, blue: MAX_BRIGHTNESS / 3 },
+	Apa106Led { red: MAX_BRIGHTNESS / 2, green: MAX_BRIGHTNESS, blue: 0 },
+	Apa106Led { red: MAX_BRIGHTNESS, green: MAX_BRIGHTNESS / 2, blue: 0 },
+];
+
+/// Run a cellular automaton for `generations`, reseeding the board whenever it dies or settles down.
+/// `density` is the chance of each cell starting alive, out of 255.
+pub fn automaton<S: Scheduler>(cube: &mut Cube4, sched: &mut S, rule: Rule, density: u8, generations: u16) {
+	const GENERATION_MS: u32 = 200;
+	const FINAL_MS: u32 = 1000;
+	// Oscillators with long periods aren't spotted, so give up on a board after this many generations
+	const MAX_GENERATIONS: u16 = 150;
+
+	let mut automaton = Automaton::new(rule);
+
+	automaton.seed(density);
+
+	for _ in 0..generations {
+		automaton.render(cube, &AGE_PALETTE);
+
+		sched.show(cube, GENERATION_MS);
+
+		automaton.step();
+
+		if automaton.is_stable() || automaton.generation >= MAX_GENERATIONS {
+			// Hold the final state for a moment before starting again
+			automaton.render(cube, &AGE_PALETTE);
+
+			sched.show(cube, FINAL_MS);
+
+			automaton.seed(density);
 		}
+	}
+}
 
-		cube.flush();
+/// Columns round the outside of the cube, anticlockwise looking down, starting at the front left
+const PERIMETER: [(u8, u8); 12] = [
+	(0, 0), (1, 0), (2, 0), (3, 0),
+	(3, 1), (3, 2), (3, 3),
+	(2, 3), (1, 3), (0, 3),
+	(0, 2), (0, 1),
+];
+
+/// Rows lit in one column of a line of text, and the index of the character the column is part of.
+/// Returns `None` for the gaps between characters and past the end of the text.
+fn text_column(text: &[u8], font: &Font, column: usize) -> Option<(u8, usize)> {
+	let mut start = 0;
+
+	for (index, &character) in text.iter().enumerate() {
+		let advance = font.advance(character) as usize;
+
+		if column < start + advance {
+			return Some((font.column(character, (column - start) as u8), index));
+		}
+
+		start += advance + LETTER_SPACING as usize;
+
+		if column < start {
+			return None;
+		}
+	}
+
+	None
+}
+
+/// Scroll text round the four side faces of the cube. Characters are coloured from `palette` in turn.
+pub fn marquee<S: Scheduler>(cube: &mut Cube4, sched: &mut S, text: &[u8], font: &Font, palette: &[Apa106Led]) {
+	const STEP_MS: u32 = 150;
+
+	if palette.is_empty() {
+		return;
+	}
+
+	let width: usize = text.iter().map(|&character| (font.advance(character) + LETTER_SPACING) as usize).sum();
+
+	// The text starts just off the end of the perimeter and goes all the way round until it's gone
+	for scroll in 0..width + PERIMETER.len() {
+		cube.fill(OFF);
+
+		for (position, &(x, y)) in PERIMETER.iter().enumerate() {
+			if scroll + position < PERIMETER.len() {
+				continue;
+			}
+
+			if let Some((rows, index)) = text_column(text, font, scroll + position - PERIMETER.len()) {
+				for row in 0..font.height {
+					// Fonts taller than the cube lose their bottom rows
+					if row < CUBE_SIZE && rows & (1 << row) != 0 {
+						cube.set_at_coord(Voxel { x: x, y: y, z: CUBE_SIZE - 1 - row }, palette[index % palette.len()]);
+					}
+				}
+			}
+		}
 
-		timer.wait_ms(100);
+		sched.show(cube, STEP_MS);
 	}
-}
\ No newline at end of file
+}
+
+/// Sum of four moving sine waves, coloured round the colour wheel. `speed` is how far the waves move
+/// every 30ms and `scale` is how much the waves change from one voxel to the next, both in 256ths of a
+/// wavelength.
+pub fn plasma<S: Scheduler>(cube: &mut Cube4, sched: &mut S, speed: u8, scale: u8) {
+	let mut elapsed_us = 0;
+
+	while elapsed_us < TIMED_PATTERN_US {
+		let time = travelled(elapsed_us, speed) as u8;
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					// Distance squared from the middle of the cube, in quarter voxels
+					let radius = [ 9, 1, 1, 9 ];
+					let distance = radius[x as usize] + radius[y as usize] + radius[z as usize];
+
+					// Angles are allowed to wrap round
+					let angle = |steps: u16, divisor: u16| (steps * scale as u16 / divisor) as u8;
+
+					let sum = procedural::sine(angle(x as u16, 1).wrapping_add(time)) as i16
+						+ procedural::sine(angle(y as u16, 1).wrapping_sub(time.wrapping_mul(2))) as i16
+						+ procedural::sine(angle((x + y + z) as u16, 2).wrapping_add(time / 2)) as i16
+						+ procedural::sine(angle(distance, 4).wrapping_sub(time)) as i16;
+
+					// Sum is +/- 508. Drift the hue slowly too so the whole palette gets used.
+					let hue = ((sum / 4 + 128) as u8).wrapping_add(time / 4);
+
+					cube.set_at_coord(Voxel { x: x, y: y, z: z }, colour_functions::scale(rgb_wheel(hue), MAX_BRIGHTNESS));
+				}
+			}
+		}
+
+		sched.show_frame(cube);
+		elapsed_us += sched.delta_us();
+	}
+}
+
+/// Colours from drifting 3D noise. `speed` is how far the cube moves through the noise every 30ms and
+/// `scale` is the distance between voxels in noise space, both in 256ths of a lattice cell.
+pub fn noise_field<S: Scheduler>(cube: &mut Cube4, sched: &mut S, kind: Noise, speed: u8, scale: u8) {
+	let mut elapsed_us = 0;
+
+	while elapsed_us < TIMED_PATTERN_US {
+		let time = travelled(elapsed_us, speed) as i32;
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					let value = procedural::noise(
+						kind,
+						x as i32 * scale as i32 + time,
+						y as i32 * scale as i32 + time / 2,
+						z as i32 * scale as i32 - time / 3
+					);
+
+					// Spread roughly +/- 256 over one and a half turns of the colour wheel
+					let hue = ((value + NOISE_ONE) * 3 / 4 + time / 8) as u8;
+
+					cube.set_at_coord(Voxel { x: x, y: y, z: z }, colour_functions::scale(rgb_wheel(hue), MAX_BRIGHTNESS));
+				}
+			}
+		}
+
+		sched.show_frame(cube);
+		elapsed_us += sched.delta_us();
+	}
+}
+
+/// Flames licking up from the bottom of the cube, coloured by black body temperature. `speed` is how
+/// fast the flames rise and `scale` is how big they are, as in `noise_field`.
+pub fn fire<S: Scheduler>(cube: &mut Cube4, sched: &mut S, speed: u8, scale: u8) {
+	const COOLEST_K: i32 = 500;
+	const HOTTEST_K: i32 = 2300;
+
+	let mut elapsed_us = 0;
+
+	while elapsed_us < TIMED_PATTERN_US {
+		let rise = travelled(elapsed_us, speed) as i32;
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					// Sampling further down the noise each frame makes the pattern move up
+					let turbulence = procedural::simplex(x as i32 * scale as i32, y as i32 * scale as i32, z as i32 * scale as i32 - rise);
+
+					// Hottest at the bottom, cooling towards the top
+					let heat = 230 - z as i32 * 64 + turbulence * 3 / 4;
+					let heat = if heat < 0 { 0 } else if heat > 255 { 255 } else { heat };
+
+					let colour = blackbody((COOLEST_K + heat * (HOTTEST_K - COOLEST_K) / 255) as u32);
+
+					// Cooler gas is dimmer as well as redder
+					let brightness = (heat * heat / 255) * MAX_BRIGHTNESS as i32 / 255;
+
+					cube.set_at_coord(Voxel { x: x, y: y, z: z }, colour_functions::scale(colour, brightness as u8));
+				}
+			}
+		}
+
+		sched.show_frame(cube);
+		elapsed_us += sched.delta_us();
+	}
+}
+
+/// Colour temperature of daylight at the start of each hour, in Kelvin: candle light overnight, cool
+/// daylight around midday
+const DAYLIGHT_K: [u16; 25] = [
+	1900, 1900, 1900, 1900, 1900, 1900, 2300, 3200, 4200, 5000, 5800, 6300,
+	6500, 6500, 6300, 5800, 5000, 4200, 3400, 2900, 2600, 2300, 2100, 1900, 1900,
+];
+
+fn daylight_kelvin(minute_of_day: u16) -> u32 {
+	let hour = (minute_of_day / 60) as usize;
+	let minute = (minute_of_day % 60) as u32;
+
+	(DAYLIGHT_K[hour] as u32 * (60 - minute) + DAYLIGHT_K[hour + 1] as u32 * minute) / 60
+}
+
+/// The colour of daylight at the current time of day, a little cooler towards the top of the cube like
+/// the sky, with the brightness rippling slowly. Without the time it runs through a whole day instead.
+pub fn daylight<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const FRAMES: u16 = 400;
+	const FRAME_MS: u32 = 50;
+	/// How much cooler each layer is than the one below
+	const LAYER_K: u32 = 300;
+	const SCALE: i32 = 80;
+
+	for frame in 0..FRAMES {
+		let minute_of_day = match sched.time() {
+			Some(time) => time.minute_of_day(),
+			None => (frame as u32 * MINUTES_PER_DAY as u32 / FRAMES as u32) as u16,
+		};
+
+		let kelvin = daylight_kelvin(minute_of_day);
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					let ripple = procedural::simplex(x as i32 * SCALE, y as i32 * SCALE, z as i32 * SCALE + frame as i32 * 3);
+
+					// Between half and full brightness
+					let brightness = MAX_BRIGHTNESS as i32 * (3 * NOISE_ONE + ripple) / (4 * NOISE_ONE);
+					let brightness = if brightness < 0 { 0 } else if brightness > 255 { 255 } else { brightness };
+
+					let colour = temp_to_rgb(kelvin + z as u32 * LAYER_K);
+
+					cube.set_at_coord(Voxel { x: x, y: y, z: z }, colour_functions::scale(colour, brightness as u8));
+				}
+			}
+		}
+
+		sched.show(cube, FRAME_MS);
+	}
+}
+
+/// `recordings/spiral.zca`, converted from `recordings/spiral.csv` with `cube_convert`
+static SPIRAL: &'static [u8] = include_bytes!("../recordings/spiral.zca");
+
+/// A comet spiralling up round the outside of the cube and back down again, played from a recording
+pub fn spiral<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	if let Ok(recording) = Playback::new(SPIRAL) {
+		for _ in 0..3 {
+			recording.play(cube, sched);
+		}
+	}
+}
+
+/// Spectrum analyser: each column of the cube shows one frequency band, lowest at the front left and
+/// snaking back and forth to the highest at the back. Columns are lit in a rainbow with a white voxel
+/// marking each band's recent peak. Nothing lights up if the scheduler has no microphone.
+pub fn spectrum<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const FRAMES: u16 = 600;
+	const FRAME_MS: u32 = 30;
+
+	let mut analyser = Analyser::new();
+	let peak_colour = colour_functions::scale(WARM_WHITE, MAX_BRIGHTNESS);
+
+	for _ in 0..FRAMES {
+		if let Some(energy) = sched.bands() {
+			analyser.update(&energy);
+		}
+
+		for band in 0..BANDS {
+			let y = (band / CUBE_SIZE as usize) as u8;
+			let x = (band % CUBE_SIZE as usize) as u8;
+			let column = Voxel { x: if y % 2 == 0 { x } else { CUBE_SIZE - 1 - x }, y: y, z: 0 };
+
+			let colour = colour_functions::scale(rgb_wheel((band * 160 / BANDS) as u8), MAX_BRIGHTNESS);
+
+			cube.fill_column(column, OFF);
+
+			// Height in quarter voxels, with the top voxel dimmed to show the fraction
+			let height = analyser.levels[band] as u16 * CUBE_SIZE as u16 * 4 / 255;
+
+			for z in 0..CUBE_SIZE {
+				let lit = height.saturating_sub(z as u16 * 4);
+
+				if lit > 0 {
+					let brightness = if lit >= 4 { 255 } else { lit as u8 * 64 };
+
+					cube.set_at_coord(Voxel { z: z, ..column }, colour_functions::scale(colour, brightness));
+				}
+			}
+
+			if analyser.peaks[band] > 0 {
+				let peak = (analyser.peaks[band] as u16 * CUBE_SIZE as u16 / 256) as u8;
+
+				cube.set_at_coord(Voxel { z: peak, ..column }, peak_colour);
+			}
+		}
+
+		sched.show(cube, FRAME_MS);
+	}
+}
+
+/// Write `value` in decimal into `buffer`, returning the number of digits
+fn format_number(value: u16, buffer: &mut [u8]) -> usize {
+	let mut digits = [0u8; 5];
+	let mut count = 0;
+	let mut remaining = value;
+
+	loop {
+		digits[count] = b'0' + (remaining % 10) as u8;
+		count += 1;
+		remaining /= 10;
+
+		if remaining == 0 {
+			break;
+		}
+	}
+
+	for (i, &digit) in digits[..count].iter().rev().enumerate() {
+		buffer[i] = digit;
+	}
+
+	count
+}
+
+/// 3D snake. The cube plays itself until a direction key (see `Direction::from_key`) is pressed on the
+/// UART console, then a fresh game starts for the player. It goes back to playing itself if nobody
+/// presses anything for a while, and returns once the demo has run for long enough.
+pub fn snake<S: Scheduler>(cube: &mut Cube4, sched: &mut S) {
+	const STEP_MS: u32 = 300;
+	// Steps without a key press before the player is assumed to have walked off (30s)
+	const IDLE_STEPS: u16 = 100;
+	// How long the demo runs before moving on to the next pattern (90s)
+	const DEMO_STEPS: u16 = 300;
+
+	let head = Apa106Led { red: MAX_BRIGHTNESS / 2, green: MAX_BRIGHTNESS, blue: 0 };
+	let tail = Apa106Led { red: 0, green: MAX_BRIGHTNESS / 5, blue: MAX_BRIGHTNESS / 10 };
+	let food = Apa106Led { red: MAX_BRIGHTNESS, green: 0, blue: MAX_BRIGHTNESS / 4 };
+	let crashed = Apa106Led { red: MAX_BRIGHTNESS, green: 0, blue: 0 };
+
+	let mut playing = false;
+	let mut demo_steps = 0;
+
+	loop {
+		let mut game = Snake::new();
+		let mut idle = 0;
+		let mut taken_over = false;
+
+		loop {
+			match sched.key().and_then(Direction::from_key) {
+				Some(_) if !playing => {
+					playing = true;
+					taken_over = true;
+
+					break;
+				},
+				Some(direction) => {
+					game.turn(direction);
+					idle = 0;
+				},
+				None if playing => {
+					idle += 1;
+
+					if idle > IDLE_STEPS {
+						playing = false;
+					}
+				},
+				None => (),
+			}
+
+			if !playing {
+				let direction = game.ai_direction();
+
+				game.turn(direction);
+
+				demo_steps += 1;
+			}
+
+			game.render(cube, head, tail, food);
+
+			sched.show(cube, STEP_MS);
+
+			match game.step() {
+				Step::Crashed | Step::Won => break,
+				Step::Moved | Step::Ate => (),
+			}
+
+			if !playing && demo_steps >= DEMO_STEPS {
+				break;
+			}
+		}
+
+		if taken_over {
+			continue;
+		}
+
+		// Game over: flash the snake and scroll the score round the cube
+		for _ in 0..3 {
+			game.fill_body(cube, crashed);
+			sched.show(cube, 200);
+
+			game.render(cube, head, tail, food);
+			sched.show(cube, 200);
+		}
+
+		let mut text = [0u8; 11];
+
+		text[..6].copy_from_slice(b"SCORE ");
+
+		let length = 6 + format_number(game.score, &mut text[6..]);
+
+		marquee(cube, sched, &text[..length], &FONT_4X4, &[ head ]);
+
+		if !playing && demo_steps >= DEMO_STEPS {
+			return;
+		}
+	}
+}
@@ -0,0 +1,227 @@
+//! Compact recorded animation format and a pattern that plays it back.
+//!
+//! All multibyte fields are little endian. A file starts with an 11 byte header:
+//!
+//! ```text
+//! | "ZCA" | version: u8 | size x, y, z: u8 | frame count: u16 | delay ms: u16 |
+//! ```
+//!
+//! followed by `frame count` frames. Each frame starts with a flags byte. If `FLAG_DELAY` is set a
+//! `u16` delay follows which overrides the header delay for that frame only. The frame body comes
+//! next, covering the 64 voxels in coordinate order (`x + 4 * y + 16 * z`):
+//!
+//! * Key frames are run length encoded as `count: u8, r, g, b` runs.
+//! * Delta frames (`FLAG_DELTA`) only store what changed since the previous frame, as
+//!   `skip: u8, count: u8, count * (r, g, b)` spans. Skipped voxels keep their previous colour.
+//!
+//! Recordings can be made on a PC with the `cube_record` host tool and embedded in the firmware with
+//! `include_bytes!`. `cube_convert` can also generate them, or an uncompressed table of `TableFrame`s,
+//! from CSV and JSON frame lists.
+
+use apa106led::{ Apa106Led, OFF };
+use cube::Cube4;
+use scheduler::Scheduler;
+
+pub const MAGIC: [u8; 3] = [ b'Z', b'C', b'A' ];
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 11;
+
+pub const FLAG_DELTA: u8 = 1 << 0;
+pub const FLAG_DELAY: u8 = 1 << 1;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PlaybackError {
+	BadMagic,
+	UnsupportedVersion,
+	/// Recording was made for a cube that isn't 4x4x4
+	WrongSize,
+	/// Data ended part way through a frame
+	Truncated,
+	/// A run or span went past the end of the frame
+	Corrupt,
+}
+
+#[derive(Copy, Clone)]
+pub struct Header {
+	pub frame_count: u16,
+	pub delay_ms: u16,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+	data[offset] as u16 | (data[offset + 1] as u16) << 8
+}
+
+pub fn parse_header(data: &[u8]) -> Result<Header, PlaybackError> {
+	if data.len() < HEADER_LEN {
+		return Err(PlaybackError::Truncated);
+	}
+
+	if data[0..3] != MAGIC {
+		return Err(PlaybackError::BadMagic);
+	}
+
+	if data[3] != VERSION {
+		return Err(PlaybackError::UnsupportedVersion);
+	}
+
+	if data[4] != 4 || data[5] != 4 || data[6] != 4 {
+		return Err(PlaybackError::WrongSize);
+	}
+
+	Ok(Header {
+		frame_count: read_u16(data, 7),
+		delay_ms: read_u16(data, 9),
+	})
+}
+
+/// Decodes frames one at a time into a caller provided buffer, which must be left alone between
+/// calls as delta frames are applied on top of it.
+pub struct FrameDecoder<'a> {
+	data: &'a [u8],
+	header: Header,
+	position: usize,
+	frames_read: u16,
+}
+
+impl<'a> FrameDecoder<'a> {
+	pub fn new(data: &'a [u8]) -> Result<FrameDecoder<'a>, PlaybackError> {
+		let header = try!(parse_header(data));
+
+		Ok(FrameDecoder {
+			data: data,
+			header: header,
+			position: HEADER_LEN,
+			frames_read: 0,
+		})
+	}
+
+	pub fn header(&self) -> Header {
+		self.header
+	}
+
+	fn byte(&mut self) -> Result<u8, PlaybackError> {
+		if self.position >= self.data.len() {
+			return Err(PlaybackError::Truncated);
+		}
+
+		let byte = self.data[self.position];
+
+		self.position += 1;
+
+		Ok(byte)
+	}
+
+	fn colour(&mut self) -> Result<Apa106Led, PlaybackError> {
+		let red = try!(self.byte());
+		let green = try!(self.byte());
+		let blue = try!(self.byte());
+
+		Ok(Apa106Led { red: red, green: green, blue: blue })
+	}
+
+	/// Decode the next frame into `frame`, returning how long it should be shown for, or `None` once
+	/// all frames have been read.
+	pub fn next_frame(&mut self, frame: &mut [Apa106Led; 64]) -> Result<Option<u16>, PlaybackError> {
+		if self.frames_read == self.header.frame_count {
+			return Ok(None);
+		}
+
+		let flags = try!(self.byte());
+
+		let delay_ms = if flags & FLAG_DELAY != 0 {
+			let low = try!(self.byte());
+			let high = try!(self.byte());
+
+			low as u16 | (high as u16) << 8
+		} else {
+			self.header.delay_ms
+		};
+
+		let mut voxel = 0;
+
+		if flags & FLAG_DELTA != 0 {
+			while voxel < 64 {
+				let skip = try!(self.byte()) as usize;
+				let count = try!(self.byte()) as usize;
+
+				if voxel + skip + count > 64 {
+					return Err(PlaybackError::Corrupt);
+				}
+
+				voxel += skip;
+
+				for _ in 0..count {
+					frame[voxel] = try!(self.colour());
+					voxel += 1;
+				}
+			}
+		} else {
+			while voxel < 64 {
+				let count = try!(self.byte()) as usize;
+				let colour = try!(self.colour());
+
+				if voxel + count > 64 {
+					return Err(PlaybackError::Corrupt);
+				}
+
+				for _ in 0..count {
+					frame[voxel] = colour;
+					voxel += 1;
+				}
+			}
+		}
+
+		self.frames_read += 1;
+
+		Ok(Some(delay_ms))
+	}
+}
+
+/// Plays a recording embedded in the firmware, e.g.
+///
+/// ```ignore
+/// static SNOWFALL: &'static [u8] = include_bytes!("../recordings/snowfall.zca");
+/// ```
+pub struct Playback {
+	data: &'static [u8],
+}
+
+impl Playback {
+	pub fn new(data: &'static [u8]) -> Result<Playback, PlaybackError> {
+		try!(parse_header(data));
+
+		Ok(Playback { data: data })
+	}
+
+	/// Play the recording through once. Playback stops early if a corrupt frame is found.
+	pub fn play<S: Scheduler>(&self, cube: &mut Cube4, sched: &mut S) {
+		let mut decoder = match FrameDecoder::new(self.data) {
+			Ok(decoder) => decoder,
+			Err(_) => return
+		};
+
+		let mut frame = [OFF; 64];
+
+		while let Ok(Some(delay_ms)) = decoder.next_frame(&mut frame) {
+			cube.set_frame(&frame);
+
+			sched.show(cube, delay_ms as u32);
+		}
+	}
+}
+
+/// Uncompressed frame as generated by `cube_convert` when writing a Rust table
+pub struct TableFrame {
+	pub delay_ms: u16,
+	/// Voxel colours in coordinate order (`x + 4 * y + 16 * z`)
+	pub voxels: [Apa106Led; 64],
+}
+
+/// Play a generated table of frames through once
+pub fn play_table<S: Scheduler>(frames: &[TableFrame], cube: &mut Cube4, sched: &mut S) {
+	for frame in frames.iter() {
+		cube.set_frame(&frame.voxels);
+
+		sched.show(cube, frame.delay_ms as u32);
+	}
+}
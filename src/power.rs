@@ -0,0 +1,102 @@
+//! When to cut the LEDs' power and when to put the processor to sleep.
+//!
+//! * `On`: frames are shown as normal.
+//! * `Dark`: patterns are still running, but nothing has been lit for `BLANK_US`, so the LEDs are
+//!   unpowered until the next frame that lights anything.
+//! * `Asleep`: turned off with the controls or console. The LEDs are unpowered and the processor sleeps
+//!   until a button is pressed or a byte arrives on the UART.
+//!
+//! A blank LED still draws current, which is what `Dark` saves. Like `input` and `status`, nothing here
+//! touches the hardware; the scheduler tells it what's happened and sets the pins to match.
+
+/// How long the cube has to show nothing before the LEDs are powered down
+pub const BLANK_US: u32 = 5_000_000;
+
+/// How long the LEDs need after their power comes back before they'll take a frame
+pub const LED_POWER_UP_MS: u32 = 10;
+
+/// How long after waking before the controls do anything, so the press that woke the cube doesn't
+/// also skip a pattern or turn it off again. Longer than a long press.
+pub const WAKE_IGNORE_US: u32 = 1_000_000;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum State {
+	On,
+	Dark,
+	Asleep,
+}
+
+pub struct Power {
+	state: State,
+	/// When the frames went blank, while they're blank
+	blank_since: Option<u32>,
+	/// When the cube last woke up, until `WAKE_IGNORE_US` has passed
+	woken_at: Option<u32>,
+}
+
+impl Power {
+	pub fn new() -> Power {
+		Power {
+			state: State::On,
+			blank_since: None,
+			woken_at: None,
+		}
+	}
+
+	pub fn state(&self) -> State {
+		self.state
+	}
+
+	/// Whether the LEDs should have power
+	pub fn led_power(&self) -> bool {
+		self.state == State::On
+	}
+
+	pub fn is_asleep(&self) -> bool {
+		self.state == State::Asleep
+	}
+
+	/// Call with each frame before it's flushed, saying whether every voxel is off
+	pub fn frame(&mut self, blank: bool, now_us: u32) {
+		match (self.state, blank) {
+			(State::Asleep, _) => (),
+			(_, false) => {
+				self.state = State::On;
+				self.blank_since = None;
+			},
+			(State::On, true) => {
+				match self.blank_since {
+					Some(since) if now_us.wrapping_sub(since) >= BLANK_US => self.state = State::Dark,
+					Some(_) => (),
+					None => self.blank_since = Some(now_us),
+				}
+			},
+			(State::Dark, true) => (),
+		}
+	}
+
+	pub fn turn_off(&mut self) {
+		self.state = State::Asleep;
+		self.blank_since = None;
+	}
+
+	/// Call when a button press or UART byte wakes the processor
+	pub fn wake(&mut self, now_us: u32) {
+		if self.state == State::Asleep {
+			self.state = State::On;
+			self.woken_at = Some(now_us);
+		}
+	}
+
+	/// Whether the controls should be acted on. False for a little while after waking up.
+	pub fn accepts_actions(&mut self, now_us: u32) -> bool {
+		match self.woken_at {
+			Some(woken_at) if now_us.wrapping_sub(woken_at) < WAKE_IGNORE_US => false,
+			_ => {
+				self.woken_at = None;
+
+				true
+			},
+		}
+	}
+}
@@ -0,0 +1,157 @@
+//! Integer only 3D noise for generative patterns. The Cortex-M4F has an FPU, but fixed point keeps
+//! every voxel's sample to a handful of multiplies so a whole frame of noise fits easily in a 30ms frame.
+//!
+//! Noise functions take coordinates in 8.8 fixed point, where 256 is the distance between lattice
+//! points, and return values between roughly -256 and 256.
+
+use fixed;
+
+pub const NOISE_ONE: i32 = 1 << 8;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Noise {
+	/// Random values at lattice points, smoothly interpolated. Blobby, with visible grid alignment.
+	Value,
+	/// Ken Perlin's improved gradient noise
+	Perlin,
+	/// Simplex noise: smoother than Perlin with fewer directional artefacts
+	Simplex,
+}
+
+/// Sine of an angle in 256ths of a turn, scaled to +/- 127
+pub fn sine(angle: u8) -> i8 {
+	((fixed::sin((angle as u16) << 8).0 * 127 + 0x8000) >> 16) as i8
+}
+
+/// Scramble lattice coordinates into a pseudo random number
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+	let mut hash = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841) ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+
+	hash ^= hash >> 13;
+	hash = hash.wrapping_mul(0x5bd1_e995);
+	hash ^= hash >> 15;
+
+	hash
+}
+
+fn lerp(a: i32, b: i32, t: i32) -> i32 {
+	a + (((b - a) * t) >> 8)
+}
+
+/// 3t^2 - 2t^3, for `t` from 0 to 256
+fn smoothstep(t: i32) -> i32 {
+	(t * t * (3 * NOISE_ONE - 2 * t)) >> 16
+}
+
+/// Perlin's 6t^5 - 15t^4 + 10t^3, for `t` from 0 to 256
+fn quintic(t: i32) -> i32 {
+	let t3 = (t * t * t) >> 16;
+
+	(t3 * (((t * (6 * t - 15 * NOISE_ONE)) >> 8) + 10 * NOISE_ONE)) >> 8
+}
+
+/// Dot product of `(x, y, z)` with one of the 12 vectors pointing at the middles of a cube's edges,
+/// chosen by `hash`
+fn gradient(hash: u32, x: i32, y: i32, z: i32) -> i32 {
+	let h = hash & 15;
+	let u = if h < 8 { x } else { y };
+	let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+
+	(if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Split a coordinate into its lattice cell and the position within the cell
+fn cell(value: i32) -> (i32, i32) {
+	(value >> 8, value & (NOISE_ONE - 1))
+}
+
+pub fn value_noise(x: i32, y: i32, z: i32) -> i32 {
+	let ((xi, xf), (yi, yf), (zi, zf)) = (cell(x), cell(y), cell(z));
+	let (u, v, w) = (smoothstep(xf), smoothstep(yf), smoothstep(zf));
+
+	let corner = |dx: i32, dy: i32, dz: i32| (hash(xi + dx, yi + dy, zi + dz) & 511) as i32 - NOISE_ONE;
+
+	lerp(
+		lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), u), lerp(corner(0, 1, 0), corner(1, 1, 0), u), v),
+		lerp(lerp(corner(0, 0, 1), corner(1, 0, 1), u), lerp(corner(0, 1, 1), corner(1, 1, 1), u), v),
+		w
+	)
+}
+
+pub fn perlin(x: i32, y: i32, z: i32) -> i32 {
+	let ((xi, xf), (yi, yf), (zi, zf)) = (cell(x), cell(y), cell(z));
+	let (u, v, w) = (quintic(xf), quintic(yf), quintic(zf));
+
+	let corner = |dx: i32, dy: i32, dz: i32| {
+		gradient(hash(xi + dx, yi + dy, zi + dz), xf - dx * NOISE_ONE, yf - dy * NOISE_ONE, zf - dz * NOISE_ONE)
+	};
+
+	lerp(
+		lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), u), lerp(corner(0, 1, 0), corner(1, 1, 0), u), v),
+		lerp(lerp(corner(0, 0, 1), corner(1, 0, 1), u), lerp(corner(0, 1, 1), corner(1, 1, 1), u), v),
+		w
+	)
+}
+
+pub fn simplex(x: i32, y: i32, z: i32) -> i32 {
+	// 8 bits of fraction isn't enough for the falloff below, so work with 12
+	const ONE: i32 = 1 << 12;
+	const G3: i32 = ONE / 6;
+
+	let (x, y, z) = (x << 4, y << 4, z << 4);
+
+	// Skew the input space to find which simplex cell we're in
+	let s = (x + y + z) / 3;
+	let (i, j, k) = ((x + s) >> 12, (y + s) >> 12, (z + s) >> 12);
+
+	// Unskew the cell origin back and get the position relative to it
+	let t = (i + j + k) * G3;
+	let (x0, y0, z0) = (x - (i * ONE - t), y - (j * ONE - t), z - (k * ONE - t));
+
+	// Offsets of the second and third corners of the simplex
+	let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+		if y0 >= z0 {
+			((1, 0, 0), (1, 1, 0))
+		} else if x0 >= z0 {
+			((1, 0, 0), (1, 0, 1))
+		} else {
+			((0, 0, 1), (1, 0, 1))
+		}
+	} else if y0 < z0 {
+		((0, 0, 1), (0, 1, 1))
+	} else if x0 < z0 {
+		((0, 1, 0), (0, 1, 1))
+	} else {
+		((0, 1, 0), (1, 1, 0))
+	};
+
+	let corner = |di: i32, dj: i32, dk: i32, x: i32, y: i32, z: i32| {
+		let falloff = ONE * 6 / 10 - ((x * x + y * y + z * z) >> 12);
+
+		if falloff <= 0 {
+			return 0;
+		}
+
+		let falloff = (falloff * falloff) >> 12;
+		let falloff = (falloff * falloff) >> 12;
+
+		(falloff * gradient(hash(i + di, j + dj, k + dk), x, y, z)) >> 12
+	};
+
+	let n = corner(0, 0, 0, x0, y0, z0)
+		+ corner(i1, j1, k1, x0 - i1 * ONE + G3, y0 - j1 * ONE + G3, z0 - k1 * ONE + G3)
+		+ corner(i2, j2, k2, x0 - i2 * ONE + 2 * G3, y0 - j2 * ONE + 2 * G3, z0 - k2 * ONE + 2 * G3)
+		+ corner(1, 1, 1, x0 - ONE + 3 * G3, y0 - ONE + 3 * G3, z0 - ONE + 3 * G3);
+
+	// Scale to roughly +/- 1, then back down to 8 bits of fraction
+	(n * 32) >> 4
+}
+
+/// Sample the chosen kind of noise
+pub fn noise(kind: Noise, x: i32, y: i32, z: i32) -> i32 {
+	match kind {
+		Noise::Value => value_noise(x, y, z),
+		Noise::Perlin => perlin(x, y, z),
+		Noise::Simplex => simplex(x, y, z),
+	}
+}
@@ -0,0 +1,354 @@
+//! Parsers for the common LED matrix serial protocols, so PC software like Jinx!, Glediator and
+//! Prismatik can drive the cube without any custom tools.
+//!
+//! All of them treat the cube as a strip of 64 pixels in coordinate order (`x + 4 * y + 16 * z`),
+//! which `Cube4::set_frame` maps onto the physical LED chain. Frames with fewer than 64 pixels leave
+//! the rest off; extra pixels are ignored.
+
+use apa106led::{ Apa106Led, OFF };
+use stream::StreamParser;
+
+const NUM_PIXELS: usize = 64;
+
+/// Store byte number `position` of an RGB stream into `frame`, ignoring anything past the last pixel
+fn store_channel(frame: &mut [Apa106Led; 64], position: usize, byte: u8) {
+	let pixel = position / 3;
+
+	if pixel < NUM_PIXELS {
+		match position % 3 {
+			0 => frame[pixel].red = byte,
+			1 => frame[pixel].green = byte,
+			_ => frame[pixel].blue = byte,
+		}
+	}
+}
+
+const TPM2_START: u8 = 0xC9;
+const TPM2_DATA_FRAME: u8 = 0xDA;
+const TPM2_END: u8 = 0x36;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Tpm2State {
+	Start,
+	PacketType,
+	SizeHigh,
+	SizeLow,
+	Data,
+	End,
+}
+
+/// TPM2 serial protocol (`0xC9 0xDA <size: u16 big endian> <data> 0x36`). Only data frames are
+/// handled; command and response packets are ignored.
+pub struct Tpm2Parser {
+	state: Tpm2State,
+	size: usize,
+	position: usize,
+	frame: [Apa106Led; 64],
+}
+
+impl Tpm2Parser {
+	pub fn new() -> Tpm2Parser {
+		Tpm2Parser {
+			state: Tpm2State::Start,
+			size: 0,
+			position: 0,
+			frame: [OFF; 64],
+		}
+	}
+
+	pub fn push(&mut self, byte: u8) -> bool {
+		match self.state {
+			Tpm2State::Start => {
+				if byte == TPM2_START {
+					self.state = Tpm2State::PacketType;
+				}
+			},
+			Tpm2State::PacketType => {
+				self.state = if byte == TPM2_DATA_FRAME {
+					Tpm2State::SizeHigh
+				} else if byte == TPM2_START {
+					Tpm2State::PacketType
+				} else {
+					Tpm2State::Start
+				};
+			},
+			Tpm2State::SizeHigh => {
+				self.size = (byte as usize) << 8;
+				self.state = Tpm2State::SizeLow;
+			},
+			Tpm2State::SizeLow => {
+				self.size |= byte as usize;
+				self.position = 0;
+				self.frame = [OFF; 64];
+
+				self.state = if self.size == 0 { Tpm2State::End } else { Tpm2State::Data };
+			},
+			Tpm2State::Data => {
+				store_channel(&mut self.frame, self.position, byte);
+				self.position += 1;
+
+				if self.position == self.size {
+					self.state = Tpm2State::End;
+				}
+			},
+			Tpm2State::End => {
+				self.state = Tpm2State::Start;
+
+				return byte == TPM2_END;
+			},
+		}
+
+		false
+	}
+
+	/// True unless a data packet has started and hasn't ended yet
+	pub fn is_idle(&self) -> bool {
+		self.state == Tpm2State::Start || self.state == Tpm2State::PacketType
+	}
+
+	pub fn frame(&self) -> &[Apa106Led; 64] {
+		&self.frame
+	}
+}
+
+const ADA_MAGIC: [u8; 3] = [ b'A', b'd', b'a' ];
+
+#[derive(Copy, Clone, PartialEq)]
+enum AdaState {
+	Magic(usize),
+	CountHigh,
+	CountLow,
+	Checksum,
+	Data,
+}
+
+/// Adalight protocol as spoken by Prismatik and friends:
+/// `"Ada" <count - 1: u16 big endian> <checksum: high ^ low ^ 0x55> <count * RGB>`
+pub struct AdaParser {
+	state: AdaState,
+	count_high: u8,
+	count_low: u8,
+	length: usize,
+	position: usize,
+	frame: [Apa106Led; 64],
+}
+
+impl AdaParser {
+	pub fn new() -> AdaParser {
+		AdaParser {
+			state: AdaState::Magic(0),
+			count_high: 0,
+			count_low: 0,
+			length: 0,
+			position: 0,
+			frame: [OFF; 64],
+		}
+	}
+
+	pub fn push(&mut self, byte: u8) -> bool {
+		match self.state {
+			AdaState::Magic(matched) => {
+				self.state = if byte == ADA_MAGIC[matched] {
+					if matched + 1 == ADA_MAGIC.len() { AdaState::CountHigh } else { AdaState::Magic(matched + 1) }
+				} else if byte == ADA_MAGIC[0] {
+					AdaState::Magic(1)
+				} else {
+					AdaState::Magic(0)
+				};
+			},
+			AdaState::CountHigh => {
+				self.count_high = byte;
+				self.state = AdaState::CountLow;
+			},
+			AdaState::CountLow => {
+				self.count_low = byte;
+				self.state = AdaState::Checksum;
+			},
+			AdaState::Checksum => {
+				if byte == self.count_high ^ self.count_low ^ 0x55 {
+					self.length = (((self.count_high as usize) << 8 | self.count_low as usize) + 1) * 3;
+					self.position = 0;
+					self.frame = [OFF; 64];
+					self.state = AdaState::Data;
+				} else {
+					self.state = AdaState::Magic(0);
+				}
+			},
+			AdaState::Data => {
+				store_channel(&mut self.frame, self.position, byte);
+				self.position += 1;
+
+				if self.position == self.length {
+					self.state = AdaState::Magic(0);
+
+					return true;
+				}
+			},
+		}
+
+		false
+	}
+
+	/// True unless a header with a good checksum has been received and the pixel data after it
+	/// hasn't. Text like `Ada Lovelace` looks like the start of a header for a few bytes, so the
+	/// header itself doesn't count.
+	pub fn is_idle(&self) -> bool {
+		self.state != AdaState::Data
+	}
+
+	pub fn frame(&self) -> &[Apa106Led; 64] {
+		&self.frame
+	}
+}
+
+const GLEDIATOR_START: u8 = 0x01;
+
+/// Glediator/Jinx! raw frame mode: a `0x01` start byte followed by exactly 64 RGB triplets.
+///
+/// There's no checksum and `0x01` can turn up in other traffic on the UART, so frames are only
+/// accepted once two in a row have been correctly delimited by start bytes. The parser drops back
+/// out of sync as soon as a frame isn't followed by a start byte.
+pub struct GlediatorParser {
+	synced: bool,
+	in_frame: bool,
+	awaiting_start: bool,
+	position: usize,
+	frame: [Apa106Led; 64],
+}
+
+impl GlediatorParser {
+	pub fn new() -> GlediatorParser {
+		GlediatorParser {
+			synced: false,
+			in_frame: false,
+			awaiting_start: false,
+			position: 0,
+			frame: [OFF; 64],
+		}
+	}
+
+	pub fn push(&mut self, byte: u8) -> bool {
+		if self.awaiting_start {
+			self.awaiting_start = false;
+			self.synced = byte == GLEDIATOR_START;
+		}
+
+		if byte == GLEDIATOR_START {
+			if self.in_frame {
+				// Start byte in the middle of a frame means we've lost some data
+				self.synced = false;
+			}
+
+			self.in_frame = true;
+			self.position = 0;
+
+			return false;
+		}
+
+		if !self.in_frame {
+			return false;
+		}
+
+		store_channel(&mut self.frame, self.position, byte);
+		self.position += 1;
+
+		if self.position == NUM_PIXELS * 3 {
+			self.in_frame = false;
+			self.awaiting_start = true;
+
+			return self.synced;
+		}
+
+		false
+	}
+
+	/// True unless a start byte has been received and the 64 pixels after it haven't
+	pub fn is_idle(&self) -> bool {
+		!self.in_frame
+	}
+
+	pub fn frame(&self) -> &[Apa106Led; 64] {
+		&self.frame
+	}
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Protocol {
+	Stream,
+	Tpm2,
+	Ada,
+	Glediator,
+}
+
+/// Listens for all supported protocols at once and hands back the first complete frame from any of them
+pub struct FrameReceiver {
+	stream: StreamParser,
+	tpm2: Tpm2Parser,
+	ada: AdaParser,
+	glediator: GlediatorParser,
+
+	last: Protocol,
+}
+
+impl FrameReceiver {
+	pub fn new() -> FrameReceiver {
+		FrameReceiver {
+			stream: StreamParser::new(),
+			tpm2: Tpm2Parser::new(),
+			ada: AdaParser::new(),
+			glediator: GlediatorParser::new(),
+
+			last: Protocol::Stream,
+		}
+	}
+
+	/// Feed a received byte to every parser. Returns true if it completed a frame.
+	pub fn push(&mut self, byte: u8) -> bool {
+		let mut complete = None;
+
+		if self.stream.push(byte) {
+			complete = Some(Protocol::Stream);
+		}
+
+		if self.tpm2.push(byte) && complete.is_none() {
+			complete = Some(Protocol::Tpm2);
+		}
+
+		if self.ada.push(byte) && complete.is_none() {
+			complete = Some(Protocol::Ada);
+		}
+
+		if self.glediator.push(byte) && complete.is_none() {
+			complete = Some(Protocol::Glediator);
+		}
+
+		match complete {
+			Some(protocol) => {
+				self.last = protocol;
+
+				true
+			},
+			None => false
+		}
+	}
+
+	/// True unless some parser is partway through a frame, so a byte arriving now isn't frame data
+	pub fn is_idle(&self) -> bool {
+		self.stream.is_idle() && self.tpm2.is_idle() && self.ada.is_idle() && self.glediator.is_idle()
+	}
+
+	/// Protocol the most recent frame arrived over
+	pub fn protocol(&self) -> Protocol {
+		self.last
+	}
+
+	/// Most recently completed frame, in coordinate order
+	pub fn frame(&self) -> &[Apa106Led; 64] {
+		match self.last {
+			Protocol::Stream => self.stream.frame(),
+			Protocol::Tpm2 => self.tpm2.frame(),
+			Protocol::Ada => self.ada.frame(),
+			Protocol::Glediator => self.glediator.frame(),
+		}
+	}
+}
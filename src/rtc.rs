@@ -0,0 +1,62 @@
+//! The hibernation module's real time clock, counting seconds of local time as set over the console.
+//!
+//! Like the data `watchdog::Retained` keeps, the count survives resets but not being unplugged (unless
+//! VBAT is given a battery of its own). After power up it starts again from 0, which is before any
+//! time that can be set, so an unset clock is easy to spot.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+use clock::{ DateTime, EARLIEST_SET };
+use watchdog::Retained;
+
+const HIB_BASE: u32 = 0x400F_C000;
+
+const HIB_RTCC: *const u32 = (HIB_BASE + 0x000) as *const u32;
+const HIB_RTCLD: *mut u32 = (HIB_BASE + 0x00C) as *mut u32;
+const HIB_CTL: *mut u32 = (HIB_BASE + 0x010) as *mut u32;
+
+/// Set in HIBCTL when the hibernation module is ready for another write
+const CTL_WRC: u32 = 1 << 31;
+/// Starts the RTC counting
+const CTL_RTCEN: u32 = 1 << 0;
+
+unsafe fn hib_write(register: *mut u32, value: u32) {
+	while volatile_load(HIB_CTL) & CTL_WRC == 0 {}
+
+	volatile_store(register, value);
+}
+
+pub struct Rtc;
+
+impl Rtc {
+	/// Start the clock counting, if it isn't already from before a reset. The hibernation module has to be
+	/// turned on first, which creating `Retained` does.
+	pub fn start(_retained: &Retained) -> Rtc {
+		unsafe {
+			let control = volatile_load(HIB_CTL);
+
+			if control & CTL_RTCEN == 0 {
+				hib_write(HIB_CTL, control | CTL_RTCEN);
+			}
+		}
+
+		Rtc
+	}
+
+	/// The time, or `None` if it hasn't been set since power up
+	pub fn now(&self) -> Option<DateTime> {
+		let seconds = unsafe { volatile_load(HIB_RTCC) };
+
+		if seconds < EARLIEST_SET {
+			None
+		} else {
+			Some(DateTime::from_seconds(seconds))
+		}
+	}
+
+	pub fn set(&self, time: &DateTime) {
+		unsafe {
+			hib_write(HIB_RTCLD, time.to_seconds());
+		}
+	}
+}
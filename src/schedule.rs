@@ -0,0 +1,109 @@
+//! What the cube should do at different times of day and year: dim overnight, play only calm patterns
+//! while it's dim, and keep the christmas colours for December.
+//!
+//! `SCHEDULE` is a list of rules, each saying what to change between two times of day in some months.
+//! Every rule that covers the current time applies, with later rules winning where they disagree.
+//! Without the time (the clock hasn't been set) no rules apply and the cube runs as it always has.
+
+use clock::DateTime;
+use pattern_list::{ DAYLIGHT, FIRE, NOISE_FIELD, PLASMA, SMOOTH_BLENDER };
+
+/// Month masks for `Rule::months`, with bit 0 for January
+pub const ALL_YEAR: u16 = 0xfff;
+pub const DECEMBER: u16 = 1 << 11;
+
+/// Patterns played overnight, as a playlist
+const CALM_PATTERNS: u32 = 1 << PLASMA | 1 << NOISE_FIELD | 1 << FIRE | 1 << SMOOTH_BLENDER | 1 << DAYLIGHT;
+
+#[derive(Copy, Clone)]
+pub struct Rule {
+	/// Months the rule applies in
+	pub months: u16,
+	/// Minutes after midnight the rule starts and stops applying. If `end` is before `start` the rule
+	/// runs past midnight, and if they're the same it applies all day.
+	pub start: u16,
+	pub end: u16,
+	/// Brightness level, as an index into `settings::BRIGHTNESS_LEVELS`, to go no brighter than
+	pub brightness: Option<u8>,
+	/// Playlist to use in place of the one saved in the settings
+	pub playlist: Option<u32>,
+	/// Whether patterns should use christmas colours
+	pub christmas: Option<bool>,
+}
+
+pub const SCHEDULE: [Rule; 2] = [
+	// Dim and calm from half ten at night until seven in the morning
+	Rule {
+		months: ALL_YEAR,
+		start: 22 * 60 + 30,
+		end: 7 * 60,
+		brightness: Some(1),
+		playlist: Some(CALM_PATTERNS),
+		christmas: None,
+	},
+
+	// Christmas colours only in December
+	Rule {
+		months: ALL_YEAR & !DECEMBER,
+		start: 0,
+		end: 0,
+		brightness: None,
+		playlist: None,
+		christmas: Some(false),
+	},
+];
+
+/// What the schedule says for a particular time
+#[derive(Copy, Clone, PartialEq)]
+pub struct Plan {
+	pub brightness: Option<u8>,
+	pub playlist: Option<u32>,
+	pub christmas: bool,
+}
+
+impl Rule {
+	pub fn applies(&self, time: &DateTime) -> bool {
+		if self.months & 1 << (time.month - 1) == 0 {
+			return false;
+		}
+
+		let minute = time.minute_of_day();
+
+		if self.start <= self.end {
+			self.start == self.end || (minute >= self.start && minute < self.end)
+		} else {
+			minute >= self.start || minute < self.end
+		}
+	}
+}
+
+/// Combine every rule in `schedule` that applies at `time`
+pub fn plan(schedule: &[Rule], time: Option<DateTime>) -> Plan {
+	let mut plan = Plan { brightness: None, playlist: None, christmas: true };
+
+	let time = match time {
+		Some(time) => time,
+		None => return plan,
+	};
+
+	for rule in schedule.iter().filter(|rule| rule.applies(&time)) {
+		if let Some(brightness) = rule.brightness {
+			plan.brightness = Some(brightness);
+		}
+
+		if let Some(playlist) = rule.playlist {
+			plan.playlist = Some(playlist);
+		}
+
+		if let Some(christmas) = rule.christmas {
+			plan.christmas = christmas;
+		}
+	}
+
+	plan
+}
+
+/// Whether patterns should use christmas colours at `time`, going by `SCHEDULE`
+pub fn christmas(time: Option<DateTime>) -> bool {
+	plan(&SCHEDULE, time).christmas
+}
@@ -0,0 +1,54 @@
+use audio::BANDS;
+use beat::Beat;
+use clock::DateTime;
+use cube::Cube4;
+
+/// Frame rate `show_frame()` holds to, for patterns that move by elapsed time
+pub const TARGET_FPS: u32 = 60;
+pub const FRAME_US: u32 = 1_000_000 / TARGET_FPS;
+
+/// Patterns hand each finished frame to a `Scheduler`, which decides how to get it onto the
+/// hardware (or a host side simulator) and how long to hold it for.
+pub trait Scheduler {
+	/// Display the current contents of `cube` and return `hold_us` after the previous frame was handed
+	/// back, so the time spent drawing and flushing this one comes out of the hold
+	fn show_us(&mut self, cube: &mut Cube4, hold_us: u32);
+
+	/// Time between the last two frames being handed back, which is how far animations that move by
+	/// elapsed time should move for the next
+	fn delta_us(&mut self) -> u32;
+
+	/// `show_us()` in milliseconds
+	fn show(&mut self, cube: &mut Cube4, delay_ms: u32) {
+		self.show_us(cube, delay_ms * 1000);
+	}
+
+	/// `show_us()` at `TARGET_FPS`
+	fn show_frame(&mut self, cube: &mut Cube4) {
+		self.show_us(cube, FRAME_US);
+	}
+
+	/// The last key pressed since this was last called, for interactive patterns. Schedulers without
+	/// any input never have one.
+	fn key(&mut self) -> Option<u8> {
+		None
+	}
+
+	/// Energy in each band of the latest block of audio heard, from `audio::band_energy()`. Schedulers
+	/// without a microphone never have any.
+	fn bands(&mut self) -> Option<[u64; BANDS]> {
+		None
+	}
+
+	/// The beat clock, for patterns that keep time with music. Reading it clears the kick flag.
+	/// Schedulers without a microphone never have one.
+	fn beat(&mut self) -> Option<Beat> {
+		None
+	}
+
+	/// Local date and time, for patterns that follow the time of day. Schedulers without a clock, or
+	/// whose clock hasn't been set, never have one.
+	fn time(&mut self) -> Option<DateTime> {
+		None
+	}
+}
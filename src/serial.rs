@@ -0,0 +1,94 @@
+//! Non-blocking receive from UART0. Zinc's Tiva C UART only implements `CharIO` (transmit), so
+//! the receive side pokes the peripheral registers directly. The UART is still set up by the
+//! platformtree config in `main.rs`.
+//!
+//! There's a transmit side here too, for code that needs to reply to the PC but doesn't have the
+//! platformtree's UART handle.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+const UART0_BASE: u32 = 0x4000_C000;
+
+const UART_DR: *mut u32 = (UART0_BASE + 0x000) as *mut u32;
+const UART_RSR: *mut u32 = (UART0_BASE + 0x004) as *mut u32;
+const UART_FR: *const u32 = (UART0_BASE + 0x018) as *const u32;
+
+/// Receive FIFO empty flag in UARTFR
+const FR_RXFE: u32 = 1 << 4;
+
+/// Transmit FIFO full flag in UARTFR
+const FR_TXFF: u32 = 1 << 5;
+
+/// Framing, parity, break and overrun error flags in UARTDR
+const DR_ERRORS: u32 = 0xf00;
+
+/// The same flags in UARTRSR, where they stay set until cleared
+const RSR_ERRORS: u32 = 0xf;
+
+/// Pop a byte off the receive FIFO if one is waiting. Bytes received with errors are discarded.
+pub fn read_byte() -> Option<u8> {
+	unsafe {
+		while volatile_load(UART_FR) & FR_RXFE == 0 {
+			let data = volatile_load(UART_DR);
+
+			if data & DR_ERRORS == 0 {
+				return Some((data & 0xff) as u8);
+			}
+		}
+	}
+
+	None
+}
+
+/// Whether any bytes have been received with errors, or lost to the receive FIFO overflowing, since
+/// this was last called
+pub fn take_errors() -> bool {
+	unsafe {
+		let errors = volatile_load(UART_RSR) & RSR_ERRORS != 0;
+
+		// Any write clears the flags (the register is UARTECR when written)
+		if errors {
+			volatile_store(UART_RSR, 0);
+		}
+
+		errors
+	}
+}
+
+/// Send a byte, waiting for space in the transmit FIFO if it's full
+pub fn write_byte(byte: u8) {
+	unsafe {
+		while volatile_load(UART_FR) & FR_TXFF != 0 {}
+
+		volatile_store(UART_DR, byte as u32);
+	}
+}
+
+pub fn write_bytes(bytes: &[u8]) {
+	for &byte in bytes {
+		write_byte(byte);
+	}
+}
+
+pub fn write_str(string: &str) {
+	write_bytes(string.as_bytes());
+}
+
+/// Send `value` in decimal
+pub fn write_decimal(value: u32) {
+	let mut digits = [0u8; 10];
+	let mut count = 0;
+	let mut remaining = value;
+
+	loop {
+		digits[digits.len() - 1 - count] = b'0' + (remaining % 10) as u8;
+		count += 1;
+		remaining /= 10;
+
+		if remaining == 0 {
+			break;
+		}
+	}
+
+	write_bytes(&digits[digits.len() - count..]);
+}
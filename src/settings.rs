@@ -0,0 +1,320 @@
+//! Settings kept across power cycles: brightness, colour correction, which patterns to play and
+//! which one was playing last.
+//!
+//! Settings are saved as a small record of 32 bit words with a version and a checksum. Records go
+//! into `SLOTS` slots in turn, each with a sequence number, and loading picks the newest slot that
+//! checks out. That spreads the wear over the whole EEPROM, and if the power goes mid-write, the
+//! half-written record fails its checksum and the one before it is used instead. Words that already
+//! hold the right value aren't written again.
+//!
+//! Nothing here touches the hardware. The EEPROM is anything implementing `Eeprom`: the Tiva C's in
+//! `eeprom`, or an in-memory mock on the host.
+
+use console::{ Text, TEXT_LEN };
+use cube::{ Correction, NORMAL_BRIGHTNESS };
+use pattern_list::{ EVERY_PATTERN, PATTERN_COUNT };
+
+/// Brightness settings the controls step through, in 16ths. Each is about 1.4 times the one before, so
+/// the steps look even.
+pub const BRIGHTNESS_LEVELS: [u8; 9] = [ 4, 6, 8, 11, NORMAL_BRIGHTNESS, 23, 32, 45, 64 ];
+
+/// The level patterns are drawn for
+pub const DEFAULT_BRIGHTNESS: u8 = 4;
+
+/// Playlist with every pattern in it, including any added after it was saved
+pub const ALL_PATTERNS: u32 = 0xffff_ffff;
+
+/// Top 16 bits of a record's first word
+const MAGIC: u32 = 0x5E77_0000;
+
+/// Bottom 16 bits of a record's first word. Bump it whenever the layout of a record changes; records
+/// from other versions are ignored, so the settings go back to their defaults.
+const VERSION: u32 = 1;
+
+/// Words in a record: magic and version, sequence number, packed small settings, playlist, checksum
+pub const RECORD_WORDS: usize = 5;
+
+/// Records are written to each slot in turn. 32 slots of 16 words is all of the TM4C123's 2KB.
+pub const SLOTS: u32 = 32;
+
+/// Words from the start of one slot to the next, which puts each in its own EEPROM block
+pub const SLOT_WORDS: u32 = 16;
+
+/// Word addressed storage that keeps its contents without power
+pub trait Eeprom {
+	fn read(&self, address: u32) -> u32;
+	fn write(&mut self, address: u32, value: u32);
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Settings {
+	/// Index into `BRIGHTNESS_LEVELS`
+	pub brightness: u8,
+	pub correction: Correction,
+	/// Bit mask of patterns to play, bit 0 being the first. Patterns not in the show are ignored.
+	pub playlist: u32,
+	/// Pattern that was playing last, to carry on from at power on
+	pub last_pattern: u8,
+}
+
+impl Settings {
+	pub fn defaults() -> Settings {
+		Settings {
+			brightness: DEFAULT_BRIGHTNESS,
+			correction: Correction::None,
+			playlist: ALL_PATTERNS,
+			last_pattern: 0,
+		}
+	}
+
+	/// The words of a record holding these settings
+	pub fn encode(&self, sequence: u32) -> [u32; RECORD_WORDS] {
+		let correction = match self.correction {
+			Correction::None => 0,
+			Correction::Gamma => 1,
+		};
+
+		let mut record = [
+			MAGIC | VERSION,
+			sequence,
+			self.brightness as u32 | correction << 8 | (self.last_pattern as u32) << 16,
+			self.playlist,
+			0,
+		];
+
+		record[RECORD_WORDS - 1] = checksum(&record[..RECORD_WORDS - 1]);
+
+		record
+	}
+
+	/// Settings and sequence number from a record, if it's valid and from this version
+	pub fn decode(record: &[u32; RECORD_WORDS]) -> Option<(Settings, u32)> {
+		if record[0] != MAGIC | VERSION || record[RECORD_WORDS - 1] != checksum(&record[..RECORD_WORDS - 1]) {
+			return None;
+		}
+
+		let packed = record[2];
+		let brightness = (packed & 0xff) as u8;
+
+		let correction = match (packed >> 8) & 0xff {
+			0 => Correction::None,
+			1 => Correction::Gamma,
+			_ => return None,
+		};
+
+		if brightness as usize >= BRIGHTNESS_LEVELS.len() {
+			return None;
+		}
+
+		let settings = Settings {
+			brightness: brightness,
+			correction: correction,
+			playlist: record[3],
+			last_pattern: ((packed >> 16) & 0xff) as u8,
+		};
+
+		Some((settings, record[1]))
+	}
+}
+
+/// FNV-1a over whole words. Not cryptographic, but a torn write or flipped bit won't get past it.
+fn checksum(words: &[u32]) -> u32 {
+	let mut hash: u32 = 0x811C_9DC5;
+
+	for &word in words {
+		hash = (hash ^ word).wrapping_mul(0x0100_0193);
+	}
+
+	hash
+}
+
+/// Loads and saves `Settings` in an `Eeprom`
+pub struct Store<E: Eeprom> {
+	eeprom: E,
+	/// Slot and sequence number of the newest record
+	slot: u32,
+	sequence: u32,
+	/// What's in the newest record, so saving the same settings again writes nothing
+	saved: Settings,
+}
+
+impl<E: Eeprom> Store<E> {
+	/// Find the newest valid record. Without one, the defaults are used (and saved the first time
+	/// anything changes).
+	pub fn open(eeprom: E) -> Store<E> {
+		let mut store = Store {
+			eeprom: eeprom,
+			// So the first save goes in slot 0
+			slot: SLOTS - 1,
+			sequence: 0,
+			saved: Settings::defaults(),
+		};
+
+		let mut found = false;
+
+		for slot in 0..SLOTS {
+			if let Some((settings, sequence)) = Settings::decode(&store.read_record(slot)) {
+				if !found || sequence.wrapping_sub(store.sequence) < 0x8000_0000 {
+					store.slot = slot;
+					store.sequence = sequence;
+					store.saved = settings;
+
+					found = true;
+				}
+			}
+		}
+
+		store
+	}
+
+	fn read_record(&self, slot: u32) -> [u32; RECORD_WORDS] {
+		let mut record = [0; RECORD_WORDS];
+
+		for (i, word) in record.iter_mut().enumerate() {
+			*word = self.eeprom.read(slot * SLOT_WORDS + i as u32);
+		}
+
+		record
+	}
+
+	/// The settings in the newest record, or the defaults
+	pub fn settings(&self) -> Settings {
+		self.saved
+	}
+
+	/// Write `settings` to the next slot, unless they're what was last saved
+	pub fn save(&mut self, settings: &Settings) {
+		if *settings == self.saved {
+			return;
+		}
+
+		let slot = (self.slot + 1) % SLOTS;
+		let sequence = self.sequence.wrapping_add(1);
+		let record = settings.encode(sequence);
+
+		for (i, &word) in record.iter().enumerate() {
+			let address = slot * SLOT_WORDS + i as u32;
+
+			if self.eeprom.read(address) != word {
+				self.eeprom.write(address, word);
+			}
+		}
+
+		self.slot = slot;
+		self.sequence = sequence;
+		self.saved = *settings;
+	}
+
+	pub fn eeprom(&self) -> &E {
+		&self.eeprom
+	}
+
+	pub fn eeprom_mut(&mut self) -> &mut E {
+		&mut self.eeprom
+	}
+}
+
+/// Parse a playlist typed at the console: `all`, or pattern numbers counting from 1 separated by
+/// spaces or commas, with ranges like `3-7`
+pub fn parse_playlist(text: &[u8]) -> Option<u32> {
+	if text == b"all" {
+		return Some(ALL_PATTERNS);
+	}
+
+	let mut playlist = 0;
+
+	for item in text.split(|&byte| byte == b' ' || byte == b',').filter(|item| !item.is_empty()) {
+		let range = match item.iter().position(|&byte| byte == b'-') {
+			Some(dash) => (parse_number(&item[..dash]), parse_number(&item[dash + 1..])),
+			None => (parse_number(item), parse_number(item)),
+		};
+
+		let (first, last) = match range {
+			(Some(first), Some(last)) => (first, last),
+			_ => return None,
+		};
+
+		if first < 1 || first > last || last > PATTERN_COUNT as u32 {
+			return None;
+		}
+
+		for number in first..last + 1 {
+			playlist |= 1 << (number - 1);
+		}
+	}
+
+	if playlist == 0 { None } else { Some(playlist) }
+}
+
+/// Parse a number of one or two digits
+pub fn parse_number(text: &[u8]) -> Option<u32> {
+	if text.is_empty() || text.len() > 2 {
+		return None;
+	}
+
+	let mut number = 0;
+
+	for &byte in text {
+		match byte {
+			b'0'...b'9' => number = number * 10 + (byte - b'0') as u32,
+			_ => return None,
+		}
+	}
+
+	Some(number)
+}
+
+/// Write a playlist the way `parse_playlist` reads it, with runs of patterns as ranges. Bits past the
+/// last pattern are left out.
+pub fn describe_playlist(playlist: u32) -> Text {
+	if playlist & EVERY_PATTERN == EVERY_PATTERN {
+		return Text::new(b"all");
+	}
+
+	let mut bytes = [0u8; TEXT_LEN];
+	let mut len = 0;
+	let mut number = 0;
+
+	while number < PATTERN_COUNT as u32 {
+		if playlist & 1 << number == 0 {
+			number += 1;
+
+			continue;
+		}
+
+		let first = number;
+
+		while number < PATTERN_COUNT as u32 && playlist & 1 << number != 0 {
+			number += 1;
+		}
+
+		if len > 0 {
+			bytes[len] = b' ';
+			len += 1;
+		}
+
+		len += write_number(first + 1, &mut bytes[len..]);
+
+		if number - first > 1 {
+			bytes[len] = b'-';
+			len += 1;
+			len += write_number(number, &mut bytes[len..]);
+		}
+	}
+
+	Text::new(&bytes[..len])
+}
+
+/// Write a number from 1 to `PATTERN_COUNT` into `buffer`, returning how many digits it took
+fn write_number(number: u32, buffer: &mut [u8]) -> usize {
+	if number >= 10 {
+		buffer[0] = b'0' + (number / 10) as u8;
+		buffer[1] = b'0' + (number % 10) as u8;
+
+		2
+	} else {
+		buffer[0] = b'0' + number as u8;
+
+		1
+	}
+}
@@ -0,0 +1,85 @@
+//! Anti-aliased rendering of points that sit between voxels. A point's colour is spread over the 8
+//! voxels surrounding it using trilinear weights, so something moving slowly across the cube fades
+//! smoothly from one voxel to the next instead of jumping.
+//!
+//! Positions are 8.8 fixed point: `SUBVOXEL_ONE` is one voxel, and `(0, 0, 0)` is the centre of the
+//! voxel at the origin.
+
+use apa106led::Apa106Led;
+use cube::{ Cube4, Voxel, CUBE_SIZE };
+
+pub const SUBVOXEL_SHIFT: u8 = 8;
+pub const SUBVOXEL_ONE: i16 = 1 << SUBVOXEL_SHIFT;
+
+#[derive(Copy, Clone)]
+pub struct SubPoint {
+	pub x: i16,
+	pub y: i16,
+	pub z: i16,
+}
+
+impl SubPoint {
+	/// Point at the centre of a voxel
+	pub fn from_voxel(x: u8, y: u8, z: u8) -> SubPoint {
+		SubPoint {
+			x: x as i16 * SUBVOXEL_ONE,
+			y: y as i16 * SUBVOXEL_ONE,
+			z: z as i16 * SUBVOXEL_ONE,
+		}
+	}
+
+	/// Point `t / SUBVOXEL_ONE` of the way from `self` to `other`
+	pub fn lerp(&self, other: SubPoint, t: i16) -> SubPoint {
+		let lerp = |a: i16, b: i16| (a as i32 + (((b as i32 - a as i32) * t as i32) >> SUBVOXEL_SHIFT)) as i16;
+
+		SubPoint {
+			x: lerp(self.x, other.x),
+			y: lerp(self.y, other.y),
+			z: lerp(self.z, other.z),
+		}
+	}
+}
+
+fn scale_channel(channel: u8, weight: u32) -> u8 {
+	// Weight is 24 bit fixed point (three 8 bit fractions multiplied together). Round to nearest.
+	((channel as u32 * weight + (1 << 23)) >> 24) as u8
+}
+
+/// Draw a point with sub-voxel precision, adding its colour into the frame. The parts of the point
+/// that fall outside the cube are clipped.
+pub fn splat(cube: &mut Cube4, position: SubPoint, colour: Apa106Led) {
+	let one = SUBVOXEL_ONE as u32;
+
+	// Arithmetic shift rounds towards negative infinity, so this is floor() for negative positions too
+	let (base_x, base_y, base_z) = (position.x >> SUBVOXEL_SHIFT, position.y >> SUBVOXEL_SHIFT, position.z >> SUBVOXEL_SHIFT);
+	let mask = SUBVOXEL_ONE - 1;
+	let (frac_x, frac_y, frac_z) = ((position.x & mask) as u32, (position.y & mask) as u32, (position.z & mask) as u32);
+
+	for corner in 0..8 {
+		let (high_x, high_y, high_z) = (corner & 1 != 0, corner & 2 != 0, corner & 4 != 0);
+
+		let x = base_x + high_x as i16;
+		let y = base_y + high_y as i16;
+		let z = base_z + high_z as i16;
+
+		if x < 0 || y < 0 || z < 0 || x >= CUBE_SIZE as i16 || y >= CUBE_SIZE as i16 || z >= CUBE_SIZE as i16 {
+			continue;
+		}
+
+		let weight_x = if high_x { frac_x } else { one - frac_x };
+		let weight_y = if high_y { frac_y } else { one - frac_y };
+		let weight_z = if high_z { frac_z } else { one - frac_z };
+
+		let weight = weight_x * weight_y * weight_z;
+
+		if weight == 0 {
+			continue;
+		}
+
+		cube.add_at_coord(Voxel { x: x as u8, y: y as u8, z: z as u8 }, Apa106Led {
+			red: scale_channel(colour.red, weight),
+			green: scale_channel(colour.green, weight),
+			blue: scale_channel(colour.blue, weight),
+		});
+	}
+}
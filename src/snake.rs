@@ -0,0 +1,310 @@
+//! 3D snake. The snake moves one voxel per step in any of six directions; eating food makes it one
+//! voxel longer and scores a point, and running into a wall or itself ends the game.
+
+use apa106led::Apa106Led;
+use colour_functions::lerp_colour;
+use cube::{ Cube4, Voxel, CUBE_SIZE };
+use embedded_rand::rand_range;
+
+const MAX_LENGTH: usize = 64;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Direction {
+	/// +X
+	Right,
+	/// -X
+	Left,
+	/// +Y
+	Away,
+	/// -Y
+	Towards,
+	/// +Z
+	Up,
+	/// -Z
+	Down,
+}
+
+pub const DIRECTIONS: [Direction; 6] = [
+	Direction::Right, Direction::Left, Direction::Away, Direction::Towards, Direction::Up, Direction::Down,
+];
+
+impl Direction {
+	/// Map a key to a direction: WASD moves in the horizontal plane, R and F go up and down
+	pub fn from_key(key: u8) -> Option<Direction> {
+		match key {
+			b'd' | b'D' => Some(Direction::Right),
+			b'a' | b'A' => Some(Direction::Left),
+			b'w' | b'W' => Some(Direction::Away),
+			b's' | b'S' => Some(Direction::Towards),
+			b'r' | b'R' => Some(Direction::Up),
+			b'f' | b'F' => Some(Direction::Down),
+			_ => None,
+		}
+	}
+
+	pub fn opposite(&self) -> Direction {
+		match *self {
+			Direction::Right => Direction::Left,
+			Direction::Left => Direction::Right,
+			Direction::Away => Direction::Towards,
+			Direction::Towards => Direction::Away,
+			Direction::Up => Direction::Down,
+			Direction::Down => Direction::Up,
+		}
+	}
+
+	/// The voxel next to `from` in this direction, or `None` if that's outside the cube
+	pub fn step(&self, from: Voxel) -> Option<Voxel> {
+		let max = CUBE_SIZE - 1;
+
+		match *self {
+			Direction::Right if from.x < max => Some(Voxel { x: from.x + 1, ..from }),
+			Direction::Left if from.x > 0 => Some(Voxel { x: from.x - 1, ..from }),
+			Direction::Away if from.y < max => Some(Voxel { y: from.y + 1, ..from }),
+			Direction::Towards if from.y > 0 => Some(Voxel { y: from.y - 1, ..from }),
+			Direction::Up if from.z < max => Some(Voxel { z: from.z + 1, ..from }),
+			Direction::Down if from.z > 0 => Some(Voxel { z: from.z - 1, ..from }),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Step {
+	Moved,
+	Ate,
+	/// Hit a wall or itself
+	Crashed,
+	/// Filled the whole cube
+	Won,
+}
+
+fn index(voxel: Voxel) -> usize {
+	(voxel.x + voxel.y * CUBE_SIZE + voxel.z * CUBE_SIZE * CUBE_SIZE) as usize
+}
+
+pub struct Snake {
+	/// Ring buffer of body segments, `head` is the newest
+	body: [Voxel; MAX_LENGTH],
+	head: usize,
+	length: usize,
+	direction: Direction,
+	food: Voxel,
+	pub score: u16,
+}
+
+impl Snake {
+	/// A three voxel snake along the bottom front edge, heading right
+	pub fn new() -> Snake {
+		let mut snake = Snake {
+			body: [Voxel { x: 0, y: 0, z: 0 }; MAX_LENGTH],
+			head: 2,
+			length: 3,
+			direction: Direction::Right,
+			food: Voxel { x: 0, y: 0, z: 0 },
+			score: 0,
+		};
+
+		for x in 0..3 {
+			snake.body[x as usize] = Voxel { x: x, y: 0, z: 0 };
+		}
+
+		snake.place_food();
+
+		snake
+	}
+
+	pub fn head(&self) -> Voxel {
+		self.body[self.head]
+	}
+
+	/// Body segments from the head back to the tail
+	fn segment(&self, n: usize) -> Voxel {
+		self.body[(self.head + MAX_LENGTH - n) % MAX_LENGTH]
+	}
+
+	fn occupied(&self) -> [bool; 64] {
+		let mut occupied = [false; 64];
+
+		for n in 0..self.length {
+			occupied[index(self.segment(n))] = true;
+		}
+
+		occupied
+	}
+
+	fn place_food(&mut self) {
+		let occupied = self.occupied();
+		let free = occupied.iter().filter(|&&o| !o).count();
+
+		if free == 0 {
+			return;
+		}
+
+		let mut choice = rand_range(0, free as u32 - 1) as usize;
+
+		for z in 0..CUBE_SIZE {
+			for y in 0..CUBE_SIZE {
+				for x in 0..CUBE_SIZE {
+					let voxel = Voxel { x: x, y: y, z: z };
+
+					if occupied[index(voxel)] {
+						continue;
+					}
+
+					if choice == 0 {
+						self.food = voxel;
+
+						return;
+					}
+
+					choice -= 1;
+				}
+			}
+		}
+	}
+
+	/// Change direction for the next step. Doubling straight back on itself is ignored.
+	pub fn turn(&mut self, direction: Direction) {
+		if direction != self.direction.opposite() {
+			self.direction = direction;
+		}
+	}
+
+	/// True if moving the head in `direction` wouldn't crash. The tail moves out of the way unless the
+	/// snake is about to eat.
+	fn is_safe(&self, direction: Direction) -> bool {
+		match direction.step(self.head()) {
+			Some(next) => {
+				let tail_moves = next != self.food;
+				let body = if tail_moves { self.length - 1 } else { self.length };
+
+				(0..body).all(|n| self.segment(n) != next)
+			},
+			None => false,
+		}
+	}
+
+	/// Move one voxel in the current direction
+	pub fn step(&mut self) -> Step {
+		if !self.is_safe(self.direction) {
+			return Step::Crashed;
+		}
+
+		let next = match self.direction.step(self.head()) {
+			Some(next) => next,
+			None => return Step::Crashed,
+		};
+
+		let ate = next == self.food;
+
+		self.head = (self.head + 1) % MAX_LENGTH;
+		self.body[self.head] = next;
+
+		if !ate {
+			return Step::Moved;
+		}
+
+		self.length += 1;
+		self.score += 1;
+
+		if self.length == MAX_LENGTH {
+			return Step::Won;
+		}
+
+		self.place_food();
+
+		Step::Ate
+	}
+
+	/// Number of free voxels reachable from `start` if the snake's body is where it is now
+	fn reachable(&self, start: Voxel) -> usize {
+		let mut blocked = self.occupied();
+		let mut stack = [Voxel { x: 0, y: 0, z: 0 }; 64];
+		let mut top = 0;
+		let mut count = 0;
+
+		// The tail will have moved on by the time the head gets anywhere near it
+		blocked[index(self.segment(self.length - 1))] = false;
+
+		stack[top] = start;
+		top += 1;
+		blocked[index(start)] = true;
+
+		while top > 0 {
+			top -= 1;
+			count += 1;
+
+			let voxel = stack[top];
+
+			for direction in DIRECTIONS.iter() {
+				if let Some(next) = direction.step(voxel) {
+					if !blocked[index(next)] {
+						blocked[index(next)] = true;
+						stack[top] = next;
+						top += 1;
+					}
+				}
+			}
+		}
+
+		count
+	}
+
+	/// Pick a direction for the demo mode: head for the food, but never crash if there's a way not to
+	/// and avoid moves into pockets too small for the snake to fit in.
+	pub fn ai_direction(&self) -> Direction {
+		let head = self.head();
+		let distance = |voxel: Voxel| {
+			let axis = |a: u8, b: u8| (a as i8 - b as i8).abs() as u16;
+
+			axis(voxel.x, self.food.x) + axis(voxel.y, self.food.y) + axis(voxel.z, self.food.z)
+		};
+
+		// Keep going the same way (and crash) if nothing's safe
+		let mut best = self.direction;
+		let mut best_score = u16::max_value();
+
+		for &direction in DIRECTIONS.iter() {
+			if direction == self.direction.opposite() || !self.is_safe(direction) {
+				continue;
+			}
+
+			let next = match direction.step(head) {
+				Some(next) => next,
+				None => continue,
+			};
+
+			// Lower is better: getting trapped matters far more than distance to the food
+			let trapped = self.reachable(next) < self.length;
+			let score = (trapped as u16) * 100 + distance(next);
+
+			if score < best_score {
+				best = direction;
+				best_score = score;
+			}
+		}
+
+		best
+	}
+
+	/// Draw the snake fading from a bright head to a dim tail, and the food
+	pub fn render(&self, cube: &mut Cube4, head: Apa106Led, tail: Apa106Led, food: Apa106Led) {
+		cube.fill(Apa106Led { red: 0, green: 0, blue: 0 });
+
+		for n in 0..self.length {
+			let t = if self.length > 1 { (n * 255 / (self.length - 1)) as u8 } else { 0 };
+
+			cube.set_at_coord(self.segment(n), lerp_colour(head, tail, t));
+		}
+
+		cube.set_at_coord(self.food, food);
+	}
+
+	/// Every voxel the snake covers, so a game over animation can flash it
+	pub fn fill_body(&self, cube: &mut Cube4, colour: Apa106Led) {
+		for n in 0..self.length {
+			cube.set_at_coord(self.segment(n), colour);
+		}
+	}
+}
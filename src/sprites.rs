@@ -0,0 +1,212 @@
+//! Small voxel models that can be drawn into the cube at any offset. Sprites store one palette index
+//! per voxel, so they're cheap to keep in flash as `const` data. Palette entries that are `KEY` are
+//! transparent and leave whatever is already in the cube showing through.
+//!
+//! Voxels are listed in X, then Y, then Z order, with the *top* layer first. That way a flat sprite
+//! reads the right way up in the source.
+
+use apa106led::Apa106Led;
+use colour_functions::scale;
+use cube::Cube4;
+use draw::{ self, Point };
+
+/// Transparent key colour
+pub const KEY: Apa106Led = Apa106Led { red: 255, green: 0, blue: 255 };
+
+/// Tint that leaves a sprite's colours unchanged
+pub const NO_TINT: Apa106Led = Apa106Led { red: 255, green: 255, blue: 255 };
+
+pub struct Sprite {
+	/// Size along X
+	pub width: u8,
+	/// Size along Y
+	pub depth: u8,
+	/// Size along Z
+	pub height: u8,
+	pub palette: &'static [Apa106Led],
+	pub voxels: &'static [u8],
+}
+
+impl Sprite {
+	/// Palette index of a voxel, with `z` counting up from the bottom of the sprite
+	pub fn index_at(&self, x: u8, y: u8, z: u8) -> u8 {
+		let row = (self.height - 1 - z) as usize * self.depth as usize + y as usize;
+
+		self.voxels[row * self.width as usize + x as usize]
+	}
+}
+
+/// Draw a sprite with its bottom, front, left corner at `offset`. Every colour is multiplied by `tint`
+/// and scaled by `brightness` (both out of 255), and the parts outside the cube are clipped.
+pub fn blit(cube: &mut Cube4, sprite: &Sprite, offset: Point, tint: Apa106Led, brightness: u8) {
+	for z in 0..sprite.height {
+		for y in 0..sprite.depth {
+			for x in 0..sprite.width {
+				let colour = sprite.palette[sprite.index_at(x, y, z) as usize];
+
+				if colour == KEY {
+					continue;
+				}
+
+				let tinted = Apa106Led {
+					red: (colour.red as u16 * tint.red as u16 / 255) as u8,
+					green: (colour.green as u16 * tint.green as u16 / 255) as u8,
+					blue: (colour.blue as u16 * tint.blue as u16 / 255) as u8,
+				};
+
+				let point = Point {
+					x: offset.x.saturating_add(x as i8),
+					y: offset.y.saturating_add(y as i8),
+					z: offset.z.saturating_add(z as i8),
+				};
+
+				draw::plot(cube, point, scale(tinted, brightness));
+			}
+		}
+	}
+}
+
+const RED: Apa106Led = Apa106Led { red: 255, green: 0, blue: 0 };
+const PINK: Apa106Led = Apa106Led { red: 255, green: 60, blue: 90 };
+const WHITE: Apa106Led = Apa106Led { red: 255, green: 255, blue: 255 };
+const ICE: Apa106Led = Apa106Led { red: 90, green: 160, blue: 255 };
+
+/// Single colour palette for sprites that are meant to be tinted
+const MONO: [Apa106Led; 2] = [ KEY, WHITE ];
+
+pub const HEART: Sprite = Sprite {
+	width: 4,
+	depth: 1,
+	height: 4,
+	palette: &[ KEY, RED, PINK ],
+	voxels: &[
+		1, 0, 0, 1,
+		1, 2, 1, 1,
+		1, 1, 1, 1,
+		0, 1, 1, 0,
+	],
+};
+
+/// Arrow pointing up the Z axis, filling the whole cube
+pub const ARROW_UP: Sprite = Sprite {
+	width: 4,
+	depth: 4,
+	height: 4,
+	palette: &MONO,
+	voxels: &[
+		0, 0, 0, 0,
+		0, 1, 1, 0,
+		0, 1, 1, 0,
+		0, 0, 0, 0,
+
+		1, 1, 1, 1,
+		1, 1, 1, 1,
+		1, 1, 1, 1,
+		1, 1, 1, 1,
+
+		0, 0, 0, 0,
+		0, 1, 1, 0,
+		0, 1, 1, 0,
+		0, 0, 0, 0,
+
+		0, 0, 0, 0,
+		0, 1, 1, 0,
+		0, 1, 1, 0,
+		0, 0, 0, 0,
+	],
+};
+
+/// Flat arrow pointing along the X axis
+pub const ARROW_RIGHT: Sprite = Sprite {
+	width: 4,
+	depth: 1,
+	height: 4,
+	palette: &MONO,
+	voxels: &[
+		0, 0, 1, 0,
+		1, 1, 1, 1,
+		1, 1, 1, 1,
+		0, 0, 1, 0,
+	],
+};
+
+/// Three bars crossing in the middle of the cube with ice tipped corners
+pub const SNOWFLAKE: Sprite = Sprite {
+	width: 4,
+	depth: 4,
+	height: 4,
+	palette: &[ KEY, WHITE, ICE ],
+	voxels: &[
+		2, 0, 0, 2,
+		0, 1, 1, 0,
+		0, 1, 1, 0,
+		2, 0, 0, 2,
+
+		0, 1, 1, 0,
+		1, 1, 1, 1,
+		1, 1, 1, 1,
+		0, 1, 1, 0,
+
+		0, 1, 1, 0,
+		1, 1, 1, 1,
+		1, 1, 1, 1,
+		0, 1, 1, 0,
+
+		2, 0, 0, 2,
+		0, 1, 1, 0,
+		0, 1, 1, 0,
+		2, 0, 0, 2,
+	],
+};
+
+pub const LETTER_H: Sprite = Sprite {
+	width: 4,
+	depth: 1,
+	height: 4,
+	palette: &MONO,
+	voxels: &[
+		1, 0, 0, 1,
+		1, 1, 1, 1,
+		1, 0, 0, 1,
+		1, 0, 0, 1,
+	],
+};
+
+pub const LETTER_I: Sprite = Sprite {
+	width: 3,
+	depth: 1,
+	height: 4,
+	palette: &MONO,
+	voxels: &[
+		1, 1, 1,
+		0, 1, 0,
+		0, 1, 0,
+		1, 1, 1,
+	],
+};
+
+pub const LETTER_O: Sprite = Sprite {
+	width: 4,
+	depth: 1,
+	height: 4,
+	palette: &MONO,
+	voxels: &[
+		0, 1, 1, 0,
+		1, 0, 0, 1,
+		1, 0, 0, 1,
+		0, 1, 1, 0,
+	],
+};
+
+pub const LETTER_X: Sprite = Sprite {
+	width: 4,
+	depth: 1,
+	height: 4,
+	palette: &MONO,
+	voxels: &[
+		1, 0, 0, 1,
+		0, 1, 1, 0,
+		0, 1, 1, 0,
+		1, 0, 0, 1,
+	],
+};
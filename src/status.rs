@@ -0,0 +1,130 @@
+//! What the LaunchPad's RGB LED shows. Only the red and blue parts (PF1 and PF2) are used.
+//!
+//! * A short blue blink every second while patterns run, so it's obvious the firmware hasn't hung.
+//! * Every `CODE_REPEAT_US`, and whenever a pattern starts, the number of the pattern (counting from 1)
+//!   blinked out in blue: long blinks for tens then short blinks for units, with 0 as ten short blinks.
+//! * Red and blue flashing in turn while frames are streamed from a PC.
+//! * Solid red for a couple of seconds after a UART receive error.
+//!
+//! Like `input`, nothing here touches the hardware. The scheduler asks for the lights as often as it
+//! likes and sets the pins to match, so the blinking never holds anything up.
+
+pub const LONG_BLINK_US: u32 = 600_000;
+pub const SHORT_BLINK_US: u32 = 150_000;
+/// Between blinks of a code
+pub const BLINK_GAP_US: u32 = 300_000;
+/// Extra gap between the tens and the units
+pub const DIGIT_GAP_US: u32 = 600_000;
+
+/// How often the pattern number is blinked out
+pub const CODE_REPEAT_US: u32 = 10_000_000;
+
+const HEARTBEAT_PERIOD_US: u32 = 1_000_000;
+pub const HEARTBEAT_US: u32 = 50_000;
+
+/// Time each colour is on for while streaming
+const STREAM_FLASH_US: u32 = 125_000;
+
+/// How long red stays on after an error
+pub const ERROR_US: u32 = 2_000_000;
+
+/// Which parts of the LED should be on
+#[derive(Copy, Clone, PartialEq)]
+pub struct Lights {
+	pub red: bool,
+	pub blue: bool,
+}
+
+const DARK: Lights = Lights { red: false, blue: false };
+const BLUE: Lights = Lights { red: false, blue: true };
+const RED: Lights = Lights { red: true, blue: false };
+
+/// Tracks the state of the firmware and sequences blinks to show it. Times are counts of the 1us
+/// timer, so everything works across it wrapping.
+pub struct Status {
+	/// Pattern number to blink out, counting from 1
+	code: u8,
+	/// When the pattern started
+	code_start: u32,
+
+	streaming: bool,
+
+	/// Time of the latest error, if there's been one recently
+	error_at: Option<u32>,
+}
+
+impl Status {
+	pub fn new(now: u32) -> Status {
+		Status {
+			code: 1,
+			code_start: now,
+
+			streaming: false,
+
+			error_at: None,
+		}
+	}
+
+	/// A new pattern has started. `index` counts from 0, but is blinked out counting from 1.
+	pub fn start_pattern(&mut self, index: u8, now: u32) {
+		self.code = index + 1;
+		self.code_start = now;
+	}
+
+	pub fn set_streaming(&mut self, streaming: bool) {
+		self.streaming = streaming;
+	}
+
+	/// Something went wrong that's worth showing, but not bad enough to stop for
+	pub fn error(&mut self, now: u32) {
+		self.error_at = Some(now);
+	}
+
+	/// What the LED should show at `now`
+	pub fn lights(&mut self, now: u32) -> Lights {
+		if let Some(error_at) = self.error_at {
+			if now.wrapping_sub(error_at) < ERROR_US {
+				return RED;
+			}
+
+			self.error_at = None;
+		}
+
+		if self.streaming {
+			return if now / STREAM_FLASH_US % 2 == 0 { RED } else { BLUE };
+		}
+
+		let position = now.wrapping_sub(self.code_start) % CODE_REPEAT_US;
+		let (tens, units) = (self.code / 10, self.code % 10);
+		let units = if units == 0 { 10 } else { units };
+
+		let mut remaining = position;
+
+		for &(count, on_us, gap_us) in [ (tens, LONG_BLINK_US, DIGIT_GAP_US), (units, SHORT_BLINK_US, 0) ].iter() {
+			for _ in 0..count {
+				if remaining < on_us {
+					return BLUE;
+				}
+
+				remaining -= on_us;
+
+				if remaining < BLINK_GAP_US {
+					return DARK;
+				}
+
+				remaining -= BLINK_GAP_US;
+			}
+
+			if count > 0 {
+				if remaining < gap_us {
+					return DARK;
+				}
+
+				remaining -= gap_us;
+			}
+		}
+
+		// Heartbeat for the rest of the time, starting a little after the code so they don't run together
+		if remaining % HEARTBEAT_PERIOD_US >= HEARTBEAT_PERIOD_US - HEARTBEAT_US { BLUE } else { DARK }
+	}
+}
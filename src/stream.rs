@@ -0,0 +1,174 @@
+//! Binary frame streaming protocol used to push frames to the cube from a PC over the UART.
+//!
+//! A packet looks like this, all multibyte fields little endian:
+//!
+//! ```text
+//! | 0x5A 0xA5 | length: u16 | payload: length bytes | crc: u16 |
+//! ```
+//!
+//! The payload is always 64 RGB triplets (192 bytes) in coordinate order, that is voxel `(x, y, z)`
+//! lives at `x + 4 * y + 16 * z`. The CRC is CRC-16/CCITT-FALSE over the length and payload bytes.
+
+use apa106led::{ Apa106Led, OFF };
+
+pub const SYNC: [u8; 2] = [ 0x5A, 0xA5 ];
+
+pub const PAYLOAD_LEN: usize = 64 * 3;
+
+/// Sync + length + payload + CRC
+pub const PACKET_LEN: usize = 2 + 2 + PAYLOAD_LEN + 2;
+
+/// Frames are displayed until no new frame has been received for this long, then the playlist resumes
+pub const STREAM_TIMEOUT_MS: u32 = 2000;
+
+pub fn crc16(crc: u16, byte: u8) -> u16 {
+	let mut crc = crc ^ ((byte as u16) << 8);
+
+	for _ in 0..8 {
+		crc = if crc & 0x8000 != 0 {
+			(crc << 1) ^ 0x1021
+		} else {
+			crc << 1
+		};
+	}
+
+	crc
+}
+
+/// Encode a coordinate ordered frame into a packet ready to send down the wire
+pub fn encode_packet(frame: &[Apa106Led; 64], packet: &mut [u8; PACKET_LEN]) {
+	packet[0] = SYNC[0];
+	packet[1] = SYNC[1];
+	packet[2] = (PAYLOAD_LEN & 0xff) as u8;
+	packet[3] = (PAYLOAD_LEN >> 8) as u8;
+
+	for (index, voxel) in frame.iter().enumerate() {
+		packet[4 + index * 3] = voxel.red;
+		packet[4 + index * 3 + 1] = voxel.green;
+		packet[4 + index * 3 + 2] = voxel.blue;
+	}
+
+	let mut crc = 0xffff;
+
+	for byte in packet[2..4 + PAYLOAD_LEN].iter() {
+		crc = crc16(crc, *byte);
+	}
+
+	packet[4 + PAYLOAD_LEN] = (crc & 0xff) as u8;
+	packet[5 + PAYLOAD_LEN] = (crc >> 8) as u8;
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum State {
+	Sync0,
+	Sync1,
+	LengthLow,
+	LengthHigh,
+	Payload,
+	CrcLow,
+	CrcHigh,
+}
+
+/// Byte-at-a-time packet parser. Bad lengths and CRC failures silently drop the packet and hunt for
+/// the next sync sequence.
+pub struct StreamParser {
+	state: State,
+	length: u16,
+	position: usize,
+	crc: u16,
+	received_crc: u16,
+	payload: [u8; PAYLOAD_LEN],
+	frame: [Apa106Led; 64],
+}
+
+impl StreamParser {
+	pub fn new() -> StreamParser {
+		StreamParser {
+			state: State::Sync0,
+			length: 0,
+			position: 0,
+			crc: 0xffff,
+			received_crc: 0,
+			payload: [0; PAYLOAD_LEN],
+			frame: [OFF; 64],
+		}
+	}
+
+	/// Feed a received byte into the parser. Returns true when a complete, valid frame has been
+	/// received; it can then be read with `frame()`.
+	pub fn push(&mut self, byte: u8) -> bool {
+		match self.state {
+			State::Sync0 => {
+				if byte == SYNC[0] {
+					self.state = State::Sync1;
+				}
+			},
+			State::Sync1 => {
+				self.state = if byte == SYNC[1] {
+					State::LengthLow
+				} else if byte == SYNC[0] {
+					State::Sync1
+				} else {
+					State::Sync0
+				};
+			},
+			State::LengthLow => {
+				self.crc = crc16(0xffff, byte);
+				self.length = byte as u16;
+				self.state = State::LengthHigh;
+			},
+			State::LengthHigh => {
+				self.crc = crc16(self.crc, byte);
+				self.length |= (byte as u16) << 8;
+				self.position = 0;
+
+				self.state = if self.length as usize == PAYLOAD_LEN {
+					State::Payload
+				} else {
+					State::Sync0
+				};
+			},
+			State::Payload => {
+				self.crc = crc16(self.crc, byte);
+				self.payload[self.position] = byte;
+				self.position += 1;
+
+				if self.position == PAYLOAD_LEN {
+					self.state = State::CrcLow;
+				}
+			},
+			State::CrcLow => {
+				self.received_crc = byte as u16;
+				self.state = State::CrcHigh;
+			},
+			State::CrcHigh => {
+				self.received_crc |= (byte as u16) << 8;
+				self.state = State::Sync0;
+
+				if self.received_crc == self.crc {
+					for index in 0..64 {
+						self.frame[index] = Apa106Led {
+							red: self.payload[index * 3],
+							green: self.payload[index * 3 + 1],
+							blue: self.payload[index * 3 + 2],
+						};
+					}
+
+					return true;
+				}
+			},
+		}
+
+		false
+	}
+
+	/// True unless a packet header has been received and the rest of the packet hasn't
+	pub fn is_idle(&self) -> bool {
+		self.state == State::Sync0 || self.state == State::Sync1
+	}
+
+	/// Last complete frame, in coordinate order
+	pub fn frame(&self) -> &[Apa106Led; 64] {
+		&self.frame
+	}
+}
@@ -29,7 +29,8 @@ pub const GAMMA_MAP: [u8; 256] = [
 	242, 245, 247, 250, 252, 255
 ];
 
-// 500K – 8000K in 20K steps
+// Black body colours from 500K to 16000K in 20K steps, from the same fit as `temp_to_rgb()`.
+// `colour_functions::blackbody()` looks them up.
 pub const BLACKBODY_MAP: [Apa106Led; 776] = [
 	Apa106Led { red: 255, green: 0, blue: 0 },
 	Apa106Led { red: 255, green: 2, blue: 0 },
@@ -807,4 +808,20 @@ pub const BLACKBODY_MAP: [Apa106Led; 776] = [
 	Apa106Led { red: 178, green: 203, blue: 255 },
 	Apa106Led { red: 178, green: 203, blue: 255 },
 	Apa106Led { red: 178, green: 203, blue: 255 },
-];
\ No newline at end of file
+];
+
+// The first quarter of a sine wave in Q16.16, 128 steps plus the end point. `fixed::sin()` interpolates
+// between them.
+pub const QUARTER_SINE_TABLE: [i32; 129] = [
+	0, 804, 1608, 2412, 3216, 4019, 4821, 5623, 6424, 7224, 8022, 8820,
+	9616, 10411, 11204, 11996, 12785, 13573, 14359, 15143, 15924, 16703, 17479, 18253,
+	19024, 19792, 20557, 21320, 22078, 22834, 23586, 24335, 25080, 25821, 26558, 27291,
+	28020, 28745, 29466, 30182, 30893, 31600, 32303, 33000, 33692, 34380, 35062, 35738,
+	36410, 37076, 37736, 38391, 39040, 39683, 40320, 40951, 41576, 42194, 42806, 43412,
+	44011, 44604, 45190, 45769, 46341, 46906, 47464, 48015, 48559, 49095, 49624, 50146,
+	50660, 51166, 51665, 52156, 52639, 53114, 53581, 54040, 54491, 54934, 55368, 55794,
+	56212, 56621, 57022, 57414, 57798, 58172, 58538, 58896, 59244, 59583, 59914, 60235,
+	60547, 60851, 61145, 61429, 61705, 61971, 62228, 62476, 62714, 62943, 63162, 63372,
+	63572, 63763, 63944, 64115, 64277, 64429, 64571, 64704, 64827, 64940, 65043, 65137,
+	65220, 65294, 65358, 65413, 65457, 65492, 65516, 65531, 65536
+];
@@ -0,0 +1,571 @@
+use zinc::hal::pin::Gpio;
+use zinc::hal::timer::Timer;
+use zinc::hal::tiva_c::pin::Pin;
+use zinc::hal::tiva_c::timer::{ Timer as TivaCTimer };
+
+use adc::Microphone;
+use audio::{ Listener, BANDS };
+use beat::Beat;
+use clock::{ self, DateTime };
+use console::{ self, Console, Text };
+use controls::Controls;
+use apa106led::OFF;
+use cube::{ Correction, Cube4 };
+use deep_sleep;
+use eeprom::TivaEeprom;
+use frame_timing::FrameTiming;
+use input::Action;
+use power::{ Power, LED_POWER_UP_MS };
+use protocols::FrameReceiver;
+use rtc::Rtc;
+use schedule::{ self, Plan, SCHEDULE };
+use scheduler::Scheduler;
+use serial;
+use settings::{ self, Settings, Store, BRIGHTNESS_LEVELS };
+use status::{ Lights, Status };
+use stream::STREAM_TIMEOUT_MS;
+use watchdog::{ Retained, Watchdog };
+
+/// Longest a hold goes without feeding the watchdog, well inside `WATCHDOG_TIMEOUT_MS`
+const FEED_INTERVAL_US: u32 = 500_000;
+
+/// Runs patterns on the real hardware. While each frame is held it keeps up with the UART, microphone,
+/// controls and status LED, switching to frames streamed from a PC when they arrive, and it looks after
+/// the LED power, the watchdog and saving the settings.
+pub struct TivaScheduler<'a> {
+	timer: &'a TivaCTimer,
+
+	receiver: FrameReceiver,
+	console: Console,
+
+	/// Marquee text set over the console
+	message: Option<Text>,
+	/// Last byte received, for `key()`
+	key: Option<u8>,
+
+	microphone: Microphone<'a>,
+	listener: Listener,
+	/// Timer count when the last block of audio was finished
+	last_block: u32,
+
+	controls: Controls<'a>,
+	/// Fast forwarding to the end of the current pattern
+	skipping: bool,
+
+	status: Status,
+	status_red: &'a Pin,
+	status_blue: &'a Pin,
+	/// What the LED was last set to
+	lights: Option<Lights>,
+
+	watchdog: Watchdog,
+	retained: Retained,
+
+	frame_timing: FrameTiming,
+
+	power: Power,
+	led_power: &'a Pin,
+	/// What the LED power pin was last set to
+	led_powered: bool,
+
+	settings: Settings,
+	store: Store<TivaEeprom>,
+
+	rtc: Rtc,
+}
+
+impl<'a> TivaScheduler<'a> {
+	pub fn new(timer: &'a TivaCTimer, status_red: &'a Pin, status_blue: &'a Pin, led_power: &'a Pin, watchdog: Watchdog, retained: Retained, store: Store<TivaEeprom>, rtc: Rtc) -> TivaScheduler<'a> {
+		TivaScheduler {
+			timer: timer,
+
+			receiver: FrameReceiver::new(),
+			console: Console::new(),
+
+			message: None,
+			key: None,
+
+			microphone: Microphone::new(timer),
+			listener: Listener::new(),
+			last_block: timer.get_counter(),
+
+			controls: Controls::new(timer),
+			skipping: false,
+
+			status: Status::new(timer.get_counter()),
+			status_red: status_red,
+			status_blue: status_blue,
+			lights: None,
+
+			watchdog: watchdog,
+			retained: retained,
+
+			frame_timing: FrameTiming::new(),
+
+			power: Power::new(),
+			led_power: led_power,
+			// `run()` turns it on before the first flush
+			led_powered: true,
+
+			settings: store.settings(),
+			store: store,
+
+			rtc: rtc,
+		}
+	}
+
+	pub fn settings(&self) -> Settings {
+		self.settings
+	}
+
+	/// What the schedule says for now. Once the clock has been set, `schedule::SCHEDULE` can dim the cube
+	/// below the brightness setting and swap in a different playlist at some times of day.
+	fn plan(&self) -> Plan {
+		schedule::plan(&SCHEDULE, self.rtc.now())
+	}
+
+	/// Patterns to play: the playlist setting, unless the schedule has one of its own for now
+	pub fn playlist(&self) -> u32 {
+		self.plan().playlist.unwrap_or(self.settings.playlist)
+	}
+
+	/// Call before starting each pattern, to stop fast forwarding if the last one was skipped, to show
+	/// `index` on the status LED, to save the settings and to blame `index` for any reset from now on.
+	///
+	/// Settings changed with the controls are only saved here, so a burst of presses is one write.
+	pub fn start_pattern(&mut self, index: u8) {
+		self.skipping = false;
+		self.frame_timing.reset();
+
+		self.status.start_pattern(index, self.timer.get_counter());
+
+		self.settings.last_pattern = index;
+		self.save();
+	}
+
+	/// Save the settings, which can take a while if the EEPROM has to be erased. No pattern is kept in
+	/// `Retained` meanwhile, so if the watchdog runs out the pattern doesn't get the blame.
+	fn save(&mut self) {
+		self.retained.clear_pattern();
+		self.store.save(&self.settings);
+		self.retained.set_pattern(self.settings.last_pattern);
+	}
+
+	fn update_status(&mut self) {
+		let lights = self.status.lights(self.timer.get_counter());
+
+		if self.lights == Some(lights) {
+			return;
+		}
+
+		if lights.red { self.status_red.set_high() } else { self.status_red.set_low() }
+		if lights.blue { self.status_blue.set_high() } else { self.status_blue.set_low() }
+
+		self.lights = Some(lights);
+	}
+
+	/// Text set with the `text` command, if there's been one
+	pub fn message(&self) -> Option<Text> {
+		self.message
+	}
+
+	/// Run a console command:
+	///
+	/// * `text <message>` sets the text scrolled by the marquee
+	/// * `text` prints the current text
+	/// * `settings` prints the saved settings
+	/// * `brightness <0-8>` sets the brightness, 4 being what the patterns are drawn for
+	/// * `correction <none|gamma>` sets the colour correction
+	/// * `playlist <patterns>` sets which patterns play, e.g. `1 3 5-9` or `all`
+	/// * `defaults` puts all the settings back to their defaults
+	/// * `time` prints the date and time
+	/// * `time <YYYY-MM-DD HH:MM[:SS]>` sets the date and time
+	/// * `stats` prints frame timings since they were last printed
+	/// * `off` turns the cube off until a button is pressed or anything else is sent
+	fn handle_command(&mut self, line: Text) {
+		match console::split_command(line.as_bytes()) {
+			(b"text", b"") => {
+				match self.message {
+					Some(message) => serial::write_bytes(message.as_bytes()),
+					None => serial::write_str("(default)"),
+				}
+
+				serial::write_str("\r\n");
+			},
+			(b"text", message) => {
+				self.message = Some(Text::new(message));
+
+				serial::write_str("ok\r\n");
+			},
+			(b"settings", b"") => self.print_settings(),
+			(b"brightness", level) => {
+				match settings::parse_number(level) {
+					Some(level) if (level as usize) < BRIGHTNESS_LEVELS.len() => {
+						self.settings.brightness = level as u8;
+						self.save_settings();
+					},
+					_ => serial::write_str("expected a brightness from 0 to 8\r\n"),
+				}
+			},
+			(b"correction", correction) => {
+				let correction = match correction {
+					b"none" => Some(Correction::None),
+					b"gamma" => Some(Correction::Gamma),
+					_ => None,
+				};
+
+				match correction {
+					Some(correction) => {
+						self.settings.correction = correction;
+						self.save_settings();
+					},
+					None => serial::write_str("expected none or gamma\r\n"),
+				}
+			},
+			(b"playlist", patterns) => {
+				match settings::parse_playlist(patterns) {
+					Some(playlist) => {
+						self.settings.playlist = playlist;
+						self.save_settings();
+					},
+					None => serial::write_str("expected pattern numbers like 1 3 5-9, or all\r\n"),
+				}
+			},
+			(b"defaults", b"") => {
+				self.settings = Settings::defaults();
+				self.save_settings();
+			},
+			(b"time", b"") => {
+				match self.rtc.now() {
+					Some(time) => serial::write_bytes(time.format().as_bytes()),
+					None => serial::write_str("not set"),
+				}
+
+				serial::write_str("\r\n");
+			},
+			(b"time", time) => {
+				match clock::parse(time) {
+					Some(time) => {
+						self.rtc.set(&time);
+
+						serial::write_str("ok\r\n");
+					},
+					None => serial::write_str("expected a time like 2024-12-24 18:30\r\n"),
+				}
+			},
+			(b"stats", b"") => self.print_stats(),
+			(b"off", b"") => {
+				self.power.turn_off();
+
+				serial::write_str("ok\r\n");
+			},
+			_ => serial::write_str("unknown command\r\n"),
+		}
+	}
+
+	/// Save settings changed over the console straight away, rather than when the next pattern starts
+	fn save_settings(&mut self) {
+		self.save();
+
+		serial::write_str("ok\r\n");
+	}
+
+	fn print_settings(&self) {
+		serial::write_str("brightness ");
+		serial::write_decimal(self.settings.brightness as u32);
+
+		serial::write_str("\r\ncorrection ");
+		serial::write_str(match self.settings.correction {
+			Correction::None => "none",
+			Correction::Gamma => "gamma",
+		});
+
+		serial::write_str("\r\nplaylist ");
+		serial::write_bytes(settings::describe_playlist(self.settings.playlist).as_bytes());
+
+		serial::write_str("\r\nlast pattern ");
+		serial::write_decimal(self.settings.last_pattern as u32 + 1);
+		serial::write_str("\r\n");
+	}
+
+	/// Print how long frames have been taking to draw and flush; see `frame_timing`
+	fn print_stats(&mut self) {
+		let stats = self.frame_timing.take_stats();
+
+		serial::write_str("frames ");
+		serial::write_decimal(stats.frames);
+		serial::write_str(", dropped ");
+		serial::write_decimal(stats.dropped);
+
+		serial::write_str("\r\nrender average ");
+		serial::write_decimal(stats.render_average_us());
+		serial::write_str("us, max ");
+		serial::write_decimal(stats.render_max_us);
+
+		serial::write_str("us\r\nflush average ");
+		serial::write_decimal(stats.flush_average_us());
+		serial::write_str("us, max ");
+		serial::write_decimal(stats.flush_max_us);
+		serial::write_str("us\r\n");
+	}
+
+	/// Act on the buttons and encoder; see `input::Inputs` for what they do. Moving on to the next pattern
+	/// fast forwards through the rest of the current one: `show()` returns straight away without flushing
+	/// until `start_pattern()` is called for the next.
+	fn handle_action(&mut self, action: Action) {
+		// The press that woke the cube does nothing else
+		if !self.power.accepts_actions(self.timer.get_counter()) {
+			return;
+		}
+
+		match action {
+			Action::NextPattern => self.skipping = true,
+			Action::BrightnessUp => {
+				if (self.settings.brightness as usize) < BRIGHTNESS_LEVELS.len() - 1 {
+					self.settings.brightness += 1;
+				}
+			},
+			Action::BrightnessDown => {
+				if self.settings.brightness > 0 {
+					self.settings.brightness -= 1;
+				}
+			},
+			Action::DefaultBrightness => self.settings.brightness = settings::DEFAULT_BRIGHTNESS,
+			Action::Power => self.power.turn_off(),
+		}
+	}
+
+	/// Poll the UART for up to `timeout_us`. Returns true as soon as a complete frame is received in any
+	/// of the protocols `FrameReceiver` understands, and false early if the controls ask to skip the
+	/// pattern or the cube is turned off.
+	///
+	/// Bytes that aren't part of a frame go to the console; see `handle_command()`. The controls, the
+	/// microphone and the status LED are polled in the same loop, with each block of audio analysed for
+	/// `bands()` and `beat()` as soon as it's complete.
+	///
+	/// Everything is polled at least once even with no time to wait, so a pattern whose frames are always
+	/// late still keeps up with the UART and controls. The watchdog isn't fed; see `hold()`.
+	fn wait_for_frame(&mut self, timeout_us: u32) -> bool {
+		let start = self.timer.get_counter();
+
+		loop {
+			if let Some(action) = self.controls.poll() {
+				self.handle_action(action);
+
+				if self.skipping || self.power.is_asleep() {
+					return false;
+				}
+			}
+
+			if let Some(block) = self.microphone.poll() {
+				let now = self.timer.get_counter();
+
+				self.listener.listen(block, now.wrapping_sub(self.last_block));
+				self.last_block = now;
+			}
+
+			while let Some(byte) = serial::read_byte() {
+				// Frame data, from the byte that starts a frame to the one that completes it, isn't typing
+				let idle = self.receiver.is_idle();
+				let complete = self.receiver.push(byte);
+
+				if complete || !idle || !self.receiver.is_idle() {
+					self.console.discard();
+
+					if complete {
+						return true;
+					}
+
+					continue;
+				}
+
+				self.key = Some(byte);
+
+				if let Some(line) = self.console.push(byte) {
+					self.handle_command(line);
+
+					if self.power.is_asleep() {
+						return false;
+					}
+				}
+			}
+
+			if serial::take_errors() {
+				self.status.error(self.timer.get_counter());
+			}
+
+			self.update_status();
+
+			if self.timer.get_counter().wrapping_sub(start) >= timeout_us {
+				return false;
+			}
+		}
+	}
+
+	/// `wait_for_frame()` for as long as a pattern's hold or the stream timeout, feeding the watchdog every
+	/// `FEED_INTERVAL_US` so that a long wait isn't taken for a hang. Otherwise it's only fed once each
+	/// frame has been flushed, so a pattern that never finishes a frame, or takes longer than the timeout
+	/// to fast forward through skipped ones, lets it run out.
+	fn hold(&mut self, timeout_us: u32) -> bool {
+		let mut left_us = timeout_us;
+
+		loop {
+			let wait_us = if left_us < FEED_INTERVAL_US { left_us } else { FEED_INTERVAL_US };
+
+			if self.wait_for_frame(wait_us) {
+				return true;
+			}
+
+			left_us -= wait_us;
+
+			if left_us == 0 || self.skipping || self.power.is_asleep() {
+				return false;
+			}
+
+			self.watchdog.feed();
+		}
+	}
+
+	fn apply_settings(&self, cube: &mut Cube4) {
+		let brightness = match self.plan().brightness {
+			Some(most) if most < self.settings.brightness => most,
+			_ => self.settings.brightness,
+		};
+
+		cube.set_brightness(BRIGHTNESS_LEVELS[brightness as usize]);
+		cube.set_correction(self.settings.correction);
+	}
+
+	fn update_led_power(&mut self) {
+		let powered = self.power.led_power();
+
+		if powered == self.led_powered {
+			return;
+		}
+
+		if powered {
+			self.led_power.set_high();
+			self.timer.wait_ms(LED_POWER_UP_MS);
+		} else {
+			self.led_power.set_low();
+		}
+
+		self.led_powered = powered;
+	}
+
+	/// Flush `cube` with the settings applied, powering the LEDs up or down first. They're powered down
+	/// while patterns show nothing for a while (see `power`), and nothing is sent then, as they'd only be
+	/// showing a blank frame anyway.
+	///
+	/// The UART has no interrupt driven buffering, so bytes that arrive while the SPI is being flushed
+	/// can overflow the 16 byte receive FIFO. Senders should leave a gap between packets (30FPS is fine).
+	fn flush(&mut self, cube: &mut Cube4) {
+		let blank = cube.get_frame().iter().all(|&voxel| voxel == OFF);
+
+		self.power.frame(blank, self.timer.get_counter());
+		self.update_led_power();
+
+		if self.led_powered {
+			self.apply_settings(cube);
+			cube.flush();
+		}
+	}
+
+	/// Blank the cube, power the LEDs down and sleep until a button press or UART byte wakes it, after
+	/// the controls or the `off` command turn it off. The watchdog's clock stops while the processor is
+	/// asleep, and no pattern is kept in `Retained` in case a reset comes before it's fed again.
+	fn sleep(&mut self, cube: &mut Cube4) {
+		self.retained.clear_pattern();
+
+		if self.led_powered {
+			cube.set_brightness(0);
+			cube.flush();
+		}
+
+		self.update_led_power();
+
+		self.status_red.set_low();
+		self.status_blue.set_low();
+		self.lights = None;
+
+		deep_sleep::sleep_until_woken();
+
+		self.watchdog.feed();
+		self.power.wake(self.timer.get_counter());
+
+		self.retained.set_pattern(self.settings.last_pattern);
+	}
+
+	/// Show streamed frames until the PC goes quiet for `STREAM_TIMEOUT_MS`, after which the interrupted
+	/// pattern carries on. The watchdog is fed with each frame, and no pattern is kept in `Retained`, as
+	/// a reset now wouldn't be the pattern's fault.
+	fn stream(&mut self, cube: &mut Cube4) {
+		self.retained.clear_pattern();
+		self.status.set_streaming(true);
+
+		loop {
+			cube.set_frame(self.receiver.frame());
+			self.flush(cube);
+			self.watchdog.feed();
+
+			if !self.hold(STREAM_TIMEOUT_MS * 1000) || self.power.is_asleep() {
+				break;
+			}
+		}
+
+		self.status.set_streaming(false);
+		self.retained.set_pattern(self.settings.last_pattern);
+
+		// Anything typed while streaming was frame data
+		self.key = None;
+	}
+}
+
+impl<'a> Scheduler for TivaScheduler<'a> {
+	/// Flush the frame and hold it until `hold_us` has passed since the previous one was handed back, so
+	/// drawing and flushing come out of the hold rather than adding to it; see `frame_timing`. Streaming
+	/// and sleeping happen in here too, taking over until they're done.
+	fn show_us(&mut self, cube: &mut Cube4, hold_us: u32) {
+		if self.skipping {
+			return;
+		}
+
+		self.frame_timing.start_flush(self.timer.get_counter());
+		self.flush(cube);
+		self.watchdog.feed();
+
+		let wait_us = self.frame_timing.end_flush(self.timer.get_counter(), hold_us);
+
+		if self.hold(wait_us) {
+			self.stream(cube);
+			self.frame_timing.reset();
+		}
+
+		if self.power.is_asleep() {
+			self.sleep(cube);
+			self.frame_timing.reset();
+		}
+
+		self.frame_timing.end_hold(self.timer.get_counter());
+	}
+
+	fn delta_us(&mut self) -> u32 {
+		self.frame_timing.delta_us()
+	}
+
+	fn key(&mut self) -> Option<u8> {
+		self.key.take()
+	}
+
+	fn bands(&mut self) -> Option<[u64; BANDS]> {
+		Some(self.listener.energy)
+	}
+
+	fn beat(&mut self) -> Option<Beat> {
+		Some(self.listener.tracker.beat())
+	}
+
+	fn time(&mut self) -> Option<DateTime> {
+		self.rtc.now()
+	}
+}
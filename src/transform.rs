@@ -0,0 +1,183 @@
+//! Geometric transforms of the whole frame: quarter turn rotations, mirroring, shifting and
+//! arbitrary rotations with resampling.
+
+use apa106led::Apa106Led;
+use cube::{ Cube4, Voxel, CUBE_SIZE };
+use fixed::{ self, Fixed16, FIXED16_ONE };
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Axis {
+	X,
+	Y,
+	Z,
+}
+
+/// What to do with voxels shifted in from outside the cube
+#[derive(Copy, Clone)]
+pub enum Edge {
+	/// Voxels that fall off one side come back in on the other
+	Wrap,
+	/// New voxels are filled with this colour
+	Fill(Apa106Led),
+}
+
+const MAX: i8 = CUBE_SIZE as i8 - 1;
+
+/// Rebuild the frame by asking `source_of` where each destination voxel's colour comes from.
+/// Sources outside the cube are filled with `fill`.
+fn remap<F>(cube: &mut Cube4, source_of: F, fill: Apa106Led) where F: Fn(i8, i8, i8) -> (i8, i8, i8) {
+	let frame = cube.get_frame();
+
+	for z in 0..CUBE_SIZE as i8 {
+		for y in 0..CUBE_SIZE as i8 {
+			for x in 0..CUBE_SIZE as i8 {
+				let (sx, sy, sz) = source_of(x, y, z);
+
+				let colour = if sx < 0 || sy < 0 || sz < 0 || sx > MAX || sy > MAX || sz > MAX {
+					fill
+				} else {
+					frame[(sx + sy * 4 + sz * 16) as usize]
+				};
+
+				cube.set_at_coord(Voxel { x: x as u8, y: y as u8, z: z as u8 }, colour);
+			}
+		}
+	}
+}
+
+/// Rotate the frame a quarter turn anticlockwise `turns` times, looking down `axis` from its positive end
+pub fn rotate_90(cube: &mut Cube4, axis: Axis, turns: u8) {
+	let off = Apa106Led { red: 0, green: 0, blue: 0 };
+
+	for _ in 0..turns % 4 {
+		match axis {
+			Axis::X => remap(cube, |x, y, z| (x, z, MAX - y), off),
+			Axis::Y => remap(cube, |x, y, z| (MAX - z, y, x), off),
+			Axis::Z => remap(cube, |x, y, z| (y, MAX - x, z), off),
+		}
+	}
+}
+
+/// Mirror the frame along `axis`, e.g. `Axis::Z` swaps the top and bottom layers
+pub fn mirror(cube: &mut Cube4, axis: Axis) {
+	let off = Apa106Led { red: 0, green: 0, blue: 0 };
+
+	match axis {
+		Axis::X => remap(cube, |x, y, z| (MAX - x, y, z), off),
+		Axis::Y => remap(cube, |x, y, z| (x, MAX - y, z), off),
+		Axis::Z => remap(cube, |x, y, z| (x, y, MAX - z), off),
+	}
+}
+
+/// Move the frame `amount` voxels along `axis`. Negative amounts move towards zero.
+pub fn shift(cube: &mut Cube4, axis: Axis, amount: i8, edge: Edge) {
+	let size = CUBE_SIZE as i16;
+
+	// In i16, as `value - amount` doesn't fit in an i8 when `amount` is big
+	let source = |value: i8| {
+		let moved = value as i16 - amount as i16;
+
+		match edge {
+			Edge::Wrap => ((moved % size + size) % size) as i8,
+			// However far outside the cube it is, it's filled
+			Edge::Fill(_) => if moved < 0 || moved >= size { -1 } else { moved as i8 },
+		}
+	};
+
+	let fill = match edge {
+		Edge::Wrap => Apa106Led { red: 0, green: 0, blue: 0 },
+		Edge::Fill(colour) => colour,
+	};
+
+	match axis {
+		Axis::X => remap(cube, |x, y, z| (source(x), y, z), fill),
+		Axis::Y => remap(cube, |x, y, z| (x, source(y), z), fill),
+		Axis::Z => remap(cube, |x, y, z| (x, y, source(z)), fill),
+	}
+}
+
+/// Apply a linear transform about the centre of the cube, resampling with trilinear interpolation.
+///
+/// `matrix` maps each destination voxel (relative to the centre of the cube) back to the position it
+/// should be sampled from, i.e. it's the inverse of the transform you want to see. Anything sampled
+/// from outside the cube is `background`.
+pub fn transform(cube: &mut Cube4, matrix: &[[Fixed16; 3]; 3], background: Apa106Led) {
+	let frame = cube.get_frame();
+	let centre = Fixed16::from_ratio(CUBE_SIZE as i32 - 1, 2);
+
+	let sample = |x: i8, y: i8, z: i8| -> [Fixed16; 3] {
+		let colour = if x < 0 || y < 0 || z < 0 || x > MAX || y > MAX || z > MAX {
+			background
+		} else {
+			frame[(x + y * 4 + z * 16) as usize]
+		};
+
+		[ Fixed16::from_int(colour.red as i32), Fixed16::from_int(colour.green as i32), Fixed16::from_int(colour.blue as i32) ]
+	};
+
+	let channel = |value: Fixed16| {
+		let rounded = value.round();
+
+		if rounded < 0 { 0 } else if rounded > 255 { 255 } else { rounded as u8 }
+	};
+
+	for z in 0..CUBE_SIZE {
+		for y in 0..CUBE_SIZE {
+			for x in 0..CUBE_SIZE {
+				let dx = Fixed16::from_int(x as i32) - centre;
+				let dy = Fixed16::from_int(y as i32) - centre;
+				let dz = Fixed16::from_int(z as i32) - centre;
+
+				let sx = matrix[0][0] * dx + matrix[0][1] * dy + matrix[0][2] * dz + centre;
+				let sy = matrix[1][0] * dx + matrix[1][1] * dy + matrix[1][2] * dz + centre;
+				let sz = matrix[2][0] * dx + matrix[2][1] * dy + matrix[2][2] * dz + centre;
+
+				// Keep well inside i8 so sampling far outside the cube can't overflow
+				let (bx, by, bz) = (sx.floor(), sy.floor(), sz.floor());
+
+				if bx < -2 || by < -2 || bz < -2 || bx > 4 || by > 4 || bz > 4 {
+					cube.set_at_coord(Voxel { x: x, y: y, z: z }, background);
+
+					continue;
+				}
+
+				let (tx, ty, tz) = (sx - Fixed16::from_int(bx), sy - Fixed16::from_int(by), sz - Fixed16::from_int(bz));
+				let (bx, by, bz) = (bx as i8, by as i8, bz as i8);
+
+				let mut result = [Fixed16(0); 3];
+
+				for (index, value) in result.iter_mut().enumerate() {
+					let c00 = Fixed16::lerp(sample(bx, by, bz)[index], sample(bx + 1, by, bz)[index], tx);
+					let c10 = Fixed16::lerp(sample(bx, by + 1, bz)[index], sample(bx + 1, by + 1, bz)[index], tx);
+					let c01 = Fixed16::lerp(sample(bx, by, bz + 1)[index], sample(bx + 1, by, bz + 1)[index], tx);
+					let c11 = Fixed16::lerp(sample(bx, by + 1, bz + 1)[index], sample(bx + 1, by + 1, bz + 1)[index], tx);
+
+					*value = Fixed16::lerp(Fixed16::lerp(c00, c10, ty), Fixed16::lerp(c01, c11, ty), tz);
+				}
+
+				cube.set_at_coord(Voxel { x: x, y: y, z: z }, Apa106Led {
+					red: channel(result[0]),
+					green: channel(result[1]),
+					blue: channel(result[2]),
+				});
+			}
+		}
+	}
+}
+
+/// Rotate the frame by any angle about an axis through the centre of the cube. `angle` is in
+/// 65536ths of a turn like `fixed::sin()`'s, anticlockwise looking down the axis from its positive
+/// end like `rotate_90`.
+pub fn rotate(cube: &mut Cube4, axis: Axis, angle: u16, background: Apa106Led) {
+	// Sample from the opposite rotation
+	let (sin, cos) = (-fixed::sin(angle), fixed::cos(angle));
+	let (zero, one) = (Fixed16(0), Fixed16(FIXED16_ONE));
+
+	let matrix = match axis {
+		Axis::X => [ [ one, zero, zero ], [ zero, cos, -sin ], [ zero, sin, cos ] ],
+		Axis::Y => [ [ cos, zero, sin ], [ zero, one, zero ], [ -sin, zero, cos ] ],
+		Axis::Z => [ [ cos, -sin, zero ], [ sin, cos, zero ], [ zero, zero, one ] ],
+	};
+
+	transform(cube, &matrix, background);
+}
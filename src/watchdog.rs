@@ -0,0 +1,214 @@
+//! Watchdog supervision, and working out why the last reset happened.
+//!
+//! Watchdog 0 counts down on the system clock. The first time it runs out it only raises its interrupt
+//! flag; if it runs out again before the flag's been cleared, it resets the chip. Feeding it clears
+//! the flag and reloads it, so it's loaded with half the timeout.
+//!
+//! Normal RAM is cleared at startup, so which pattern was running is kept in the hibernation module's
+//! data registers instead. They're powered from VBAT, which the LaunchPad ties to 3.3V, so they
+//! survive any reset but not being unplugged.
+
+use core::intrinsics::{ volatile_load, volatile_store };
+
+const SYSCTL_RESC: *mut u32 = 0x400F_E05C as *mut u32;
+const SYSCTL_RCGCWD: *mut u32 = 0x400F_E600 as *mut u32;
+const SYSCTL_RCGCHIB: *mut u32 = 0x400F_E614 as *mut u32;
+
+const WDT0_BASE: u32 = 0x4000_0000;
+
+const WDT_LOAD: *mut u32 = (WDT0_BASE + 0x000) as *mut u32;
+const WDT_CTL: *mut u32 = (WDT0_BASE + 0x008) as *mut u32;
+const WDT_ICR: *mut u32 = (WDT0_BASE + 0x00C) as *mut u32;
+const WDT_TEST: *mut u32 = (WDT0_BASE + 0x418) as *mut u32;
+
+/// Interrupt (which starts the counter) and reset enable bits in WDTCTL
+const CTL_INTEN: u32 = 1 << 0;
+const CTL_RESEN: u32 = 1 << 1;
+
+/// Stops the watchdog while a debugger has the processor halted
+const TEST_STALL: u32 = 1 << 8;
+
+const HIB_BASE: u32 = 0x400F_C000;
+
+const HIB_CTL: *mut u32 = (HIB_BASE + 0x010) as *mut u32;
+const HIB_DATA: u32 = HIB_BASE + 0x030;
+
+/// Set in HIBCTL when the hibernation module is ready for another write
+const CTL_WRC: u32 = 1 << 31;
+/// Turns on the hibernation module's 32kHz oscillator, which it needs for writes
+const CTL_CLK32EN: u32 = 1 << 6;
+
+/// Cause bits in RESC
+const RESC_EXT: u32 = 1 << 0;
+const RESC_POR: u32 = 1 << 1;
+const RESC_BOR: u32 = 1 << 2;
+const RESC_WDT0: u32 = 1 << 3;
+const RESC_SW: u32 = 1 << 4;
+
+const SYSTEM_CLOCK_HZ: u32 = 80_000_000;
+
+/// Marks the hibernation data as ours. After power up it's whatever the registers came up as.
+const MAGIC: u32 = 0xC0BE_0001;
+
+/// Word offsets into the hibernation data
+const WORD_MAGIC: u32 = 0;
+const WORD_PATTERN: u32 = 1;
+const WORD_HUNG: u32 = 2;
+
+/// Stored in place of a pattern index before any pattern has started
+const NO_PATTERN: u32 = 0xff;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ResetCause {
+	PowerOn,
+	/// The reset button
+	External,
+	BrownOut,
+	Watchdog,
+	/// A reset requested by the firmware, like the panic handler's
+	Software,
+	Unknown,
+}
+
+impl ResetCause {
+	pub fn name(&self) -> &'static str {
+		match *self {
+			ResetCause::PowerOn => "power on",
+			ResetCause::External => "reset button",
+			ResetCause::BrownOut => "brown out",
+			ResetCause::Watchdog => "watchdog",
+			ResetCause::Software => "software",
+			ResetCause::Unknown => "unknown",
+		}
+	}
+}
+
+/// Why the chip last reset. Clears the record, so only call it once.
+pub fn reset_cause() -> ResetCause {
+	unsafe {
+		let causes = volatile_load(SYSCTL_RESC);
+
+		volatile_store(SYSCTL_RESC, 0);
+
+		// Power on also sets the external reset bit, so check in order of importance
+		if causes & RESC_WDT0 != 0 {
+			ResetCause::Watchdog
+		} else if causes & RESC_SW != 0 {
+			ResetCause::Software
+		} else if causes & RESC_BOR != 0 {
+			ResetCause::BrownOut
+		} else if causes & RESC_POR != 0 {
+			ResetCause::PowerOn
+		} else if causes & RESC_EXT != 0 {
+			ResetCause::External
+		} else {
+			ResetCause::Unknown
+		}
+	}
+}
+
+pub struct Watchdog;
+
+impl Watchdog {
+	/// Start the watchdog. The chip resets if it isn't fed for `timeout_ms`, which can be up to 100
+	/// seconds.
+	pub fn start(timeout_ms: u32) -> Watchdog {
+		unsafe {
+			volatile_store(SYSCTL_RCGCWD, volatile_load(SYSCTL_RCGCWD) | 1);
+
+			// Give the clock a few cycles to come up
+			volatile_load(SYSCTL_RCGCWD);
+			volatile_load(SYSCTL_RCGCWD);
+
+			volatile_store(WDT_LOAD, timeout_ms / 2 * (SYSTEM_CLOCK_HZ / 1000));
+			volatile_store(WDT_TEST, volatile_load(WDT_TEST) | TEST_STALL);
+			volatile_store(WDT_CTL, CTL_RESEN | CTL_INTEN);
+		}
+
+		Watchdog
+	}
+
+	pub fn feed(&self) {
+		unsafe {
+			volatile_store(WDT_ICR, 1);
+		}
+	}
+}
+
+unsafe fn hib_write(register: *mut u32, value: u32) {
+	while volatile_load(HIB_CTL) & CTL_WRC == 0 {}
+
+	volatile_store(register, value);
+}
+
+fn hib_word(word: u32) -> *mut u32 {
+	(HIB_DATA + word * 4) as *mut u32
+}
+
+/// The few things worth remembering across a reset, kept in the hibernation module
+pub struct Retained;
+
+impl Retained {
+	pub fn new() -> Retained {
+		unsafe {
+			volatile_store(SYSCTL_RCGCHIB, volatile_load(SYSCTL_RCGCHIB) | 1);
+
+			// It may already be running from before the reset
+			let control = volatile_load(HIB_CTL);
+
+			if control & CTL_CLK32EN == 0 {
+				hib_write(HIB_CTL, control | CTL_CLK32EN);
+			}
+		}
+
+		let retained = Retained;
+
+		if retained.read(WORD_MAGIC) != MAGIC {
+			retained.write(WORD_PATTERN, NO_PATTERN);
+			retained.write(WORD_HUNG, 0);
+			retained.write(WORD_MAGIC, MAGIC);
+		}
+
+		retained
+	}
+
+	fn read(&self, word: u32) -> u32 {
+		unsafe { volatile_load(hib_word(word)) }
+	}
+
+	fn write(&self, word: u32, value: u32) {
+		unsafe { hib_write(hib_word(word), value) }
+	}
+
+	/// The pattern that was running when the chip reset
+	pub fn pattern(&self) -> Option<u8> {
+		match self.read(WORD_PATTERN) {
+			NO_PATTERN => None,
+			index => Some(index as u8),
+		}
+	}
+
+	pub fn set_pattern(&self, index: u8) {
+		self.write(WORD_PATTERN, index as u32);
+	}
+
+	/// For while the firmware is doing something of its own, so a reset then isn't blamed on a pattern
+	pub fn clear_pattern(&self) {
+		self.write(WORD_PATTERN, NO_PATTERN);
+	}
+
+	/// Bit mask of patterns that have hung since power on
+	pub fn hung_patterns(&self) -> u32 {
+		self.read(WORD_HUNG)
+	}
+
+	pub fn mark_hung(&self, index: u8) {
+		let hung = self.hung_patterns() | 1 << index;
+
+		self.write(WORD_HUNG, hung);
+	}
+
+	pub fn clear_hung(&self) {
+		self.write(WORD_HUNG, 0);
+	}
+}